@@ -0,0 +1,182 @@
+// Client side encryption of gated Link3 uris.
+//
+// The owner encrypts a link's uri with a random content key and stores only the
+// ciphertext on the hub. Each buyer registers an X25519 public key, and once they
+// have access the owner (or a key service acting for them) posts the content key
+// wrapped for that buyer. The buyer unwraps it with their secret key and decrypts
+// the uri locally, so the plaintext never reaches contract state.
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use rand_core::{OsRng, RngCore};
+use sha2::Sha256;
+pub use x25519_dalek::{PublicKey, StaticSecret};
+use x25519_dalek::SharedSecret;
+
+pub const KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+const ENVELOPE_INFO: &[u8] = b"link3 key envelope";
+
+pub type ContentKey = [u8; KEY_LENGTH];
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+  // The bytes are too short to hold what they claim to
+  Malformed,
+  // Wrong key, or the bytes were tampered with
+  Decryption,
+  // The decrypted uri is not valid UTF-8
+  InvalidUri,
+}
+
+// New secret key of a buyer, the public half is registered on the hub
+pub fn generate_secret() -> StaticSecret {
+  StaticSecret::random_from_rng(OsRng)
+}
+
+pub fn generate_content_key() -> ContentKey {
+  let mut content_key = [0u8; KEY_LENGTH];
+  OsRng.fill_bytes(&mut content_key);
+  content_key
+}
+
+// Nonce followed by the ciphertext, what `seal_link` expects
+pub fn encrypt_uri(uri: &str, content_key: &ContentKey) -> Vec<u8> {
+  seal(content_key, uri.as_bytes())
+}
+
+pub fn decrypt_uri(sealed_uri: &[u8], content_key: &ContentKey) -> Result<String, Error> {
+  let uri = open(content_key, sealed_uri)?;
+  String::from_utf8(uri).map_err(|_| Error::InvalidUri)
+}
+
+// Wraps the content key for the buyer's public key: an ephemeral public key,
+// then the content key sealed with the secret both sides can derive from it
+pub fn wrap_key(content_key: &ContentKey, public_key: &PublicKey) -> Vec<u8> {
+  let ephemeral_secret = generate_secret();
+  let ephemeral_public = PublicKey::from(&ephemeral_secret);
+  let shared = ephemeral_secret.diffie_hellman(public_key);
+  let wrapping_key = wrapping_key(&shared, &ephemeral_public, public_key);
+
+  let mut envelope = ephemeral_public.as_bytes().to_vec();
+  envelope.extend(seal(&wrapping_key, content_key));
+  envelope
+}
+
+pub fn unwrap_key(envelope: &[u8], secret: &StaticSecret) -> Result<ContentKey, Error> {
+  if envelope.len() < KEY_LENGTH {
+    return Err(Error::Malformed);
+  }
+  let (ephemeral_public, sealed_key) = envelope.split_at(KEY_LENGTH);
+  let mut ephemeral_bytes = [0u8; KEY_LENGTH];
+  ephemeral_bytes.copy_from_slice(ephemeral_public);
+  let ephemeral_public = PublicKey::from(ephemeral_bytes);
+
+  let shared = secret.diffie_hellman(&ephemeral_public);
+  let wrapping_key = wrapping_key(&shared, &ephemeral_public, &PublicKey::from(secret));
+  let content_key = open(&wrapping_key, sealed_key)?;
+  if content_key.len() != KEY_LENGTH {
+    return Err(Error::Malformed);
+  }
+  let mut key = [0u8; KEY_LENGTH];
+  key.copy_from_slice(&content_key);
+  Ok(key)
+}
+
+// Both public keys go into the derivation so an envelope only opens for its recipient
+fn wrapping_key(
+  shared: &SharedSecret,
+  ephemeral_public: &PublicKey,
+  recipient_public: &PublicKey,
+) -> ContentKey {
+  let mut salt = ephemeral_public.as_bytes().to_vec();
+  salt.extend_from_slice(recipient_public.as_bytes());
+  let mut key = [0u8; KEY_LENGTH];
+  Hkdf::<Sha256>::new(Some(&salt), shared.as_bytes())
+    .expand(ENVELOPE_INFO, &mut key)
+    .expect("32 bytes is a valid HKDF-SHA256 output length");
+  key
+}
+
+fn seal(key: &ContentKey, plaintext: &[u8]) -> Vec<u8> {
+  let mut nonce = [0u8; NONCE_LENGTH];
+  OsRng.fill_bytes(&mut nonce);
+  let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key))
+    .encrypt(Nonce::from_slice(&nonce), plaintext)
+    .expect("ChaCha20Poly1305 encryption doesn't fail on in-memory buffers");
+
+  let mut sealed = nonce.to_vec();
+  sealed.extend(ciphertext);
+  sealed
+}
+
+fn open(key: &ContentKey, sealed: &[u8]) -> Result<Vec<u8>, Error> {
+  if sealed.len() < NONCE_LENGTH {
+    return Err(Error::Malformed);
+  }
+  let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+  ChaCha20Poly1305::new(Key::from_slice(key))
+    .decrypt(Nonce::from_slice(nonce), ciphertext)
+    .map_err(|_| Error::Decryption)
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn encrypted_uri_decrypts_with_content_key() {
+    // Given
+    let content_key = generate_content_key();
+    // When
+    let sealed_uri = encrypt_uri("https://example.com/secret", &content_key);
+    // Then
+    assert!(!sealed_uri.windows(6).any(|window| window == b"secret"));
+    assert_eq!(
+      decrypt_uri(&sealed_uri, &content_key),
+      Ok("https://example.com/secret".to_string())
+    );
+  }
+
+  #[test]
+  fn wrapped_key_unwraps_with_recipient_secret() {
+    // Given
+    let secret = generate_secret();
+    let content_key = generate_content_key();
+    // When
+    let envelope = wrap_key(&content_key, &PublicKey::from(&secret));
+    // Then
+    assert_eq!(unwrap_key(&envelope, &secret), Ok(content_key));
+  }
+
+  #[test]
+  fn wrapped_key_does_not_unwrap_with_other_secret() {
+    // Given
+    let secret = generate_secret();
+    let envelope = wrap_key(&generate_content_key(), &PublicKey::from(&secret));
+    // When
+    let unwrapped = unwrap_key(&envelope, &generate_secret());
+    // Then
+    assert_eq!(unwrapped, Err(Error::Decryption));
+  }
+
+  #[test]
+  fn tampered_uri_fails_to_decrypt() {
+    // Given
+    let content_key = generate_content_key();
+    let mut sealed_uri = encrypt_uri("https://example.com", &content_key);
+    // When
+    let last = sealed_uri.len() - 1;
+    sealed_uri[last] ^= 1;
+    // Then
+    assert_eq!(decrypt_uri(&sealed_uri, &content_key), Err(Error::Decryption));
+  }
+
+  #[test]
+  fn truncated_envelope_is_malformed() {
+    assert_eq!(unwrap_key(&[0u8; 10], &generate_secret()), Err(Error::Malformed));
+  }
+}
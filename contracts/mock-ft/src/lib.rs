@@ -0,0 +1,119 @@
+// Bare NEP-141 token for testing token gated links. It only keeps balances, anyone
+// can mint and there are no storage deposits, so never deploy it outside of tests.
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen, AccountId, Balance};
+use std::collections::HashMap;
+
+near_sdk::setup_alloc!();
+
+#[near_bindgen]
+#[derive(Default, BorshDeserialize, BorshSerialize)]
+pub struct MockFungibleToken {
+  balances: HashMap<AccountId, Balance>,
+}
+
+#[near_bindgen]
+impl MockFungibleToken {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  pub fn ft_balance_of(&self, account_id: AccountId) -> U128 {
+    U128(self.balances.get(&account_id).copied().unwrap_or(0))
+  }
+
+  pub fn ft_total_supply(&self) -> U128 {
+    U128(self.balances.values().sum())
+  }
+
+  /****************
+   * CALL METHODS *
+   ****************/
+  pub fn mint(&mut self, account_id: AccountId, amount: U128) {
+    let balance = self.balances.entry(account_id).or_insert(0);
+    *balance = balance
+      .checked_add(amount.0)
+      .unwrap_or_else(|| env::panic(b"Balance overflow"));
+  }
+
+  #[payable]
+  pub fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+    let _ = memo;
+    let sender_id = env::predecessor_account_id();
+    let balance = self.ft_balance_of(sender_id.clone()).0;
+    if balance < amount.0 {
+      env::panic(b"The account doesn't have enough balance");
+    }
+
+    self.balances.insert(sender_id, balance - amount.0);
+    self.mint(receiver_id, amount);
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use near_sdk::{testing_env, MockedBlockchain, VMContext};
+
+  fn set_predecessor(account_id: &str) {
+    testing_env!(VMContext {
+      current_account_id: "token.testnet".to_string(),
+      signer_account_id: account_id.to_string(),
+      signer_account_pk: vec![0, 1, 2],
+      predecessor_account_id: account_id.to_string(),
+      input: vec![],
+      block_index: 0,
+      block_timestamp: 0,
+      account_balance: 0,
+      account_locked_balance: 0,
+      storage_usage: 0,
+      attached_deposit: 1,
+      prepaid_gas: 10u64.pow(18),
+      random_seed: vec![0, 1, 2],
+      is_view: false,
+      output_data_receivers: vec![],
+      epoch_height: 19,
+    });
+  }
+
+  #[test]
+  fn mint_adds_to_balance() {
+    // Given
+    set_predecessor("alice.testnet");
+    let mut token = MockFungibleToken::default();
+    // When
+    token.mint("alice.testnet".to_string(), U128(10));
+    token.mint("alice.testnet".to_string(), U128(5));
+    // Then
+    assert_eq!(token.ft_balance_of("alice.testnet".to_string()), U128(15));
+    assert_eq!(token.ft_total_supply(), U128(15));
+  }
+
+  #[test]
+  fn ft_transfer_moves_balance() {
+    // Given
+    set_predecessor("alice.testnet");
+    let mut token = MockFungibleToken::default();
+    token.mint("alice.testnet".to_string(), U128(10));
+    // When
+    token.ft_transfer("robert.testnet".to_string(), U128(4), None);
+    // Then
+    assert_eq!(token.ft_balance_of("alice.testnet".to_string()), U128(6));
+    assert_eq!(token.ft_balance_of("robert.testnet".to_string()), U128(4));
+  }
+
+  #[test]
+  #[should_panic(expected = "The account doesn't have enough balance")]
+  fn ft_transfer_over_balance_panics() {
+    // Given
+    set_predecessor("alice.testnet");
+    let mut token = MockFungibleToken::default();
+    // When
+    token.ft_transfer("robert.testnet".to_string(), U128(1), None);
+    // Then
+    // - Should panic
+  }
+}
@@ -0,0 +1,512 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U64;
+use near_sdk::{env, near_bindgen, AccountId};
+use serde::{Deserialize, Serialize};
+// Crates
+use crate::profiles::{normalize_profile, ProfileId};
+use crate::*;
+
+pub const ATTESTATION_SUBJECT_MAX_LENGTH: usize = 100;
+
+// What a verifier vouches the profile owner controls
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum AttestationKind {
+  Twitter,
+  Github,
+  Domain,
+}
+
+impl AttestationKind {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      AttestationKind::Twitter => "twitter",
+      AttestationKind::Github => "github",
+      AttestationKind::Domain => "domain",
+    }
+  }
+
+  // Whether `uri` points at `subject` on this platform, e.g. `https://github.com/alice`
+  // for the `alice` GitHub handle, or any page of the domain (or its subdomains)
+  pub fn matches_uri(&self, subject: &str, uri: &str) -> bool {
+    let host = uri_host(uri);
+    let subject = subject.trim_start_matches('@').to_lowercase();
+    match self {
+      AttestationKind::Twitter => {
+        (host == "twitter.com" || host == "x.com") && uri_handle(uri) == subject
+      }
+      AttestationKind::Github => host == "github.com" && uri_handle(uri) == subject,
+      AttestationKind::Domain => host == subject || host.ends_with(&format!(".{}", subject)),
+    }
+  }
+}
+
+pub fn assert_valid_subject(subject: &str) {
+  if subject.is_empty() || subject.len() > ATTESTATION_SUBJECT_MAX_LENGTH {
+    panic!(
+      "Subject must be between 1 and {} characters long",
+      ATTESTATION_SUBJECT_MAX_LENGTH
+    );
+  }
+}
+
+// Lowercase host of the uri, without `www.` nor the port
+pub fn uri_host(uri: &str) -> String {
+  let rest = uri.split_once("://").map_or(uri, |(_, rest)| rest);
+  let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+  let host = authority.rsplit('@').next().unwrap_or("");
+  let host = host.split(':').next().unwrap_or("").to_lowercase();
+  host.strip_prefix("www.").map_or(host.clone(), str::to_string)
+}
+
+// Lowercase first path segment of the uri, where platforms put the handle
+fn uri_handle(uri: &str) -> String {
+  let rest = uri.split_once("://").map_or(uri, |(_, rest)| rest);
+  let path = rest.split_once('/').map_or("", |(_, path)| path);
+  let handle = path.split(['/', '?', '#']).next().unwrap_or("");
+  handle.trim_start_matches('@').to_lowercase()
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Attestation {
+  pub verifier_account_id: AccountId,
+  pub kind: AttestationKind,
+  // Handle or domain the attestation is about
+  pub subject: String,
+  pub issued_at: U64,
+  pub expires_at: Option<U64>,
+  pub evidence_uri: Option<String>,
+}
+
+impl Attestation {
+  pub fn is_expired(&self) -> bool {
+    self
+      .expires_at
+      .is_some_and(|expires_at| expires_at.0 <= env::block_timestamp())
+  }
+
+  // A verifier keeps one attestation of each kind per profile
+  pub fn is_replaced_by(&self, other: &Attestation) -> bool {
+    self.verifier_account_id == other.verifier_account_id && self.kind == other.kind
+  }
+}
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  pub fn get_verifiers(&self) -> Vec<AccountId> {
+    self.verifiers.to_vec()
+  }
+
+  // Attestations that haven't expired, from verifiers still in the registry (or the
+  // current verification oracle)
+  pub fn get_attestations(
+    &self,
+    account_id: AccountId,
+    profile: Option<String>,
+  ) -> Vec<Attestation> {
+    self.internal_active_attestations(&ProfileId {
+      account_id,
+      profile: normalize_profile(profile),
+    })
+  }
+
+  /****************
+   * CALL METHODS *
+   ****************/
+  pub fn add_verifier(&mut self, account_id: AccountId) {
+    self.assert_admin();
+    if !env::is_valid_account_id(account_id.as_bytes()) {
+      env::panic(b"Verifier account id is not valid");
+    }
+
+    self.verifiers.insert(&account_id);
+  }
+
+  // The verifier's attestations stop counting as active, they come back if it's re-added
+  pub fn remove_verifier(&mut self, account_id: AccountId) {
+    self.assert_admin();
+
+    self.verifiers.remove(&account_id);
+  }
+
+  // Replaces the caller's previous attestation of the same kind on the profile
+  pub fn attest(
+    &mut self,
+    account_id: AccountId,
+    profile: Option<String>,
+    kind: AttestationKind,
+    subject: String,
+    expires_at: Option<U64>,
+    evidence_uri: Option<String>,
+  ) -> Attestation {
+    let verifier_account_id = env::predecessor_account_id();
+    if !self.verifiers.contains(&verifier_account_id) {
+      env::panic(b"Only a verifier can attest profiles");
+    }
+    assert_valid_subject(&subject);
+    if expires_at.is_some_and(|expires_at| expires_at.0 <= env::block_timestamp()) {
+      env::panic(b"Attestation must expire in the future");
+    }
+
+    let profile = normalize_profile(profile);
+    self.internal_expect_profile(&account_id, &profile);
+
+    let attestation = Attestation {
+      verifier_account_id,
+      kind,
+      subject,
+      issued_at: U64(env::block_timestamp()),
+      expires_at,
+      evidence_uri,
+    };
+    self.internal_add_attestation(&ProfileId { account_id, profile }, attestation.clone());
+    attestation
+  }
+
+  pub fn revoke_attestation(
+    &mut self,
+    account_id: AccountId,
+    profile: Option<String>,
+    kind: AttestationKind,
+  ) {
+    let verifier_account_id = env::predecessor_account_id();
+    let profile_id = ProfileId {
+      account_id,
+      profile: normalize_profile(profile),
+    };
+
+    let mut attestations = self.attestations.get(&profile_id).unwrap_or_default();
+    let count = attestations.len();
+    attestations.retain(|attestation| {
+      !(attestation.verifier_account_id == verifier_account_id && attestation.kind == kind)
+    });
+    if attestations.len() == count {
+      env::panic(b"Could not find an attestation of this verifier");
+    }
+
+    self.internal_set_attestations(&profile_id, attestations);
+  }
+}
+
+impl MainHub {
+  /************
+   * INTERNAL *
+   ************/
+  pub(crate) fn internal_active_attestations(&self, profile_id: &ProfileId) -> Vec<Attestation> {
+    self
+      .attestations
+      .get(profile_id)
+      .unwrap_or_default()
+      .into_iter()
+      .filter(|attestation| {
+        !attestation.is_expired() && self.internal_is_verifier(&attestation.verifier_account_id)
+      })
+      .collect()
+  }
+
+  // Registered verifiers issue attestations directly, the oracle through proof requests
+  pub(crate) fn internal_is_verifier(&self, account_id: &AccountId) -> bool {
+    self.verifiers.contains(account_id)
+      || self.verification_oracle_account_id.as_ref() == Some(account_id)
+  }
+
+  // Drops expired attestations along the way, so the list doesn't grow forever
+  pub(crate) fn internal_add_attestation(
+    &mut self,
+    profile_id: &ProfileId,
+    attestation: Attestation,
+  ) {
+    let mut attestations = self.attestations.get(profile_id).unwrap_or_default();
+    attestations.retain(|other| !other.is_expired() && !other.is_replaced_by(&attestation));
+    attestations.push(attestation);
+
+    self.internal_set_attestations(profile_id, attestations);
+  }
+
+  pub(crate) fn internal_set_attestations(
+    &mut self,
+    profile_id: &ProfileId,
+    attestations: Vec<Attestation>,
+  ) {
+    if attestations.is_empty() {
+      self.attestations.remove(profile_id);
+    } else {
+      self.attestations.insert(profile_id, &attestations);
+    }
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::*;
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  const VERIFIER: &str = "verifier.testnet";
+
+  fn generate_hub() -> MainHub {
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    let mut main = MainHub::default();
+    main.add_verifier(VERIFIER.to_string());
+    testing_env!(user_context("alice.testnet", None));
+    main.create("Hello".to_string(), "World".to_string(), None, Some(true));
+    main
+  }
+
+  fn attest_github(main: &mut MainHub, expires_at: Option<u64>) -> Attestation {
+    testing_env!(user_context(VERIFIER, None));
+    main.attest(
+      "alice.testnet".to_string(),
+      None,
+      AttestationKind::Github,
+      "alice".to_string(),
+      expires_at.map(U64),
+      Some("https://gist.github.com/alice/1".to_string()),
+    )
+  }
+
+  fn attestations_at(main: &MainHub, block_timestamp: u64) -> Vec<Attestation> {
+    let mut context = user_context("robert.testnet", None);
+    context.block_timestamp = block_timestamp;
+    testing_env!(context);
+    main.get_attestations("alice.testnet".to_string(), None)
+  }
+
+  #[test]
+  fn attest_adds_attestation_to_profile_views() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    let attestation = attest_github(&mut main, None);
+    // Then
+    let attestations = main.get_attestations("alice.testnet".to_string(), None);
+    assert_eq!(attestations, vec![attestation.clone()]);
+    let profile = main.get_profile("alice.testnet".to_string(), None).unwrap();
+    assert_eq!(profile.attestations, vec![attestation]);
+    let json = near_sdk::serde_json::to_value(&profile).unwrap();
+    assert_eq!(json["title"], "Hello");
+    assert_eq!(json["attestations"][0]["subject"], "alice");
+    let summaries = main.get_profiles(0, 10);
+    assert_eq!(summaries[0].attestations.len(), 1);
+    assert_eq!(summaries[0].attestations[0].subject, "alice".to_string());
+  }
+
+  #[test]
+  fn attesting_again_replaces_previous_attestation() {
+    // Given
+    let mut main = generate_hub();
+    attest_github(&mut main, Some(10));
+    // When
+    attest_github(&mut main, Some(20));
+    // Then
+    let attestations = attestations_at(&main, 0);
+    assert_eq!(attestations.len(), 1);
+    assert_eq!(attestations[0].expires_at, Some(U64(20)));
+  }
+
+  #[test]
+  fn expired_attestation_is_not_active() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    attest_github(&mut main, Some(100));
+    // Then
+    assert_eq!(attestations_at(&main, 99).len(), 1);
+    assert!(attestations_at(&main, 100).is_empty());
+  }
+
+  #[test]
+  fn revoke_attestation_removes_it() {
+    // Given
+    let mut main = generate_hub();
+    attest_github(&mut main, None);
+    // When
+    main.revoke_attestation("alice.testnet".to_string(), None, AttestationKind::Github);
+    // Then
+    assert!(attestations_at(&main, 0).is_empty());
+  }
+
+  #[test]
+  fn removed_verifier_attestations_are_not_active() {
+    // Given
+    let mut main = generate_hub();
+    attest_github(&mut main, None);
+    // When
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    main.remove_verifier(VERIFIER.to_string());
+    // Then
+    assert!(attestations_at(&main, 0).is_empty());
+  }
+
+  #[test]
+  fn attestation_marks_matching_links_until_revoked() {
+    // Given
+    let mut main = generate_hub();
+    main.add_link(
+      "https://www.github.com/alice/".to_string(),
+      "GitHub".to_string(),
+      "My code".to_string(),
+      None,
+      None,
+      None,
+    );
+    main.add_link(
+      "https://github.com/eve".to_string(),
+      "Not me".to_string(),
+      "Someone else".to_string(),
+      None,
+      None,
+      None,
+    );
+    // When
+    attest_github(&mut main, None);
+    // Then
+    let is_verified = |main: &MainHub| -> Vec<bool> {
+      let links = main.get_links("alice.testnet".to_string(), None, None);
+      links.iter().map(|link| link.is_verified).collect()
+    };
+    assert_eq!(is_verified(&main), vec![true, false]);
+    main.revoke_attestation("alice.testnet".to_string(), None, AttestationKind::Github);
+    assert_eq!(is_verified(&main), vec![false, false]);
+  }
+
+  fn add_github_link(main: &mut MainHub) {
+    testing_env!(user_context("alice.testnet", None));
+    main.add_link(
+      "https://github.com/alice".to_string(),
+      "GitHub".to_string(),
+      "My code".to_string(),
+      None,
+      None,
+      None,
+    );
+  }
+
+  fn verified_links(main: &MainHub) -> Vec<bool> {
+    let links = main.get_links("alice.testnet".to_string(), None, None);
+    links.iter().map(|link| link.is_verified).collect()
+  }
+
+  #[test]
+  fn removing_verifier_unmarks_links_until_re_added() {
+    // Given
+    let mut main = generate_hub();
+    add_github_link(&mut main);
+    attest_github(&mut main, None);
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    // When
+    main.remove_verifier(VERIFIER.to_string());
+    // Then
+    assert_eq!(verified_links(&main), vec![false]);
+    main.add_verifier(VERIFIER.to_string());
+    assert_eq!(verified_links(&main), vec![true]);
+  }
+
+  #[test]
+  fn link_added_after_attestation_is_marked() {
+    // Given
+    let mut main = generate_hub();
+    attest_github(&mut main, None);
+    // When
+    add_github_link(&mut main);
+    // Then
+    assert_eq!(verified_links(&main), vec![true]);
+  }
+
+  #[test]
+  fn updated_link_is_marked_when_its_new_uri_is_attested() {
+    // Given
+    let mut main = generate_hub();
+    add_github_link(&mut main);
+    attest_github(&mut main, None);
+    testing_env!(user_context("alice.testnet", None));
+    let update = |main: &mut MainHub, uri: &str| {
+      main.update_link(
+        1,
+        uri.to_string(),
+        "GitHub".to_string(),
+        "My code".to_string(),
+        None,
+        None,
+        None,
+      );
+    };
+    // When
+    update(&mut main, "https://github.com/eve");
+    // Then
+    assert_eq!(verified_links(&main), vec![false]);
+    update(&mut main, "https://github.com/alice?tab=repositories");
+    assert_eq!(verified_links(&main), vec![true]);
+  }
+
+  #[test]
+  fn link_mark_lapses_with_its_attestation() {
+    // Given
+    let mut main = generate_hub();
+    add_github_link(&mut main);
+    // When
+    attest_github(&mut main, Some(100));
+    // Then
+    let mut context = user_context("robert.testnet", None);
+    context.block_timestamp = 100;
+    testing_env!(context);
+    assert_eq!(verified_links(&main), vec![false]);
+  }
+
+  #[test]
+  fn matches_uri_checks_platform_and_handle() {
+    assert!(AttestationKind::Twitter.matches_uri("@Alice", "https://x.com/alice?s=20"));
+    assert!(!AttestationKind::Twitter.matches_uri("alice", "https://github.com/alice"));
+    assert!(!AttestationKind::Github.matches_uri("alice", "https://github.com/alicea"));
+    assert!(AttestationKind::Domain.matches_uri("alice.dev", "https://blog.alice.dev/post"));
+    assert!(!AttestationKind::Domain.matches_uri("alice.dev", "https://notalice.dev"));
+  }
+
+  #[test]
+  #[should_panic(expected = "Could not find an attestation of this verifier")]
+  fn revoke_other_verifier_attestation_panics() {
+    // Given
+    let mut main = generate_hub();
+    attest_github(&mut main, None);
+    // When
+    testing_env!(user_context("eve.testnet", None));
+    main.revoke_attestation("alice.testnet".to_string(), None, AttestationKind::Github);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Only a verifier can attest profiles")]
+  fn attest_not_verifier_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.attest(
+      "alice.testnet".to_string(),
+      None,
+      AttestationKind::Twitter,
+      "alice".to_string(),
+      None,
+      None,
+    );
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the admin can call this method")]
+  fn add_verifier_not_admin_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.add_verifier("eve.testnet".to_string());
+    // Then
+    // - Should panic
+  }
+}
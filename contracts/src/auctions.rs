@@ -0,0 +1,454 @@
+use near_sdk::json_types::U128;
+use near_sdk::{env, log, near_bindgen, AccountId, Gas, Promise, PromiseResult};
+// Crates
+use crate::external::ext_self;
+use crate::fees::FeeSource;
+use crate::item::{Auction, AuctionSettings};
+use crate::link3::Link3;
+use crate::profiles::normalize_profile;
+use crate::*;
+
+const GAS_FOR_ON_AUCTION_REFUND: Gas = 10_000_000_000_000;
+const NO_DEPOSIT: u128 = 0;
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  // Last auction of the link, None when it was never auctioned
+  pub fn get_auction(
+    &self,
+    owner_account_id: AccountId,
+    id: u64,
+    profile: Option<String>,
+  ) -> Option<Auction> {
+    self
+      .internal_expect_profile(&owner_account_id, &normalize_profile(profile))
+      .get_link(id)
+      .auction()
+      .cloned()
+  }
+
+  /****************
+   * CALL METHODS *
+   ****************/
+  // Puts the link up for an English auction, the link can't be bought until it's settled.
+  // Once settled only the winner and earlier buyers can read it
+  pub fn start_auction(
+    &mut self,
+    id: u64,
+    settings: AuctionSettings,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
+  ) -> Link3 {
+    if settings.reserve_price.0 == 0 {
+      env::panic(b"Reserve price must be greater than 0");
+    }
+    if settings.min_increment.0 == 0 {
+      env::panic(b"Minimum increment must be greater than 0");
+    }
+    if settings.ends_at.0 <= env::block_timestamp() {
+      env::panic(b"Auction must end in the future");
+    }
+
+    let account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
+
+    // Update item
+    link3.start_auction(id, settings);
+
+    // Save to hub state
+    self.internal_save_profile(&account_id, &profile, &link3);
+    link3
+  }
+
+  // Bids the attached deposit, the bid it outbids is refunded
+  #[payable]
+  pub fn place_bid(
+    &mut self,
+    owner_account_id: AccountId,
+    link_id: u64,
+    profile: Option<String>,
+  ) -> Link3 {
+    let bidder_account_id = env::predecessor_account_id();
+    if bidder_account_id == owner_account_id {
+      env::panic(b"Owner can't bid on their own link");
+    }
+
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&owner_account_id, &profile);
+    let outbid = link3.place_bid(link_id, bidder_account_id, env::attached_deposit());
+    self.internal_save_profile(&owner_account_id, &profile, &link3);
+
+    if let Some(outbid) = outbid {
+      Promise::new(outbid.account_id.clone())
+        .transfer(outbid.amount.0)
+        .then(ext_self::on_auction_refund(
+          outbid.account_id,
+          outbid.amount,
+          &env::current_account_id(),
+          NO_DEPOSIT,
+          GAS_FOR_ON_AUCTION_REFUND,
+        ));
+    }
+
+    link3
+  }
+
+  // Closes the auction once it's over, anyone can call it. The winner gets the link and
+  // the owner the winning bid, an auction without bids just ends
+  pub fn settle_auction(
+    &mut self,
+    owner_account_id: AccountId,
+    link_id: u64,
+    profile: Option<String>,
+  ) -> Link3 {
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&owner_account_id, &profile);
+
+    let winning_bid = link3.close_auction(link_id);
+    if let Some(bid) = &winning_bid {
+      self.internal_grant_purchase(
+        &mut link3,
+        &owner_account_id,
+        &profile,
+        link_id,
+        &bid.account_id,
+      );
+    }
+    self.internal_save_profile(&owner_account_id, &profile, &link3);
+
+    if let Some(bid) = winning_bid {
+      let splits = link3.get_link(link_id).splits().clone();
+      let proceeds =
+        self.internal_take_fee(FeeSource::Sale, &owner_account_id, &None, bid.amount.0);
+      self.internal_credit_proceeds(&owner_account_id, &splits, &None, proceeds);
+    }
+
+    link3
+  }
+
+  /*************
+   * CALLBACKS *
+   *************/
+  // Credits the refund to the bidder's balance when the transfer failed
+  #[private]
+  pub fn on_auction_refund(&mut self, account_id: AccountId, amount: U128) -> bool {
+    if let PromiseResult::Successful(_) = env::promise_result(0) {
+      return true;
+    }
+
+    log!(
+      "Refund of {} to {} failed, crediting their balance",
+      amount.0,
+      account_id
+    );
+    self.internal_credit(&account_id, &None, amount.0);
+    false
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::item::Price;
+  use crate::test_utils::*;
+  use near_sdk::json_types::U64;
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  const ENDS_AT: u64 = 10_000;
+  const EXTENSION: u64 = 1_000;
+
+  // Alice auctions her link from 100 yocto, by steps of at least 10
+  fn generate_hub() -> MainHub {
+    testing_env!(user_context("alice.testnet", None));
+    let mut main = MainHub::default();
+    main.create("Hello".to_string(), "World".to_string(), None, Some(true));
+    add_sealed_link(&mut main);
+    main.start_auction(
+      1,
+      AuctionSettings {
+        reserve_price: U128(100),
+        min_increment: U128(10),
+        ends_at: U64(ENDS_AT),
+        extension: U64(EXTENSION),
+      },
+      None,
+      None,
+    );
+    main
+  }
+
+  fn bid_at(main: &mut MainHub, bidder: &str, amount: u128, block_timestamp: u64) {
+    let mut context = user_context(bidder, Some(amount));
+    context.block_timestamp = block_timestamp;
+    testing_env!(context);
+    main.place_bid("alice.testnet".to_string(), 1, None);
+  }
+
+  fn settle_at(main: &mut MainHub, block_timestamp: u64) {
+    let mut context = user_context("carol.testnet", None);
+    context.block_timestamp = block_timestamp;
+    testing_env!(context);
+    main.settle_auction("alice.testnet".to_string(), 1, None);
+  }
+
+  fn on_auction_refund(main: &mut MainHub, result: PromiseResult) -> bool {
+    testing_env!(
+      callback_context("carol.testnet"),
+      Default::default(),
+      Default::default(),
+      Default::default(),
+      vec![result]
+    );
+    main.on_auction_refund("robert.testnet".to_string(), U128(100))
+  }
+
+  fn auction(main: &MainHub) -> Option<Auction> {
+    main.get_auction("alice.testnet".to_string(), 1, None)
+  }
+
+  fn can_read(main: &MainHub, account_id: &str) -> bool {
+    testing_env!(user_context(account_id, None));
+    let reader = Some(account_id.to_string());
+    main.get_links("alice.testnet".to_string(), None, reader)[0].sealed_uri.is_some()
+  }
+
+  #[test]
+  fn bid_at_reserve_price_leads() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    bid_at(&mut main, "robert.testnet", 100, 0);
+    // Then
+    let bid = auction(&main).unwrap().highest_bid.unwrap();
+    assert_eq!(bid.account_id, "robert.testnet");
+    assert_eq!(bid.amount, U128(100));
+  }
+
+  #[test]
+  #[should_panic(expected = "Bid must be at least 100")]
+  fn bid_below_reserve_price_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    bid_at(&mut main, "robert.testnet", 99, 0);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  fn outbid_bidder_is_refunded() {
+    // Given
+    let mut main = generate_hub();
+    bid_at(&mut main, "robert.testnet", 100, 0);
+    // When
+    bid_at(&mut main, "dave.testnet", 110, 0);
+    // Then
+    let receipts = created_receipts();
+    assert!(receipts.contains(
+      r#""receiver_id":"robert.testnet","actions":[{"Transfer":{"deposit":100}}]"#
+    ));
+    assert!(receipts.contains("on_auction_refund"));
+    let bid = auction(&main).unwrap().highest_bid.unwrap();
+    assert_eq!(bid.account_id, "dave.testnet");
+  }
+
+  #[test]
+  #[should_panic(expected = "Bid must be at least 110")]
+  fn bid_tying_highest_bid_panics() {
+    // Given
+    let mut main = generate_hub();
+    bid_at(&mut main, "robert.testnet", 100, 0);
+    // When
+    bid_at(&mut main, "dave.testnet", 100, 0);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Bid must be at least 110")]
+  fn bid_below_minimum_increment_panics() {
+    // Given
+    let mut main = generate_hub();
+    bid_at(&mut main, "robert.testnet", 100, 0);
+    // When
+    bid_at(&mut main, "dave.testnet", 109, 0);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  fn late_bid_extends_auction() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    bid_at(&mut main, "robert.testnet", 100, ENDS_AT - 1);
+    // Then
+    let settings = auction(&main).unwrap().settings;
+    assert_eq!(settings.ends_at, U64(ENDS_AT - 1 + EXTENSION));
+  }
+
+  #[test]
+  fn early_bid_keeps_end() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    bid_at(&mut main, "robert.testnet", 100, ENDS_AT - EXTENSION);
+    // Then
+    assert_eq!(auction(&main).unwrap().settings.ends_at, U64(ENDS_AT));
+  }
+
+  #[test]
+  #[should_panic(expected = "Auction has ended")]
+  fn bid_after_end_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    bid_at(&mut main, "robert.testnet", 100, ENDS_AT);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Owner can't bid on their own link")]
+  fn owner_bids_on_own_link_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    bid_at(&mut main, "alice.testnet", 100, 0);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  fn failed_refund_credits_balance() {
+    // Given
+    let mut main = generate_hub();
+    bid_at(&mut main, "robert.testnet", 100, 0);
+    bid_at(&mut main, "dave.testnet", 110, 0);
+    // When
+    let refunded = on_auction_refund(&mut main, PromiseResult::Failed);
+    // Then
+    assert!(!refunded);
+    assert_eq!(main.get_balance("robert.testnet".to_string()).near, U128(100));
+  }
+
+  #[test]
+  fn successful_refund_leaves_balance() {
+    // Given
+    let mut main = generate_hub();
+    bid_at(&mut main, "robert.testnet", 100, 0);
+    bid_at(&mut main, "dave.testnet", 110, 0);
+    // When
+    let refunded = on_auction_refund(&mut main, promise_success(&()));
+    // Then
+    assert!(refunded);
+    assert_eq!(main.get_balance("robert.testnet".to_string()).near, U128(0));
+  }
+
+  #[test]
+  fn settle_grants_winner_and_pays_owner() {
+    // Given
+    let mut main = generate_hub();
+    bid_at(&mut main, "robert.testnet", 100, 0);
+    bid_at(&mut main, "dave.testnet", 120, 0);
+    // When
+    settle_at(&mut main, ENDS_AT);
+    // Then
+    assert!(auction(&main).unwrap().settled);
+    assert!(can_read(&main, "dave.testnet"));
+    assert!(!can_read(&main, "robert.testnet"));
+    assert_eq!(main.get_balance("alice.testnet".to_string()).near, U128(120));
+  }
+
+  #[test]
+  fn settle_without_bids_ends_auction() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    settle_at(&mut main, ENDS_AT);
+    // Then
+    assert!(auction(&main).unwrap().settled);
+    assert!(!can_read(&main, "robert.testnet"));
+    assert_eq!(main.get_balance("alice.testnet".to_string()).near, U128(0));
+  }
+
+  #[test]
+  #[should_panic(expected = "Auction hasn't ended")]
+  fn settle_before_end_panics() {
+    // Given
+    let mut main = generate_hub();
+    bid_at(&mut main, "robert.testnet", 100, ENDS_AT - 1);
+    // When
+    settle_at(&mut main, ENDS_AT);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Seal the link before gating it")]
+  fn auction_unsealed_link_panics() {
+    // Given
+    let mut main = generate_hub();
+    main.add_link(
+      "uri".to_string(),
+      "title".to_string(),
+      "description".to_string(),
+      None,
+      None,
+      None,
+    );
+    // When
+    main.start_auction(
+      2,
+      AuctionSettings {
+        reserve_price: U128(100),
+        min_increment: U128(10),
+        ends_at: U64(ENDS_AT),
+        extension: U64(EXTENSION),
+      },
+      None,
+      None,
+    );
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Link is up for auction")]
+  fn buy_link_up_for_auction_panics() {
+    // Given
+    let mut main = generate_hub();
+    main.set_link_prices(
+      1,
+      vec![Price {
+        token_account_id: None,
+        amount: U128(10),
+      }],
+      None,
+      None,
+    );
+    testing_env!(user_context("robert.testnet", Some(10)));
+    // When
+    main.buy_link("alice.testnet".to_string(), 1, None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Link is up for auction")]
+  fn delete_link_up_for_auction_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.delete_link(1, None, None);
+    // Then
+    // - Should panic
+  }
+}
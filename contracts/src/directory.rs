@@ -0,0 +1,205 @@
+use near_sdk::{near_bindgen, AccountId};
+use serde::Serialize;
+// Crates
+use crate::attestations::Attestation;
+use crate::profiles::{ProfileId, DEFAULT_PROFILE};
+use crate::*;
+
+// What the discovery page shows of each published profile
+#[derive(Serialize, Debug)]
+pub struct ProfileSummary {
+  pub account_id: AccountId,
+  pub profile: String,
+  pub handle: Option<String>,
+  pub title: String,
+  pub description: String,
+  pub image_uri: Option<String>,
+  pub link_count: u64,
+  // Active attestations only
+  pub attestations: Vec<Attestation>,
+}
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  pub fn total_profiles(&self) -> u64 {
+    self.directory.len()
+  }
+
+  pub fn get_profiles(&self, from_index: u64, limit: u64) -> Vec<ProfileSummary> {
+    self
+      .directory
+      .as_vector()
+      .iter()
+      .skip(from_index as usize)
+      .take(limit as usize)
+      .filter_map(|profile_id| self.internal_profile_summary(&profile_id))
+      .collect()
+  }
+}
+
+impl MainHub {
+  /************
+   * INTERNAL *
+   ************/
+  // Keeps the directory in sync, only published profiles are listed
+  pub(crate) fn internal_sync_directory(&mut self, profile_id: &ProfileId, is_published: bool) {
+    if is_published {
+      self.directory.insert(profile_id);
+    } else {
+      self.directory.remove(profile_id);
+    }
+  }
+
+  pub(crate) fn internal_profile_summary(&self, profile_id: &ProfileId) -> Option<ProfileSummary> {
+    let link3 = self.internal_get_profile(&profile_id.account_id, &profile_id.profile)?;
+    let (title, description, account_id, image_uri) = link3.info();
+    // Handles point to the default profile
+    let handle = match profile_id.profile {
+      Some(_) => None,
+      None => self.account_handles.get(&account_id),
+    };
+
+    Some(ProfileSummary {
+      account_id,
+      profile: profile_id
+        .profile
+        .clone()
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string()),
+      handle,
+      title,
+      description,
+      image_uri,
+      link_count: link3.link_count() as u64,
+      attestations: self.internal_active_attestations(profile_id),
+    })
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::*;
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  fn create(main: &mut MainHub, account_id: &str, title: &str, is_published: bool) {
+    testing_env!(user_context(account_id, None));
+    main.create(
+      title.to_string(),
+      "Description".to_string(),
+      None,
+      Some(is_published),
+    );
+  }
+
+  fn generate_hub() -> MainHub {
+    testing_env!(user_context("alice.testnet", None));
+    let mut main = MainHub::default();
+    create(&mut main, "alice.testnet", "Alice", true);
+    create(&mut main, "robert.testnet", "Robert", true);
+    create(&mut main, "jane.testnet", "Jane", false);
+    main
+  }
+
+  fn titles(summaries: Vec<ProfileSummary>) -> Vec<String> {
+    summaries.into_iter().map(|summary| summary.title).collect()
+  }
+
+  #[test]
+  fn create_adds_published_profiles_only() {
+    // Given
+    let main = generate_hub();
+    // Then
+    assert_eq!(main.total_profiles(), 2);
+    assert_eq!(
+      titles(main.get_profiles(0, 10)),
+      vec!["Alice".to_string(), "Robert".to_string()]
+    );
+  }
+
+  #[test]
+  fn get_profiles_paginates() {
+    // Given
+    let main = generate_hub();
+    // When
+    let page = main.get_profiles(1, 1);
+    // Then
+    assert_eq!(titles(page), vec!["Robert".to_string()]);
+    assert!(main.get_profiles(2, 10).is_empty());
+  }
+
+  #[test]
+  fn unpublishing_removes_profile_and_publishing_adds_it_back() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    testing_env!(user_context("alice.testnet", None));
+    main.update_published_status(false, None);
+    // Then
+    assert_eq!(titles(main.get_profiles(0, 10)), vec!["Robert".to_string()]);
+
+    // When
+    main.update_published_status(true, None);
+    // Then
+    assert_eq!(main.total_profiles(), 2);
+  }
+
+  #[test]
+  fn publishing_adds_unpublished_profile() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    testing_env!(user_context("jane.testnet", None));
+    main.update_published_status(true, None);
+    // Then
+    assert_eq!(main.total_profiles(), 3);
+  }
+
+  #[test]
+  fn delete_profile_removes_it_from_directory() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    testing_env!(user_context("robert.testnet", None));
+    main.delete_profile(None);
+    // Then
+    assert_eq!(titles(main.get_profiles(0, 10)), vec!["Alice".to_string()]);
+  }
+
+  #[test]
+  fn summaries_include_named_profiles_and_handles() {
+    // Given
+    let mut main = generate_hub();
+    testing_env!(user_context("alice.testnet", None));
+    main.claim_handle("alice".to_string());
+    main.create_profile(
+      "events".to_string(),
+      "Events".to_string(),
+      "My events".to_string(),
+      None,
+      Some(true),
+    );
+    main.add_link(
+      "uri".to_string(),
+      "title".to_string(),
+      "description".to_string(),
+      None,
+      Some("events".to_string()),
+      None,
+    );
+    // When
+    let summaries = main.get_profiles(0, 10);
+    // Then
+    assert_eq!(summaries.len(), 3);
+    assert_eq!(summaries[0].handle, Some("alice".to_string()));
+    assert_eq!(summaries[0].profile, "default".to_string());
+    assert_eq!(summaries[2].profile, "events".to_string());
+    assert_eq!(summaries[2].handle, None);
+    assert_eq!(summaries[2].link_count, 1);
+  }
+}
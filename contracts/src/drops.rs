@@ -0,0 +1,534 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{Base58PublicKey, U128};
+use near_sdk::serde_json;
+use near_sdk::{env, near_bindgen, AccountId, Balance, Gas, Promise, PromiseResult};
+use serde::{Deserialize, Serialize};
+// Crates
+use crate::external::{ext_linkdrop, ext_self};
+use crate::link3::Link3;
+use crate::*;
+
+// Gas the drop key can burn on `claim_profile_drop`, paid by the drop's funder like the
+// linkdrop contract charges its sender
+const ACCESS_KEY_ALLOWANCE: Balance = 100_000_000_000_000_000_000_000;
+// Upper bound of the bytes the drop key takes in the hub's account
+const ACCESS_KEY_STORAGE_BYTES: u64 = 200;
+const CLAIM_METHOD_NAME: &str = "claim_profile_drop";
+const GAS_FOR_CREATE_ACCOUNT: Gas = 40_000_000_000_000;
+const GAS_FOR_ON_PROFILE_DROP_CLAIMED: Gas = 20_000_000_000_000;
+const NO_DEPOSIT: u128 = 0;
+
+// Profile a drop is built from
+#[derive(Serialize, Deserialize)]
+pub struct ProfileTemplate {
+  pub title: String,
+  pub description: String,
+  pub image_uri: Option<String>,
+  pub links: Vec<LinkTemplate>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LinkTemplate {
+  pub uri: String,
+  pub title: String,
+  pub description: String,
+  pub image_uri: Option<String>,
+}
+
+// Pending profile, owned by the funder until someone claims it with the drop key
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ProfileDrop {
+  funder_account_id: AccountId,
+  link3: Link3,
+  // What the new account receives, the deposit minus the drop's storage
+  amount: Balance,
+  // Account being created, a drop can't be claimed twice while waiting on linkdrop
+  claimed_by: Option<AccountId>,
+}
+
+#[derive(Serialize)]
+pub struct ProfileDropView {
+  pub funder_account_id: AccountId,
+  pub profile: Link3,
+  pub amount: U128,
+}
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  pub fn get_profile_drop(&self, public_key: Base58PublicKey) -> Option<ProfileDropView> {
+    self
+      .profile_drops
+      .get(&public_key.0)
+      .map(|drop| ProfileDropView {
+        funder_account_id: drop.funder_account_id,
+        profile: drop.link3,
+        amount: U128(drop.amount),
+      })
+  }
+
+  pub fn get_linkdrop_account(&self) -> AccountId {
+    self.internal_linkdrop_account_id()
+  }
+
+  /****************
+   * CALL METHODS *
+   ****************/
+  // Needed when the hub's account doesn't tell the network, e.g. a top-level account
+  pub fn set_linkdrop_account(&mut self, account_id: Option<AccountId>) {
+    self.assert_admin();
+    if let Some(account_id) = &account_id {
+      if !env::is_valid_account_id(account_id.as_bytes()) {
+        env::panic(b"Linkdrop account id is not valid");
+      }
+    }
+
+    self.linkdrop_account_id = account_id;
+  }
+
+  // Prepares a profile for whoever holds the secret half of `public_key`, the hub adds
+  // the key to itself limited to `claim_profile_drop`
+  #[payable]
+  pub fn create_profile_drop(
+    &mut self,
+    public_key: Base58PublicKey,
+    template: ProfileTemplate,
+  ) -> Promise {
+    if self.profile_drops.get(&public_key.0).is_some() {
+      env::panic(b"A profile drop already exists for this key");
+    }
+    if template.links.len() > LINK_LIMIT as usize {
+      panic!("You can only have {} links", LINK_LIMIT);
+    }
+
+    let funder_account_id = env::predecessor_account_id();
+    let mut link3 = Link3::new(
+      template.title,
+      template.description,
+      template.image_uri,
+      Some(true),
+    );
+    link3.transfer_ownership(funder_account_id.clone());
+    for link in template.links {
+      link3.create_link_as(
+        &funder_account_id,
+        link.uri,
+        link.title,
+        link.description,
+        link.image_uri,
+      );
+    }
+
+    let mut drop = ProfileDrop {
+      funder_account_id,
+      link3,
+      amount: 0,
+      claimed_by: None,
+    };
+    let storage_usage = env::storage_usage();
+    self.profile_drops.insert(&public_key.0, &drop);
+    let storage_bytes = env::storage_usage() - storage_usage + ACCESS_KEY_STORAGE_BYTES;
+    let cost = storage_bytes as Balance * env::storage_byte_cost() + ACCESS_KEY_ALLOWANCE;
+
+    let deposit = env::attached_deposit();
+    if deposit <= cost {
+      panic!(
+        "Deposit must be more than the {} yocto the drop's storage and claim key cost",
+        cost
+      );
+    }
+    drop.amount = deposit - cost;
+    self.profile_drops.insert(&public_key.0, &drop);
+
+    Promise::new(env::current_account_id()).add_access_key(
+      public_key.0,
+      ACCESS_KEY_ALLOWANCE,
+      env::current_account_id(),
+      CLAIM_METHOD_NAME.as_bytes().to_vec(),
+    )
+  }
+
+  // Takes back a drop nobody claimed: the drop key is deleted and the funder is credited
+  // the drop's funds and storage, the key's allowance covers what it may have burnt
+  pub fn cancel_profile_drop(&mut self, public_key: Base58PublicKey) -> Promise {
+    let drop = self
+      .profile_drops
+      .get(&public_key.0)
+      .unwrap_or_else(|| env::panic(b"Could not find a profile drop for this key"));
+    if drop.funder_account_id != env::predecessor_account_id() {
+      env::panic(b"Only the funder can cancel a profile drop");
+    }
+    if drop.claimed_by.is_some() {
+      env::panic(b"Profile drop is already being claimed");
+    }
+
+    let storage_usage = env::storage_usage();
+    self.profile_drops.remove(&public_key.0);
+    let storage_bytes = storage_usage - env::storage_usage() + ACCESS_KEY_STORAGE_BYTES;
+    let storage_cost = storage_bytes as Balance * env::storage_byte_cost();
+    self.internal_credit(&drop.funder_account_id, &None, drop.amount + storage_cost);
+
+    Promise::new(env::current_account_id()).delete_key(public_key.0)
+  }
+
+  // Signed with the drop key: creates `new_account_id` through the network's linkdrop,
+  // with the drop key as its full access key and the drop's funds as its balance
+  pub fn claim_profile_drop(&mut self, new_account_id: AccountId) -> Promise {
+    if env::predecessor_account_id() != env::current_account_id() {
+      env::panic(b"Profile drops can only be claimed with their key");
+    }
+    if !env::is_valid_account_id(new_account_id.as_bytes()) {
+      env::panic(b"New account id is not valid");
+    }
+
+    let public_key = env::signer_account_pk();
+    let mut drop = self
+      .profile_drops
+      .get(&public_key)
+      .unwrap_or_else(|| env::panic(b"Could not find a profile drop for this key"));
+    if drop.claimed_by.is_some() {
+      env::panic(b"Profile drop is already being claimed");
+    }
+    drop.claimed_by = Some(new_account_id.clone());
+    self.profile_drops.insert(&public_key, &drop);
+
+    ext_linkdrop::create_account(
+      new_account_id.clone(),
+      Base58PublicKey(public_key.clone()),
+      &self.internal_linkdrop_account_id(),
+      drop.amount,
+      GAS_FOR_CREATE_ACCOUNT,
+    )
+    .then(ext_self::on_profile_drop_claimed(
+      Base58PublicKey(public_key),
+      new_account_id,
+      &env::current_account_id(),
+      NO_DEPOSIT,
+      GAS_FOR_ON_PROFILE_DROP_CLAIMED,
+    ))
+  }
+
+  /*************
+   * CALLBACKS *
+   *************/
+  // Moves the profile under the new account, or reopens the drop if the account
+  // couldn't be created (linkdrop refunds the funds)
+  #[private]
+  pub fn on_profile_drop_claimed(
+    &mut self,
+    public_key: Base58PublicKey,
+    new_account_id: AccountId,
+  ) -> bool {
+    let mut drop = match self.profile_drops.get(&public_key.0) {
+      Some(drop) => drop,
+      None => return false,
+    };
+
+    let is_created = match env::promise_result(0) {
+      PromiseResult::Successful(value) => {
+        serde_json::from_slice::<bool>(&value).unwrap_or(false)
+      }
+      _ => false,
+    };
+    if !is_created {
+      drop.claimed_by = None;
+      self.profile_drops.insert(&public_key.0, &drop);
+      return false;
+    }
+
+    self.profile_drops.remove(&public_key.0);
+    let mut link3 = drop.link3;
+    link3.transfer_ownership(new_account_id.clone());
+    self.internal_save_profile(&new_account_id, &None, &link3);
+
+    Promise::new(env::current_account_id()).delete_key(public_key.0);
+    true
+  }
+}
+
+impl MainHub {
+  /************
+   * INTERNAL *
+   ************/
+  // `testnet` for `hub.testnet` and `dev-*` accounts, `near` for `hub.near`
+  fn internal_linkdrop_account_id(&self) -> AccountId {
+    if let Some(account_id) = &self.linkdrop_account_id {
+      return account_id.clone();
+    }
+
+    let current_account_id = env::current_account_id();
+    match current_account_id.rsplit_once('.') {
+      Some((_, top_level)) => top_level.to_string(),
+      None if current_account_id.starts_with("dev-") => "testnet".to_string(),
+      None => env::panic(b"The admin has to set the linkdrop account"),
+    }
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::*;
+  use near_sdk::{testing_env, MockedBlockchain, VMContext};
+  use std::convert::TryFrom;
+
+  const NEAR: Balance = 1_000_000_000_000_000_000_000_000;
+  const DROP_KEY: &str = "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp";
+
+  fn drop_key() -> Base58PublicKey {
+    Base58PublicKey::try_from(DROP_KEY).unwrap()
+  }
+
+  fn template() -> ProfileTemplate {
+    ProfileTemplate {
+      title: "NEARCON".to_string(),
+      description: "Welcome!".to_string(),
+      image_uri: None,
+      links: vec![LinkTemplate {
+        uri: "https://nearcon.org".to_string(),
+        title: "Schedule".to_string(),
+        description: "Talks and workshops".to_string(),
+        image_uri: None,
+      }],
+    }
+  }
+
+  // The organizer funds a drop with 1 NEAR
+  fn generate_hub() -> MainHub {
+    testing_env!(user_context("organizer.testnet", Some(NEAR)));
+    let mut main = MainHub::default();
+    main.create_profile_drop(drop_key(), template());
+    main
+  }
+
+  // Context of the hub calling itself with the drop key
+  fn claim_context() -> VMContext {
+    let mut context = callback_context(CONTRACT_ACCOUNT_ID);
+    context.signer_account_pk = drop_key().0;
+    context
+  }
+
+  fn on_claimed(main: &mut MainHub, result: PromiseResult) -> bool {
+    testing_env!(
+      callback_context(CONTRACT_ACCOUNT_ID),
+      Default::default(),
+      Default::default(),
+      Default::default(),
+      vec![result]
+    );
+    main.on_profile_drop_claimed(drop_key(), "bob.testnet".to_string())
+  }
+
+  #[test]
+  fn create_profile_drop_adds_claim_key() {
+    // Given
+    let main = generate_hub();
+    // When
+    let drop = main.get_profile_drop(drop_key()).unwrap();
+    // Then
+    assert_eq!(drop.funder_account_id, "organizer.testnet".to_string());
+    assert_eq!(drop.profile.link_count(), 1);
+    assert!(drop.amount.0 > 0 && drop.amount.0 < NEAR);
+    assert!(created_receipts().contains(CLAIM_METHOD_NAME));
+  }
+
+  #[test]
+  fn create_profile_drop_is_owned_by_funder() {
+    // Given
+    testing_env!(get_context("relayer.testnet", "organizer.testnet", Some(NEAR)));
+    let mut main = MainHub::default();
+    // When
+    main.create_profile_drop(drop_key(), template());
+    // Then
+    let drop = main.get_profile_drop(drop_key()).unwrap();
+    assert_eq!(drop.funder_account_id, "organizer.testnet".to_string());
+    assert_eq!(drop.profile.owner_account_id(), &"organizer.testnet".to_string());
+    assert_eq!(drop.profile.link_count(), 1);
+  }
+
+  #[test]
+  fn cancel_profile_drop_credits_funder_and_deletes_key() {
+    // Given
+    let mut main = generate_hub();
+    let amount = main.get_profile_drop(drop_key()).unwrap().amount.0;
+    // When
+    testing_env!(user_context("organizer.testnet", None));
+    main.cancel_profile_drop(drop_key());
+    // Then
+    assert!(main.get_profile_drop(drop_key()).is_none());
+    let balance = main.get_balance("organizer.testnet".to_string()).near.0;
+    assert!(balance > amount && balance < NEAR);
+    assert!(created_receipts().contains("DeleteKey"));
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the funder can cancel a profile drop")]
+  fn cancel_profile_drop_as_stranger_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    testing_env!(user_context("eve.testnet", None));
+    main.cancel_profile_drop(drop_key());
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Profile drop is already being claimed")]
+  fn cancel_profile_drop_being_claimed_panics() {
+    // Given
+    let mut main = generate_hub();
+    testing_env!(claim_context());
+    main.claim_profile_drop("bob.testnet".to_string());
+    // When
+    testing_env!(user_context("organizer.testnet", None));
+    main.cancel_profile_drop(drop_key());
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  fn claim_profile_drop_creates_account_through_linkdrop() {
+    // Given
+    let mut main = generate_hub();
+    let amount = main.get_profile_drop(drop_key()).unwrap().amount.0;
+    // When
+    testing_env!(claim_context());
+    main.claim_profile_drop("bob.testnet".to_string());
+    // Then
+    let receipts = created_receipts();
+    assert!(receipts.contains(r#""receiver_id":"testnet""#));
+    assert!(receipts.contains(r#""method_name":"create_account""#));
+    assert!(receipts.contains(&format!(r#""deposit":{}"#, amount)));
+    assert!(receipts.contains("on_profile_drop_claimed"));
+  }
+
+  #[test]
+  fn on_profile_drop_claimed_moves_profile_to_new_account() {
+    // Given
+    let mut main = generate_hub();
+    testing_env!(claim_context());
+    main.claim_profile_drop("bob.testnet".to_string());
+    // When
+    let claimed = on_claimed(&mut main, promise_success(&true));
+    // Then
+    assert!(claimed);
+    assert!(main.get_profile_drop(drop_key()).is_none());
+    let link3 = main.get("bob.testnet".to_string()).unwrap();
+    assert_eq!(link3.owner_account_id(), &"bob.testnet".to_string());
+    assert_eq!(link3.list(None, &[], &[])[0].title, "Schedule".to_string());
+    assert!(created_receipts().contains("DeleteKey"));
+  }
+
+  #[test]
+  fn failed_claim_reopens_drop() {
+    // Given
+    let mut main = generate_hub();
+    testing_env!(claim_context());
+    main.claim_profile_drop("bob.testnet".to_string());
+    // When
+    let claimed = on_claimed(&mut main, promise_success(&false));
+    // Then
+    assert!(!claimed);
+    assert!(main.get("bob.testnet".to_string()).is_none());
+    testing_env!(claim_context());
+    main.claim_profile_drop("robert.testnet".to_string());
+  }
+
+  #[test]
+  #[should_panic(expected = "Profile drop is already being claimed")]
+  fn claiming_twice_panics() {
+    // Given
+    let mut main = generate_hub();
+    testing_env!(claim_context());
+    main.claim_profile_drop("bob.testnet".to_string());
+    // When
+    main.claim_profile_drop("robert.testnet".to_string());
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Profile drops can only be claimed with their key")]
+  fn claim_profile_drop_from_other_account_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    testing_env!(user_context("eve.testnet", None));
+    main.claim_profile_drop("eve2.testnet".to_string());
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  fn create_profile_drop_charges_claim_key_allowance() {
+    // Given
+    let main = generate_hub();
+    // When
+    let drop = main.get_profile_drop(drop_key()).unwrap();
+    // Then
+    assert!(drop.amount.0 < NEAR - ACCESS_KEY_ALLOWANCE);
+  }
+
+  #[test]
+  #[should_panic(expected = "Deposit must be more than the")]
+  fn create_profile_drop_covering_storage_only_panics() {
+    // Given
+    testing_env!(user_context("organizer.testnet", Some(ACCESS_KEY_ALLOWANCE)));
+    let mut main = MainHub::default();
+    // When
+    main.create_profile_drop(drop_key(), template());
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  fn dev_account_hub_claims_through_testnet() {
+    // Given
+    let dev_account_id = "dev-1634567890123-12345678901234";
+    let mut context = user_context("organizer.testnet", Some(NEAR));
+    context.current_account_id = dev_account_id.to_string();
+    testing_env!(context);
+    let mut main = MainHub::default();
+    main.create_profile_drop(drop_key(), template());
+    // When
+    let mut context = claim_context();
+    context.current_account_id = dev_account_id.to_string();
+    context.predecessor_account_id = dev_account_id.to_string();
+    testing_env!(context);
+    main.claim_profile_drop("bob.testnet".to_string());
+    // Then
+    assert_eq!(main.get_linkdrop_account(), "testnet".to_string());
+    assert!(created_receipts().contains(r#""receiver_id":"testnet""#));
+  }
+
+  #[test]
+  fn admin_set_linkdrop_account_is_used() {
+    // Given
+    let mut main = generate_hub();
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    main.set_linkdrop_account(Some("linkdrop.testnet".to_string()));
+    // When
+    testing_env!(claim_context());
+    main.claim_profile_drop("bob.testnet".to_string());
+    // Then
+    assert!(created_receipts().contains(r#""receiver_id":"linkdrop.testnet""#));
+  }
+
+  #[test]
+  #[should_panic(expected = "Deposit must be more than the")]
+  fn create_profile_drop_without_enough_deposit_panics() {
+    // Given
+    testing_env!(user_context("organizer.testnet", Some(1)));
+    let mut main = MainHub::default();
+    // When
+    main.create_profile_drop(drop_key(), template());
+    // Then
+    // - Should panic
+  }
+}
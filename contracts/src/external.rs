@@ -0,0 +1,99 @@
+// Interfaces of the contracts the hub talks to (and of its own callbacks)
+use near_sdk::ext_contract;
+use near_sdk::json_types::{Base58PublicKey, U128};
+// Crates
+use crate::attestations::{Attestation, AttestationKind};
+use crate::item::TokenGate;
+use crate::tags::LinkId;
+
+// NEP-141 fungible token
+#[ext_contract(ext_ft)]
+pub trait FungibleToken {
+  fn ft_balance_of(&self, account_id: AccountId) -> U128;
+  fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+}
+
+// Linkdrop contract of the network (`testnet` or `near`), creates top-level accounts
+#[ext_contract(ext_linkdrop)]
+pub trait Linkdrop {
+  fn create_account(&mut self, new_account_id: AccountId, new_public_key: Base58PublicKey) -> bool;
+}
+
+// Oracle of self-service verifications: looks for the challenge in the post at `proof_uri`
+// and checks the post belongs to `subject` on the platform
+#[ext_contract(ext_oracle)]
+pub trait VerificationOracle {
+  fn verify_proof(
+    &self,
+    kind: AttestationKind,
+    subject: String,
+    proof_uri: String,
+    challenge: String,
+  ) -> bool;
+}
+
+// NEP-171 receiver of `nft_transfer_call`, returns true to send the token back
+#[ext_contract(ext_nft_receiver)]
+pub trait NonFungibleTokenReceiver {
+  fn nft_on_transfer(
+    &mut self,
+    sender_id: AccountId,
+    previous_owner_id: AccountId,
+    token_id: String,
+    msg: String,
+  ) -> bool;
+}
+
+// NEP-178 account notified when it's approved for a token
+#[ext_contract(ext_nft_approval_receiver)]
+pub trait NonFungibleTokenApprovalReceiver {
+  fn nft_on_approve(
+    &mut self,
+    token_id: String,
+    owner_id: AccountId,
+    approval_id: u64,
+    msg: String,
+  );
+}
+
+// Callbacks on the hub itself
+#[ext_contract(ext_self)]
+pub trait SelfCallbacks {
+  fn on_token_gate_balance(
+    &mut self,
+    link: LinkId,
+    account_id: AccountId,
+    token_gate: TokenGate,
+  ) -> bool;
+
+  fn on_withdraw(
+    &mut self,
+    account_id: AccountId,
+    token_account_id: Option<AccountId>,
+    amount: U128,
+  ) -> bool;
+
+  fn on_auction_refund(&mut self, account_id: AccountId, amount: U128) -> bool;
+
+  fn on_withdraw_treasury(&mut self, token_account_id: Option<AccountId>, amount: U128) -> bool;
+
+  fn on_profile_drop_claimed(
+    &mut self,
+    public_key: Base58PublicKey,
+    new_account_id: AccountId,
+  ) -> bool;
+
+  fn on_proof_verified(
+    &mut self,
+    account_id: AccountId,
+    profile: Option<String>,
+    attestation: Attestation,
+  ) -> bool;
+
+  fn nft_resolve_transfer(
+    &mut self,
+    previous_owner_id: AccountId,
+    receiver_id: AccountId,
+    token_id: String,
+  ) -> bool;
+}
@@ -0,0 +1,472 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::{env, log, near_bindgen, AccountId, Gas, Promise, PromiseResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+// Crates
+use crate::external::{ext_ft, ext_self};
+use crate::ledger::AccountBalance;
+use crate::*;
+
+const GAS_FOR_FT_TRANSFER: Gas = 10_000_000_000_000;
+const GAS_FOR_ON_WITHDRAW_TREASURY: Gas = 10_000_000_000_000;
+const ONE_YOCTO: u128 = 1;
+const NO_DEPOSIT: u128 = 0;
+pub const PLAN_MAX_LENGTH: usize = 32;
+
+// Where a platform fee was taken from
+#[derive(
+  BorshSerialize,
+  BorshDeserialize,
+  Serialize,
+  Deserialize,
+  Clone,
+  Copy,
+  PartialEq,
+  Eq,
+  Hash,
+  PartialOrd,
+  Debug,
+)]
+pub enum FeeSource {
+  Sale,
+  Tip,
+  Subscription,
+}
+
+// Fees the hub operator collected, the currency is None for NEAR
+#[derive(BorshSerialize, BorshDeserialize, Default)]
+pub struct Treasury {
+  // What the admin can still withdraw
+  balances: HashMap<Option<AccountId>, Balance>,
+  // Lifetime fees, withdrawals don't lower them
+  collected: HashMap<(FeeSource, Option<AccountId>), Balance>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FeesCollected {
+  pub source: FeeSource,
+  pub token_account_id: Option<AccountId>,
+  pub amount: U128,
+}
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  pub fn get_platform_fee(&self) -> u16 {
+    self.platform_fee_bps
+  }
+
+  pub fn get_plan_fees(&self) -> HashMap<String, u16> {
+    self.plan_fees.clone()
+  }
+
+  pub fn get_account_plan(&self, account_id: AccountId) -> Option<String> {
+    self.account_plans.get(&account_id)
+  }
+
+  // Fee taken from what the account earns, its plan's override if it has one
+  pub fn get_platform_fee_for(&self, account_id: AccountId) -> u16 {
+    self.internal_fee_bps(&account_id)
+  }
+
+  pub fn get_treasury_balance(&self) -> AccountBalance {
+    let mut balance = AccountBalance {
+      near: U128(0),
+      tokens: HashMap::new(),
+    };
+    for (token_account_id, amount) in self.treasury.balances.iter() {
+      match token_account_id {
+        _ if *amount == 0 => {}
+        Some(token_account_id) => {
+          balance.tokens.insert(token_account_id.clone(), U128(*amount));
+        }
+        None => balance.near = U128(*amount),
+      }
+    }
+    balance
+  }
+
+  pub fn get_fees_collected(&self) -> Vec<FeesCollected> {
+    let mut fees: Vec<FeesCollected> = self
+      .treasury
+      .collected
+      .iter()
+      .map(|((source, token_account_id), amount)| FeesCollected {
+        source: *source,
+        token_account_id: token_account_id.clone(),
+        amount: U128(*amount),
+      })
+      .collect();
+    fees.sort_by(|a, b| {
+      (a.source, &a.token_account_id)
+        .partial_cmp(&(b.source, &b.token_account_id))
+        .unwrap()
+    });
+    fees
+  }
+
+  /****************
+   * CALL METHODS *
+   ****************/
+  pub fn set_platform_fee(&mut self, fee_bps: u16) {
+    self.assert_admin();
+    if fee_bps as u128 > BASIS_POINTS {
+      panic!("Fee must be at most {} basis points", BASIS_POINTS);
+    }
+
+    self.platform_fee_bps = fee_bps;
+  }
+
+  // Accounts on the plan pay `fee_bps` instead of the platform fee, None drops the override
+  pub fn set_plan_fee(&mut self, plan: String, fee_bps: Option<u16>) {
+    self.assert_admin();
+    assert_valid_plan(&plan);
+
+    match fee_bps {
+      Some(fee_bps) => {
+        if fee_bps as u128 > BASIS_POINTS {
+          panic!("Fee must be at most {} basis points", BASIS_POINTS);
+        }
+        self.plan_fees.insert(plan, fee_bps);
+      }
+      None => {
+        self.plan_fees.remove(&plan);
+      }
+    }
+  }
+
+  // Puts the account on a plan, None takes it off
+  pub fn set_account_plan(&mut self, account_id: AccountId, plan: Option<String>) {
+    self.assert_admin();
+
+    match plan {
+      Some(plan) => {
+        assert_valid_plan(&plan);
+        self.account_plans.insert(&account_id, &plan);
+      }
+      None => {
+        self.account_plans.remove(&account_id);
+      }
+    }
+  }
+
+  // Sends collected fees to the admin, the whole treasury balance when no amount is set
+  pub fn withdraw_treasury(
+    &mut self,
+    amount: Option<U128>,
+    token_account_id: Option<AccountId>,
+  ) -> Promise {
+    self.assert_admin();
+    let balance = self
+      .treasury
+      .balances
+      .get(&token_account_id)
+      .copied()
+      .unwrap_or(0);
+    let amount = amount.map_or(balance, |amount| amount.0);
+
+    if amount == 0 {
+      env::panic(b"Nothing to withdraw");
+    }
+    if amount > balance {
+      env::panic(b"Not enough balance to withdraw");
+    }
+
+    // Deducted up front, `on_withdraw_treasury` gives it back if the transfer fails
+    self
+      .treasury
+      .balances
+      .insert(token_account_id.clone(), balance - amount);
+
+    let receiver_id = self.admin_account_id.clone();
+    let transfer = match &token_account_id {
+      Some(token_account_id) => ext_ft::ft_transfer(
+        receiver_id,
+        U128(amount),
+        Some("Link3 treasury withdrawal".to_string()),
+        token_account_id,
+        ONE_YOCTO,
+        GAS_FOR_FT_TRANSFER,
+      ),
+      None => Promise::new(receiver_id).transfer(amount),
+    };
+
+    transfer.then(ext_self::on_withdraw_treasury(
+      token_account_id,
+      U128(amount),
+      &env::current_account_id(),
+      NO_DEPOSIT,
+      GAS_FOR_ON_WITHDRAW_TREASURY,
+    ))
+  }
+
+  /*************
+   * CALLBACKS *
+   *************/
+  #[private]
+  pub fn on_withdraw_treasury(
+    &mut self,
+    token_account_id: Option<AccountId>,
+    amount: U128,
+  ) -> bool {
+    if let PromiseResult::Successful(_) = env::promise_result(0) {
+      return true;
+    }
+
+    log!(
+      "Treasury withdrawal of {} {} failed, restoring balance",
+      amount.0,
+      token_account_id.as_deref().unwrap_or("NEAR")
+    );
+    *self.treasury.balances.entry(token_account_id).or_insert(0) += amount.0;
+    false
+  }
+}
+
+impl MainHub {
+  /************
+   * INTERNAL *
+   ************/
+  // Moves the platform fee of a payment to the treasury and returns what is left for the creator,
+  // `payee_account_id` being the account whose plan sets the fee
+  pub(crate) fn internal_take_fee(
+    &mut self,
+    source: FeeSource,
+    payee_account_id: &AccountId,
+    token_account_id: &Option<AccountId>,
+    amount: u128,
+  ) -> u128 {
+    let fee = amount * self.internal_fee_bps(payee_account_id) as u128 / BASIS_POINTS;
+    if fee > 0 {
+      *self
+        .treasury
+        .balances
+        .entry(token_account_id.clone())
+        .or_insert(0) += fee;
+      *self
+        .treasury
+        .collected
+        .entry((source, token_account_id.clone()))
+        .or_insert(0) += fee;
+    }
+
+    amount - fee
+  }
+
+  fn internal_fee_bps(&self, account_id: &AccountId) -> u16 {
+    self
+      .account_plans
+      .get(account_id)
+      .and_then(|plan| self.plan_fees.get(&plan).copied())
+      .unwrap_or(self.platform_fee_bps)
+  }
+}
+
+fn assert_valid_plan(plan: &str) {
+  if plan.is_empty() || plan.len() > PLAN_MAX_LENGTH {
+    panic!("Plan must be between 1 and {} characters", PLAN_MAX_LENGTH);
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::item::Price;
+  use crate::test_utils::*;
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  const USDC: &str = "usdc.testnet";
+
+  // Alice sells her link for 1000 yocto or 1000 USDC, the platform takes 2.5%
+  fn generate_hub() -> MainHub {
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    let mut main = MainHub::default();
+    main.add_accepted_token(USDC.to_string());
+    main.set_platform_fee(250);
+    testing_env!(user_context("alice.testnet", None));
+    main.create("Hello".to_string(), "World".to_string(), None, Some(true));
+    add_sealed_link(&mut main);
+    main.set_link_prices(
+      1,
+      vec![
+        Price {
+          token_account_id: None,
+          amount: U128(1_000),
+        },
+        Price {
+          token_account_id: Some(USDC.to_string()),
+          amount: U128(1_000),
+        },
+      ],
+      None,
+      None,
+    );
+    main
+  }
+
+  fn buy_with_near(main: &mut MainHub) {
+    testing_env!(user_context("robert.testnet", Some(1_000)));
+    main.buy_link("alice.testnet".to_string(), 1, None);
+  }
+
+  fn buy_with_usdc(main: &mut MainHub) {
+    testing_env!(get_context("eve.testnet", USDC, None));
+    main.ft_on_transfer(
+      "eve.testnet".to_string(),
+      U128(1_000),
+      r#"{"owner_account_id":"alice.testnet","link_id":1}"#.to_string(),
+    );
+  }
+
+  #[test]
+  fn sales_pay_platform_fee_to_treasury() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    buy_with_near(&mut main);
+    buy_with_usdc(&mut main);
+    // Then
+    let balance = main.get_balance("alice.testnet".to_string());
+    assert_eq!(balance.near, U128(975));
+    assert_eq!(balance.tokens.get(USDC), Some(&U128(975)));
+    let treasury = main.get_treasury_balance();
+    assert_eq!(treasury.near, U128(25));
+    assert_eq!(treasury.tokens.get(USDC), Some(&U128(25)));
+  }
+
+  #[test]
+  fn plan_fee_overrides_platform_fee() {
+    // Given
+    let mut main = generate_hub();
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    main.set_plan_fee("premium".to_string(), Some(100));
+    main.set_account_plan("alice.testnet".to_string(), Some("premium".to_string()));
+    // When
+    buy_with_near(&mut main);
+    // Then
+    assert_eq!(main.get_platform_fee_for("alice.testnet".to_string()), 100);
+    assert_eq!(main.get_balance("alice.testnet".to_string()).near, U128(990));
+    assert_eq!(main.get_treasury_balance().near, U128(10));
+  }
+
+  #[test]
+  fn plan_without_override_pays_platform_fee() {
+    // Given
+    let mut main = generate_hub();
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    main.set_plan_fee("premium".to_string(), Some(100));
+    main.set_account_plan("alice.testnet".to_string(), Some("premium".to_string()));
+    main.set_plan_fee("premium".to_string(), None);
+    // When
+    buy_with_near(&mut main);
+    // Then
+    assert_eq!(main.get_account_plan("alice.testnet".to_string()), Some("premium".to_string()));
+    assert_eq!(main.get_balance("alice.testnet".to_string()).near, U128(975));
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the admin can call this method")]
+  fn set_account_plan_not_admin_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.set_account_plan("alice.testnet".to_string(), Some("premium".to_string()));
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  fn get_fees_collected_reports_each_source() {
+    // Given
+    let mut main = generate_hub();
+    buy_with_near(&mut main);
+    buy_with_usdc(&mut main);
+    // When
+    testing_env!(user_context("robert.testnet", Some(400)));
+    main.tip("alice.testnet".to_string(), None);
+    // Then
+    let fees = main.get_fees_collected();
+    assert_eq!(fees.len(), 3);
+    assert_eq!(
+      (fees[0].source, fees[0].token_account_id.clone(), fees[0].amount),
+      (FeeSource::Sale, None, U128(25))
+    );
+    assert_eq!(
+      (fees[1].source, fees[1].token_account_id.clone(), fees[1].amount),
+      (FeeSource::Sale, Some(USDC.to_string()), U128(25))
+    );
+    assert_eq!(
+      (fees[2].source, fees[2].token_account_id.clone(), fees[2].amount),
+      (FeeSource::Tip, None, U128(10))
+    );
+  }
+
+  #[test]
+  fn withdraw_treasury_sends_fees_to_admin() {
+    // Given
+    let mut main = generate_hub();
+    buy_with_near(&mut main);
+    // When
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    main.withdraw_treasury(None, None);
+    // Then
+    assert_eq!(main.get_treasury_balance().near, U128(0));
+    assert_eq!(main.get_fees_collected()[0].amount, U128(25), "Collected fees are kept");
+    let receipts = created_receipts();
+    assert!(receipts.contains(
+      r#""receiver_id":"contract.testnet","actions":[{"Transfer":{"deposit":25}}]"#
+    ));
+    assert!(receipts.contains("on_withdraw_treasury"));
+  }
+
+  #[test]
+  fn on_withdraw_treasury_failure_restores_balance() {
+    // Given
+    let mut main = generate_hub();
+    buy_with_usdc(&mut main);
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    main.withdraw_treasury(None, Some(USDC.to_string()));
+    // When
+    testing_env!(
+      callback_context(CONTRACT_ACCOUNT_ID),
+      Default::default(),
+      Default::default(),
+      Default::default(),
+      vec![PromiseResult::Failed]
+    );
+    let withdrawn = main.on_withdraw_treasury(Some(USDC.to_string()), U128(25));
+    // Then
+    assert!(!withdrawn);
+    assert_eq!(main.get_treasury_balance().tokens.get(USDC), Some(&U128(25)));
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the admin can call this method")]
+  fn withdraw_treasury_not_admin_panics() {
+    // Given
+    let mut main = generate_hub();
+    buy_with_near(&mut main);
+    // When
+    main.withdraw_treasury(None, None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Fee must be at most 10000 basis points")]
+  fn set_platform_fee_over_total_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    main.set_platform_fee(10_001);
+    // Then
+    // - Should panic
+  }
+}
@@ -0,0 +1,290 @@
+use near_sdk::{env, near_bindgen, AccountId};
+use serde::Serialize;
+use std::cmp::Reverse;
+// Crates
+use crate::item::ItemInfo;
+use crate::profiles::ProfileId;
+use crate::*;
+
+// The feed reads every followed profile, this keeps it within a view call's gas
+pub const FOLLOWING_MAX_COUNT: u64 = 100;
+// Prefixes of the per-account sets, followed by the account's hash
+const FOLLOWING_SET_PREFIX: &[u8] = b"o";
+const FOLLOWERS_SET_PREFIX: &[u8] = b"p";
+
+// A link of a followed profile, gated links are shown without their uri
+#[derive(Serialize)]
+pub struct FeedItem {
+  pub account_id: AccountId,
+  pub link: ItemInfo,
+}
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  pub fn get_followers(
+    &self,
+    account_id: AccountId,
+    from_index: u64,
+    limit: u64,
+  ) -> Vec<AccountId> {
+    page(self.followers.get(&account_id), from_index, limit)
+  }
+
+  pub fn get_following(
+    &self,
+    account_id: AccountId,
+    from_index: u64,
+    limit: u64,
+  ) -> Vec<AccountId> {
+    page(self.following.get(&account_id), from_index, limit)
+  }
+
+  pub fn get_followers_count(&self, account_id: AccountId) -> u64 {
+    self.followers.get(&account_id).map_or(0, |set| set.len())
+  }
+
+  pub fn get_following_count(&self, account_id: AccountId) -> u64 {
+    self.following.get(&account_id).map_or(0, |set| set.len())
+  }
+
+  // Links of the published profiles the account follows, most recent first
+  pub fn get_feed(&self, account_id: AccountId, from_index: u64, limit: u64) -> Vec<FeedItem> {
+    let following = match self.following.get(&account_id) {
+      Some(following) => following,
+      None => return vec![],
+    };
+
+    let mut feed: Vec<FeedItem> = vec![];
+    for followed in following.iter() {
+      match self.internal_get_profile(&followed, &None) {
+        Some(link3) if link3.is_published() => {
+          let attestations = self.internal_active_attestations(&ProfileId {
+            account_id: followed.clone(),
+            profile: None,
+          });
+          feed.extend(link3.links().iter().map(|item| FeedItem {
+            account_id: followed.clone(),
+            link: ItemInfo::map(item, !item.is_gated(), &attestations),
+          }));
+        }
+        _ => {}
+      }
+    }
+
+    feed.sort_by_key(|entry| Reverse(entry.link.created_at.0));
+    feed
+      .into_iter()
+      .skip(from_index as usize)
+      .take(limit as usize)
+      .collect()
+  }
+
+  /****************
+   * CALL METHODS *
+   ****************/
+  pub fn follow(&mut self, account_id: AccountId) {
+    let follower_id = env::signer_account_id();
+    if follower_id == account_id {
+      env::panic(b"Can't follow yourself");
+    }
+    match self.internal_get_profile(&account_id, &None) {
+      Some(link3) if link3.is_published() => {}
+      Some(_) => env::panic(b"This contract is not published"),
+      None => env::panic(b"Could not find link3 for this account."),
+    }
+    if self.get_following_count(follower_id.clone()) >= FOLLOWING_MAX_COUNT {
+      panic!("Can follow at most {} accounts", FOLLOWING_MAX_COUNT);
+    }
+
+    if !nested_set_insert(&mut self.following, FOLLOWING_SET_PREFIX, &follower_id, &account_id) {
+      env::panic(b"Already following this account");
+    }
+    nested_set_insert(&mut self.followers, FOLLOWERS_SET_PREFIX, &account_id, &follower_id);
+  }
+
+  pub fn unfollow(&mut self, account_id: AccountId) {
+    let follower_id = env::signer_account_id();
+    if !nested_set_remove(&mut self.following, &follower_id, &account_id) {
+      env::panic(b"Not following this account");
+    }
+    nested_set_remove(&mut self.followers, &account_id, &follower_id);
+  }
+}
+
+fn page(accounts: Option<UnorderedSet<AccountId>>, from_index: u64, limit: u64) -> Vec<AccountId> {
+  match accounts {
+    Some(accounts) => accounts
+      .as_vector()
+      .iter()
+      .skip(from_index as usize)
+      .take(limit as usize)
+      .collect(),
+    None => vec![],
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::*;
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  fn create(main: &mut MainHub, account_id: &str, is_published: bool) {
+    testing_env!(user_context(account_id, None));
+    main.create(
+      account_id.to_string(),
+      "Description".to_string(),
+      None,
+      Some(is_published),
+    );
+  }
+
+  fn add_link_at(main: &mut MainHub, account_id: &str, title: &str, block_timestamp: u64) {
+    let mut context = user_context(account_id, None);
+    context.block_timestamp = block_timestamp;
+    testing_env!(context);
+    main.add_link(
+      "uri".to_string(),
+      title.to_string(),
+      "description".to_string(),
+      None,
+      None,
+      None,
+    );
+  }
+
+  // Alice and Robert are published, Jane isn't
+  fn generate_hub() -> MainHub {
+    testing_env!(user_context("alice.testnet", None));
+    let mut main = MainHub::default();
+    create(&mut main, "alice.testnet", true);
+    create(&mut main, "robert.testnet", true);
+    create(&mut main, "jane.testnet", false);
+    main
+  }
+
+  fn follow(main: &mut MainHub, follower: &str, account_id: &str) {
+    testing_env!(user_context(follower, None));
+    main.follow(account_id.to_string());
+  }
+
+  #[test]
+  fn follow_updates_both_sides() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    follow(&mut main, "eve.testnet", "alice.testnet");
+    follow(&mut main, "eve.testnet", "robert.testnet");
+    follow(&mut main, "robert.testnet", "alice.testnet");
+    // Then
+    assert_eq!(
+      main.get_following("eve.testnet".to_string(), 0, 10),
+      vec!["alice.testnet".to_string(), "robert.testnet".to_string()]
+    );
+    assert_eq!(
+      main.get_followers("alice.testnet".to_string(), 1, 10),
+      vec!["robert.testnet".to_string()]
+    );
+    assert_eq!(main.get_followers_count("alice.testnet".to_string()), 2);
+    assert_eq!(main.get_following_count("eve.testnet".to_string()), 2);
+  }
+
+  #[test]
+  fn unfollow_updates_both_sides() {
+    // Given
+    let mut main = generate_hub();
+    follow(&mut main, "eve.testnet", "alice.testnet");
+    // When
+    main.unfollow("alice.testnet".to_string());
+    // Then
+    assert!(main.get_following("eve.testnet".to_string(), 0, 10).is_empty());
+    assert_eq!(main.get_followers_count("alice.testnet".to_string()), 0);
+  }
+
+  #[test]
+  #[should_panic(expected = "Already following this account")]
+  fn follow_twice_panics() {
+    // Given
+    let mut main = generate_hub();
+    follow(&mut main, "eve.testnet", "alice.testnet");
+    // When
+    follow(&mut main, "eve.testnet", "alice.testnet");
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Not following this account")]
+  fn unfollow_not_followed_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    testing_env!(user_context("eve.testnet", None));
+    main.unfollow("alice.testnet".to_string());
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Can't follow yourself")]
+  fn follow_self_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    follow(&mut main, "alice.testnet", "alice.testnet");
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "This contract is not published")]
+  fn follow_unpublished_profile_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    follow(&mut main, "eve.testnet", "jane.testnet");
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  fn feed_merges_followed_links_most_recent_first() {
+    // Given
+    let mut main = generate_hub();
+    add_link_at(&mut main, "alice.testnet", "alice first", 1);
+    add_link_at(&mut main, "robert.testnet", "robert first", 2);
+    add_link_at(&mut main, "alice.testnet", "alice second", 3);
+    follow(&mut main, "eve.testnet", "alice.testnet");
+    follow(&mut main, "eve.testnet", "robert.testnet");
+    // When
+    let feed = main.get_feed("eve.testnet".to_string(), 0, 10);
+    // Then
+    let titles: Vec<String> = feed.iter().map(|entry| entry.link.title.clone()).collect();
+    assert_eq!(titles, vec!["alice second", "robert first", "alice first"]);
+    assert_eq!(feed[1].account_id, "robert.testnet".to_string());
+    assert_eq!(main.get_feed("eve.testnet".to_string(), 1, 1)[0].link.title, "robert first");
+  }
+
+  #[test]
+  fn feed_skips_unpublished_profiles() {
+    // Given
+    let mut main = generate_hub();
+    add_link_at(&mut main, "alice.testnet", "alice link", 1);
+    add_link_at(&mut main, "robert.testnet", "robert link", 2);
+    follow(&mut main, "eve.testnet", "alice.testnet");
+    follow(&mut main, "eve.testnet", "robert.testnet");
+    // When
+    testing_env!(user_context("robert.testnet", None));
+    main.update_published_status(false, None);
+    // Then
+    let feed = main.get_feed("eve.testnet".to_string(), 0, 10);
+    assert_eq!(feed.len(), 1);
+    assert_eq!(feed[0].link.title, "alice link".to_string());
+  }
+}
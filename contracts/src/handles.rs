@@ -0,0 +1,330 @@
+use near_sdk::{env, near_bindgen, AccountId};
+// Crates
+use crate::link3::Link3;
+use crate::*;
+
+const HANDLE_MIN_LENGTH: usize = 3;
+const HANDLE_MAX_LENGTH: usize = 30;
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  pub fn get_by_handle(&self, handle: String) -> Option<Link3> {
+    self
+      .handles
+      .get(&normalize_handle(&handle))
+      .and_then(|account_id| self.internal_get_profile(&account_id, &None))
+  }
+
+  pub fn get_handle(&self, account_id: AccountId) -> Option<String> {
+    self.account_handles.get(&account_id)
+  }
+
+  pub fn is_handle_available(&self, handle: String) -> bool {
+    let handle = normalize_handle(&handle);
+    !self.reserved_handles.contains(&handle) && self.handles.get(&handle).is_none()
+  }
+
+  pub fn get_reserved_handles(&self) -> Vec<String> {
+    self.reserved_handles.to_vec()
+  }
+
+  /****************
+   * CALL METHODS *
+   ****************/
+  pub fn claim_handle(&mut self, handle: String) -> String {
+    let account_id = env::signer_account_id();
+    if self.account_handles.get(&account_id).is_some() {
+      env::panic(b"Account already has a handle, rename it instead");
+    }
+
+    self.internal_claim_handle(&account_id, &handle)
+  }
+
+  pub fn rename_handle(&mut self, handle: String) -> String {
+    let account_id = env::signer_account_id();
+    let old_handle = self
+      .account_handles
+      .get(&account_id)
+      .unwrap_or_else(|| env::panic(b"Account has no handle to rename"));
+
+    let new_handle = self.internal_claim_handle(&account_id, &handle);
+    if new_handle != old_handle {
+      self.handles.remove(&old_handle);
+    }
+    new_handle
+  }
+
+  pub fn release_handle(&mut self) {
+    if self.internal_release_handle(&env::signer_account_id()).is_none() {
+      env::panic(b"Account has no handle to release");
+    }
+  }
+
+  // Reserving doesn't take a handle away from whoever already claimed it
+  pub fn reserve_handle(&mut self, handle: String) {
+    self.assert_admin();
+
+    self.reserved_handles.insert(&normalize_handle(&handle));
+  }
+
+  pub fn unreserve_handle(&mut self, handle: String) {
+    self.assert_admin();
+
+    self.reserved_handles.remove(&normalize_handle(&handle));
+  }
+}
+
+impl MainHub {
+  /************
+   * INTERNAL *
+   ************/
+  // Frees the account's handle for anyone to claim, None when it had none
+  pub(crate) fn internal_release_handle(&mut self, account_id: &AccountId) -> Option<String> {
+    let handle = self.account_handles.remove(account_id)?;
+    self.handles.remove(&handle);
+    Some(handle)
+  }
+
+  // The handle names the account's default profile, so it goes wherever that profile goes.
+  // It's released when the receiver already has a handle of its own
+  pub(crate) fn internal_move_handle(&mut self, sender_id: &AccountId, receiver_id: &AccountId) {
+    let handle = match self.account_handles.remove(sender_id) {
+      Some(handle) => handle,
+      None => return,
+    };
+
+    if self.account_handles.get(receiver_id).is_some() {
+      self.handles.remove(&handle);
+    } else {
+      self.handles.insert(&handle, receiver_id);
+      self.account_handles.insert(receiver_id, &handle);
+    }
+  }
+
+  fn internal_claim_handle(&mut self, account_id: &AccountId, handle: &str) -> String {
+    if self.internal_get_profile(account_id, &None).is_none() {
+      env::panic(b"Could not find link3 for this account.");
+    }
+
+    let handle = normalize_handle(handle);
+    if self.reserved_handles.contains(&handle) {
+      env::panic(b"Handle is reserved");
+    }
+    match self.handles.get(&handle) {
+      Some(owner) if &owner != account_id => env::panic(b"Handle is already taken"),
+      _ => {}
+    }
+
+    self.handles.insert(&handle, account_id);
+    self.account_handles.insert(account_id, &handle);
+    handle
+  }
+}
+
+// Lowercases the handle and drops the leading @, panics when it isn't a valid slug
+pub fn normalize_handle(handle: &str) -> String {
+  let handle = handle.strip_prefix('@').unwrap_or(handle).to_lowercase();
+
+  if handle.len() < HANDLE_MIN_LENGTH {
+    panic!(
+      "Handle must be at least {} characters long",
+      HANDLE_MIN_LENGTH
+    );
+  }
+
+  if handle.len() > HANDLE_MAX_LENGTH {
+    panic!("Handle must be at most {} characters long", HANDLE_MAX_LENGTH);
+  }
+
+  if !handle
+    .chars()
+    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+  {
+    panic!("Handle can only contain letters, numbers, _ and -");
+  }
+
+  handle
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::*;
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  fn generate_hub() -> MainHub {
+    testing_env!(user_context("alice.testnet", None));
+    let mut main = MainHub::default();
+    main.create("Hello".to_string(), "World".to_string(), None, Some(true));
+    testing_env!(user_context("robert.testnet", None));
+    main.create("Hello".to_string(), "World".to_string(), None, Some(true));
+    testing_env!(user_context("alice.testnet", None));
+    main
+  }
+
+  #[test]
+  fn deleting_default_profile_releases_handle() {
+    // Given
+    let mut main = generate_hub();
+    main.claim_handle("joaquim".to_string());
+    // When
+    main.delete_profile(None);
+    // Then
+    assert_eq!(main.get_handle("alice.testnet".to_string()), None);
+    assert!(main.is_handle_available("joaquim".to_string()));
+    testing_env!(user_context("robert.testnet", None));
+    main.claim_handle("joaquim".to_string());
+    let link3 = main.get_by_handle("joaquim".to_string()).unwrap();
+    assert_eq!(link3.owner_account_id(), &"robert.testnet".to_string());
+  }
+
+  #[test]
+  fn normalize_handle_lowercases_and_strips_at() {
+    assert_eq!(normalize_handle("@Joaquim"), "joaquim".to_string());
+    assert_eq!(normalize_handle("omen_dao-1"), "omen_dao-1".to_string());
+  }
+
+  #[test]
+  #[should_panic(expected = "Handle must be at least 3 characters long")]
+  fn normalize_handle_too_short_panics() {
+    normalize_handle("@ab");
+  }
+
+  #[test]
+  #[should_panic(expected = "Handle must be at most 30 characters long")]
+  fn normalize_handle_too_long_panics() {
+    normalize_handle("abcdefghijklmnopqrstuvwxyz12345");
+  }
+
+  #[test]
+  #[should_panic(expected = "Handle can only contain letters, numbers, _ and -")]
+  fn normalize_handle_with_invalid_chars_panics() {
+    normalize_handle("joaquim.near");
+  }
+
+  #[test]
+  fn claim_handle_maps_handle_to_profile() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.claim_handle("@Joaquim".to_string());
+    // Then
+    assert!(main.get_by_handle("joaquim".to_string()).is_some());
+    assert!(main.get_by_handle("@JOAQUIM".to_string()).is_some());
+    assert_eq!(
+      main.get_handle("alice.testnet".to_string()),
+      Some("joaquim".to_string())
+    );
+  }
+
+  #[test]
+  #[should_panic(expected = "Could not find link3 for this account.")]
+  fn claim_handle_without_profile_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    testing_env!(user_context("jane.testnet", None));
+    main.claim_handle("jane".to_string());
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Handle is already taken")]
+  fn claim_taken_handle_with_other_case_panics() {
+    // Given
+    let mut main = generate_hub();
+    main.claim_handle("joaquim".to_string());
+    // When
+    testing_env!(user_context("robert.testnet", None));
+    main.claim_handle("JoAquim".to_string());
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Account already has a handle, rename it instead")]
+  fn claim_second_handle_panics() {
+    // Given
+    let mut main = generate_hub();
+    main.claim_handle("joaquim".to_string());
+    // When
+    main.claim_handle("ley".to_string());
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  fn rename_handle_frees_old_handle() {
+    // Given
+    let mut main = generate_hub();
+    main.claim_handle("joaquim".to_string());
+    // When
+    main.rename_handle("ley".to_string());
+    // Then
+    assert!(main.get_by_handle("joaquim".to_string()).is_none());
+    assert!(main.get_by_handle("ley".to_string()).is_some());
+    assert!(main.is_handle_available("joaquim".to_string()));
+  }
+
+  #[test]
+  fn release_handle_frees_handle() {
+    // Given
+    let mut main = generate_hub();
+    main.claim_handle("joaquim".to_string());
+    // When
+    main.release_handle();
+    // Then
+    assert!(main.get_handle("alice.testnet".to_string()).is_none());
+    testing_env!(user_context("robert.testnet", None));
+    main.claim_handle("joaquim".to_string());
+    assert_eq!(
+      main.get_handle("robert.testnet".to_string()),
+      Some("joaquim".to_string())
+    );
+  }
+
+  #[test]
+  #[should_panic(expected = "Handle is reserved")]
+  fn claim_reserved_handle_panics() {
+    // Given
+    let mut main = generate_hub();
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    main.reserve_handle("@Admin".to_string());
+    // When
+    testing_env!(user_context("alice.testnet", None));
+    main.claim_handle("admin".to_string());
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  fn unreserve_handle_makes_it_available() {
+    // Given
+    let mut main = generate_hub();
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    main.reserve_handle("admin".to_string());
+    assert_eq!(main.get_reserved_handles(), vec!["admin".to_string()]);
+    // When
+    main.unreserve_handle("admin".to_string());
+    // Then
+    assert!(main.is_handle_available("admin".to_string()));
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the admin can call this method")]
+  fn reserve_handle_not_admin_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.reserve_handle("admin".to_string());
+    // Then
+    // - Should panic
+  }
+}
@@ -1,16 +1,124 @@
 // To conserve gas, efficient serialization is achieved through Borsh (http://borsh.io/)
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::{log, PanicOnDefault};
-use serde::Serialize;
+use near_sdk::json_types::{Base64VecU8, U128, U64};
+use near_sdk::{env, log, AccountId, PanicOnDefault};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+// Crates
+use crate::attestations::{Attestation, AttestationKind};
+use crate::metadata::insert_metadata;
+use crate::tags::normalize_tags;
 
 // #[near_bindgen]
+// What only readers may see stays out of the JSON, views hand out `ItemInfo` instead
 #[derive(BorshSerialize, BorshDeserialize, Clone, PanicOnDefault, Serialize, Debug)]
 pub struct Item {
   id: u64,
+  #[serde(skip_serializing)]
   uri: String,
   title: String,
   description: String,
   image_uri: Option<String>,
+  // Encrypted uri of a sealed item, `uri` is left empty so state only holds ciphertext
+  #[serde(skip_serializing)]
+  sealed_uri: Option<Vec<u8>>,
+  // Account -> content key of the sealed uri, wrapped for the account's encryption key
+  #[serde(skip_serializing)]
+  key_envelopes: HashMap<AccountId, Vec<u8>>,
+  // Block timestamp (ns) of the creation, orders the feed
+  created_at: u64,
+  token_gate: Option<TokenGate>,
+  prices: Vec<Price>,
+  // How long a purchase unlocks the item (ns), forever when None
+  access_duration: Option<u64>,
+  // Lowest membership tier of the profile unlocking the item
+  min_tier: Option<u64>,
+  // Beneficiaries of the sales, the owner gets everything when empty
+  splits: Vec<Split>,
+  metadata: HashMap<String, String>,
+  // Normalized tags, indexed by the hub while the profile is published
+  tags: Vec<String>,
+  // Purchases mint a transferable pass instead of granting the buyer, when set
+  pass: Option<PassSettings>,
+  // Passes minted so far, burnt ones included, what the supply cap counts
+  passes_issued: u64,
+  // Pass token id -> current holder
+  #[serde(skip_serializing)]
+  pass_holders: HashMap<String, AccountId>,
+  // Limited supply of purchases sold within a window, when set
+  ticket: Option<TicketSettings>,
+  // Buyers in order of their first ticket, with how many tickets they bought
+  #[serde(skip_serializing)]
+  ticket_holders: Vec<(AccountId, u32)>,
+  // Last English auction, the highest bid is held by the hub until it's settled
+  #[serde(skip_serializing)]
+  auction: Option<Auction>,
+}
+
+// Unlocks the item for holders of at least `min_balance` of a NEP-141 token
+#[derive(BorshSerialize, BorshDeserialize, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct TokenGate {
+  pub token_account_id: AccountId,
+  pub min_balance: U128,
+}
+
+// Price to unlock the item, paid in NEAR when there's no `token_account_id`
+#[derive(BorshSerialize, BorshDeserialize, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Price {
+  pub token_account_id: Option<AccountId>,
+  pub amount: U128,
+}
+
+// Access pass minted on each purchase of the item, an NFT whose holder can read the uri
+#[derive(BorshSerialize, BorshDeserialize, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct PassSettings {
+  // Most passes that can ever be issued, unlimited when None
+  pub supply_cap: Option<u64>,
+  // Share of each resale paid back to the owner (NEP-199), in basis points
+  pub royalty_bps: u16,
+}
+
+// Sells at most `supply` purchases of the item between `sale_start` and `sale_end` (ns)
+#[derive(BorshSerialize, BorshDeserialize, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct TicketSettings {
+  pub supply: u64,
+  pub sale_start: Option<U64>,
+  pub sale_end: Option<U64>,
+  pub max_per_buyer: u32,
+}
+
+// Expiry of the grants given by purchases that never expire
+pub const PERMANENT_ACCESS: u64 = u64::MAX;
+
+// Bids in the last `extension` (ns) before `ends_at` push the end to `extension` after the bid
+#[derive(BorshSerialize, BorshDeserialize, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct AuctionSettings {
+  pub reserve_price: U128,
+  pub min_increment: U128,
+  pub ends_at: U64,
+  pub extension: U64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Bid {
+  pub account_id: AccountId,
+  pub amount: U128,
+  pub placed_at: U64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Auction {
+  pub settings: AuctionSettings,
+  pub highest_bid: Option<Bid>,
+  // Settled auctions stay on the item, keeping it gated for everyone but the winner
+  pub settled: bool,
+}
+
+// Share of the item's sales going to `account_id`, in basis points
+#[derive(BorshSerialize, BorshDeserialize, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Split {
+  pub account_id: AccountId,
+  pub bps: u16,
 }
 
 // Core Logic/Implementation
@@ -32,6 +140,22 @@ impl Item {
       title,
       description,
       image_uri,
+      sealed_uri: None,
+      key_envelopes: HashMap::new(),
+      created_at: env::block_timestamp(),
+      token_gate: None,
+      prices: vec![],
+      access_duration: None,
+      min_tier: None,
+      splits: vec![],
+      metadata: HashMap::new(),
+      tags: vec![],
+      pass: None,
+      passes_issued: 0,
+      pass_holders: HashMap::new(),
+      ticket: None,
+      ticket_holders: vec![],
+      auction: None,
     }
   }
 
@@ -42,18 +166,255 @@ impl Item {
     self.id
   }
 
-  pub fn read(&self) -> ItemInfo {
-    // Not verifing access for v1, if it is public it has access
-    // Changing here so there is no need to change the ItemInfo's implementation
-    // let has_access = !self.is_premium || Item::has_access(self);
+  pub fn uri(&self) -> &String {
+    &self.uri
+  }
+
+  pub fn title(&self) -> &String {
+    &self.title
+  }
+
+  pub fn image_uri(&self) -> Option<&String> {
+    self.image_uri.as_ref()
+  }
+
+  pub fn created_at(&self) -> u64 {
+    self.created_at
+  }
+
+  pub fn tags(&self) -> &Vec<String> {
+    &self.tags
+  }
+
+  pub fn token_gate(&self) -> Option<&TokenGate> {
+    self.token_gate.as_ref()
+  }
+
+  // Price of the item in the given currency, if it can be bought with it
+  pub fn price(&self, token_account_id: &Option<AccountId>) -> Option<u128> {
+    self
+      .prices
+      .iter()
+      .find(|price| &price.token_account_id == token_account_id)
+      .map(|price| price.amount.0)
+  }
+
+  pub fn access_duration(&self) -> Option<u64> {
+    self.access_duration
+  }
+
+  // Expiry a purchase made now gives an account whose access runs until `current` (ns),
+  // renting early adds to the remaining time
+  pub fn purchase_expiry(&self, current: Option<u64>) -> u64 {
+    match self.access_duration {
+      Some(access_duration) => {
+        let current = current.unwrap_or(0);
+        current.max(env::block_timestamp()).saturating_add(access_duration)
+      }
+      None => PERMANENT_ACCESS,
+    }
+  }
+
+  pub fn min_tier(&self) -> Option<u64> {
+    self.min_tier
+  }
+
+  pub fn pass(&self) -> Option<&PassSettings> {
+    self.pass.as_ref()
+  }
 
-    let has_access = true;
-    ItemInfo::map(self, has_access)
+  pub fn passes_issued(&self) -> u64 {
+    self.passes_issued
+  }
+
+  pub fn pass_token_ids(&self) -> impl Iterator<Item = &String> {
+    self.pass_holders.keys()
+  }
+
+  pub fn ticket(&self) -> Option<&TicketSettings> {
+    self.ticket.as_ref()
+  }
+
+  pub fn tickets_sold(&self) -> u64 {
+    self
+      .ticket_holders
+      .iter()
+      .map(|(_, count)| *count as u64)
+      .sum()
+  }
+
+  pub fn tickets_of(&self, account_id: &AccountId) -> u32 {
+    self
+      .ticket_holders
+      .iter()
+      .find(|(holder, _)| holder == account_id)
+      .map_or(0, |(_, count)| *count)
+  }
+
+  pub fn ticket_holders(&self) -> &Vec<(AccountId, u32)> {
+    &self.ticket_holders
+  }
+
+  pub fn auction(&self) -> Option<&Auction> {
+    self.auction.as_ref()
+  }
+
+  pub fn is_up_for_auction(&self) -> bool {
+    self.auction.as_ref().is_some_and(|auction| !auction.settled)
+  }
+
+  pub fn splits(&self) -> &Vec<Split> {
+    &self.splits
+  }
+
+  pub fn is_sealed(&self) -> bool {
+    self.sealed_uri.is_some()
+  }
+
+  pub fn key_envelope(&self, account_id: &AccountId) -> Option<&Vec<u8>> {
+    self.key_envelopes.get(account_id)
+  }
+
+  pub fn is_gated(&self) -> bool {
+    self.token_gate.is_some()
+      || !self.prices.is_empty()
+      || self.min_tier.is_some()
+      || self.auction.is_some()
+  }
+
+  // `is_unlocked` tells whether the reader holds a grant or a membership reaching the item's
+  // tier, an anonymous reader only sees ungated items
+  pub fn read(
+    &self,
+    reader_account_id: Option<&AccountId>,
+    is_unlocked: bool,
+    attestations: &[Attestation],
+  ) -> ItemInfo {
+    let has_access = !self.is_gated()
+      || is_unlocked
+      || reader_account_id.is_some_and(|reader| self.holds_pass(reader));
+    let mut info = ItemInfo::map(self, has_access, attestations);
+    if has_access {
+      info.key_envelope = reader_account_id
+        .and_then(|reader| self.key_envelopes.get(reader))
+        .map(|envelope| Base64VecU8(envelope.clone()));
+    }
+    info
+  }
+
+  // Grants live on the hub, the item only knows who holds its passes
+  pub fn holds_pass(&self, account_id: &AccountId) -> bool {
+    self.pass_holders.values().any(|holder| holder == account_id)
   }
 
   /****************
    * CALL METHODS *
    ****************/
+  // A sealed item keeps its ciphertext and takes an empty uri, it only gets a plaintext
+  // one through `unseal`
+  pub fn update(
+    &mut self,
+    uri: String,
+    title: String,
+    description: String,
+    image_uri: Option<String>,
+  ) {
+    if self.is_sealed() {
+      if !uri.is_empty() {
+        env::panic(b"Unseal the link to give it a plaintext uri");
+      }
+    } else {
+      self.uri = uri;
+    }
+    self.title = title;
+    self.description = description;
+    self.image_uri = image_uri;
+  }
+
+  // Envelopes of the previous content key can't open the new ciphertext, so they go
+  pub fn seal(&mut self, sealed_uri: Vec<u8>) {
+    self.uri = String::new();
+    self.sealed_uri = Some(sealed_uri);
+    self.key_envelopes.clear();
+  }
+
+  pub fn unseal(&mut self, uri: String) {
+    self.uri = uri;
+    self.sealed_uri = None;
+    self.key_envelopes.clear();
+  }
+
+  pub fn set_key_envelope(&mut self, account_id: AccountId, key_envelope: Vec<u8>) {
+    self.key_envelopes.insert(account_id, key_envelope);
+  }
+
+  pub fn set_token_gate(&mut self, token_gate: Option<TokenGate>) {
+    self.token_gate = token_gate;
+  }
+
+  pub fn set_prices(&mut self, prices: Vec<Price>) {
+    self.prices = prices;
+  }
+
+  pub fn set_access_duration(&mut self, access_duration: Option<u64>) {
+    self.access_duration = access_duration;
+  }
+
+  pub fn set_min_tier(&mut self, min_tier: Option<u64>) {
+    self.min_tier = min_tier;
+  }
+
+  pub fn set_pass(&mut self, pass: Option<PassSettings>) {
+    self.pass = pass;
+  }
+
+  pub fn issue_pass(&mut self, token_id: String, account_id: AccountId) {
+    self.passes_issued += 1;
+    self.pass_holders.insert(token_id, account_id);
+  }
+
+  // Passes the item didn't issue are left out, a recreated link can't be unlocked by the
+  // pass of the one it took the id of
+  pub fn set_pass_holder(&mut self, token_id: String, account_id: AccountId) {
+    if let Some(holder) = self.pass_holders.get_mut(&token_id) {
+      *holder = account_id;
+    }
+  }
+
+  pub fn set_ticket(&mut self, ticket: Option<TicketSettings>) {
+    self.ticket = ticket;
+  }
+
+  pub fn set_auction(&mut self, auction: Option<Auction>) {
+    self.auction = auction;
+  }
+
+  pub fn add_ticket(&mut self, account_id: AccountId) {
+    match self
+      .ticket_holders
+      .iter_mut()
+      .find(|(holder, _)| holder == &account_id)
+    {
+      Some((_, count)) => *count += 1,
+      None => self.ticket_holders.push((account_id, 1)),
+    }
+  }
+
+  pub fn set_splits(&mut self, splits: Vec<Split>) {
+    self.splits = splits;
+  }
+
+  pub fn set_metadata(&mut self, key: String, value: String) {
+    insert_metadata(&mut self.metadata, key, value);
+  }
+
+  pub fn remove_metadata(&mut self, key: &str) {
+    self.metadata.remove(key);
+  }
+
+  pub fn set_tags(&mut self, tags: Vec<String>) {
+    self.tags = normalize_tags(tags);
+  }
 
   /************
    * INTERNAL *
@@ -69,13 +430,37 @@ pub struct ItemInfo {
   pub title: String,
   pub description: String,
   pub image: Option<String>,
+  pub is_sealed: bool,
+  pub is_verified: bool,
+  pub verified_domain: Option<String>,
+  // Encrypted uri and the signer's wrapped content key, when the item is sealed
+  pub sealed_uri: Option<Base64VecU8>,
+  pub key_envelope: Option<Base64VecU8>,
+  pub created_at: U64,
+  pub token_gate: Option<TokenGate>,
+  pub prices: Vec<Price>,
+  pub access_duration: Option<U64>,
+  pub min_tier: Option<u64>,
+  pub pass: Option<PassSettings>,
+  pub passes_issued: u64,
+  pub ticket: Option<TicketSettings>,
+  pub tickets_sold: u64,
+  pub auction: Option<Auction>,
+  pub metadata: HashMap<String, String>,
+  pub tags: Vec<String>,
 }
 
 impl ItemInfo {
-  pub fn map(from: &Item, has_access: bool) -> Self {
+  // `attestations` are the profile's active ones, the item is verified while one of them
+  // proves its uri (e.g. the GitHub handle), along with the domain for websites
+  pub fn map(from: &Item, has_access: bool, attestations: &[Attestation]) -> Self {
+    let proofs: Vec<&Attestation> = attestations
+      .iter()
+      .filter(|attestation| attestation.kind.matches_uri(&attestation.subject, &from.uri))
+      .collect();
     ItemInfo {
       id: from.id,
-      uri: if has_access {
+      uri: if has_access && !from.is_sealed() {
         Some(from.uri.clone())
       } else {
         None
@@ -87,6 +472,30 @@ impl ItemInfo {
       } else {
         None // from.image_preview_uri.clone()
       },
+      is_sealed: from.is_sealed(),
+      is_verified: !proofs.is_empty(),
+      verified_domain: proofs
+        .iter()
+        .find(|attestation| attestation.kind == AttestationKind::Domain)
+        .map(|attestation| attestation.subject.to_lowercase()),
+      sealed_uri: if has_access {
+        from.sealed_uri.clone().map(Base64VecU8)
+      } else {
+        None
+      },
+      key_envelope: None,
+      created_at: U64(from.created_at),
+      token_gate: from.token_gate.clone(),
+      prices: from.prices.clone(),
+      access_duration: from.access_duration.map(U64),
+      min_tier: from.min_tier,
+      pass: from.pass.clone(),
+      passes_issued: from.passes_issued,
+      ticket: from.ticket.clone(),
+      tickets_sold: from.tickets_sold(),
+      auction: from.auction.clone(),
+      metadata: from.metadata.clone(),
+      tags: from.tags.clone(),
     }
   }
 }
@@ -174,7 +583,7 @@ mod tests {
     testing_env!(context);
     let item = generate_item(123);
     // When
-    let item_info = ItemInfo::map(&item, true);
+    let item_info = ItemInfo::map(&item, true, &[]);
     // Then
     assert_eq!(item_info.id, item.id);
     assert_eq!(item_info.uri, Some(item.uri));
@@ -183,5 +592,118 @@ mod tests {
     assert_eq!(item_info.image, item.image_uri);
   }
 
-  // Missing test item_info maps correctly when has_access is false
+  #[test]
+  fn item_info_maps_metadata_without_access() {
+    // Given
+    let context = get_context(vec![], false);
+    testing_env!(context);
+    let mut item = generate_item(123);
+    item.set_metadata("badge".to_string(), "new".to_string());
+    // When
+    let item_info = ItemInfo::map(&item, false, &[]);
+    // Then
+    assert_eq!(item_info.metadata.get("badge"), Some(&"new".to_string()));
+  }
+
+  #[test]
+  fn item_info_hides_uri_and_image_without_access() {
+    // Given
+    let context = get_context(vec![], false);
+    testing_env!(context);
+    let item = generate_item(123);
+    // When
+    let item_info = ItemInfo::map(&item, false, &[]);
+    // Then
+    assert_eq!(item_info.uri, None);
+    assert_eq!(item_info.image, None);
+    assert_eq!(item_info.title, item.title);
+  }
+
+  fn generate_gate() -> TokenGate {
+    TokenGate {
+      token_account_id: "token.testnet".to_string(),
+      min_balance: U128(100),
+    }
+  }
+
+  #[test]
+  fn read_token_gated_item_without_grant_hides_uri() {
+    // Given
+    let context = get_context(vec![], false);
+    testing_env!(context);
+    let mut item = generate_item(123);
+    item.set_token_gate(Some(generate_gate()));
+    // When
+    let item_info = item.read(None, false, &[]);
+    // Then
+    assert_eq!(item_info.uri, None);
+    assert_eq!(item_info.token_gate, Some(generate_gate()));
+  }
+
+  #[test]
+  fn read_token_gated_item_unlocked_shows_uri() {
+    // Given
+    let context = get_context(vec![], false);
+    testing_env!(context);
+    let mut item = generate_item(123);
+    // When
+    item.set_token_gate(Some(generate_gate()));
+    // Then
+    let reader = "robert.testnet".to_string();
+    assert_eq!(item.read(Some(&reader), true, &[]).uri, Some("https://google.com".to_string()));
+  }
+
+  #[test]
+  fn read_priced_item_without_purchase_hides_uri() {
+    // Given
+    let context = get_context(vec![], false);
+    testing_env!(context);
+    let mut item = generate_item(123);
+    // When
+    item.set_prices(vec![Price {
+      token_account_id: None,
+      amount: U128(10),
+    }]);
+    // Then
+    assert!(item.is_gated());
+    assert_eq!(item.read(None, false, &[]).uri, None);
+  }
+
+  #[test]
+  fn read_tier_item_shows_uri_to_members_only() {
+    // Given
+    let context = get_context(vec![], false);
+    testing_env!(context);
+    let mut item = generate_item(123);
+    // When
+    item.set_min_tier(Some(1));
+    // Then
+    assert!(item.is_gated());
+    assert_eq!(item.read(None, false, &[]).uri, None);
+    assert_eq!(item.read(None, true, &[]).uri, Some("https://google.com".to_string()));
+    assert_eq!(item.read(None, true, &[]).min_tier, Some(1));
+  }
+
+  #[test]
+  fn price_returns_amount_for_currency() {
+    // Given
+    let context = get_context(vec![], false);
+    testing_env!(context);
+    let mut item = generate_item(123);
+    // When
+    item.set_prices(vec![
+      Price {
+        token_account_id: None,
+        amount: U128(10),
+      },
+      Price {
+        token_account_id: Some("usdc.testnet".to_string()),
+        amount: U128(5),
+      },
+    ]);
+    // Then
+    assert_eq!(item.price(&None), Some(10));
+    assert_eq!(item.price(&Some("usdc.testnet".to_string())), Some(5));
+    assert_eq!(item.price(&Some("dai.testnet".to_string())), None);
+  }
 }
@@ -0,0 +1,340 @@
+use near_sdk::json_types::U128;
+use near_sdk::{env, log, near_bindgen, AccountId, Gas, Promise, PromiseResult};
+use serde::Serialize;
+use std::collections::HashMap;
+// Crates
+use crate::external::{ext_ft, ext_self};
+use crate::*;
+
+const GAS_FOR_FT_TRANSFER: Gas = 10_000_000_000_000;
+const GAS_FOR_ON_WITHDRAW: Gas = 10_000_000_000_000;
+const ONE_YOCTO: u128 = 1;
+const NO_DEPOSIT: u128 = 0;
+
+// What an account earned on the hub and can withdraw, per currency
+#[derive(Serialize, Debug)]
+pub struct AccountBalance {
+  pub near: U128,
+  pub tokens: HashMap<AccountId, U128>,
+}
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  pub fn get_balance(&self, account_id: AccountId) -> AccountBalance {
+    AccountBalance {
+      near: U128(self.near_balances.get(&account_id).unwrap_or(0)),
+      tokens: self
+        .token_balances
+        .get(&account_id)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(_, amount)| *amount > 0)
+        .map(|(token_account_id, amount)| (token_account_id, U128(amount)))
+        .collect(),
+    }
+  }
+
+  pub fn get_accepted_tokens(&self) -> Vec<AccountId> {
+    self.accepted_tokens.to_vec()
+  }
+
+  /****************
+   * CALL METHODS *
+   ****************/
+  // Withdraws the caller's NEAR, or tokens when `token_account_id` is set,
+  // the whole balance when no amount is set
+  pub fn withdraw(&mut self, amount: Option<U128>, token_account_id: Option<AccountId>) -> Promise {
+    let account_id = env::predecessor_account_id();
+    let balance = self.internal_balance(&account_id, &token_account_id);
+    let amount = amount.map_or(balance, |amount| amount.0);
+
+    if amount == 0 {
+      env::panic(b"Nothing to withdraw");
+    }
+    if amount > balance {
+      env::panic(b"Not enough balance to withdraw");
+    }
+
+    // Deducted up front, `on_withdraw` gives it back if the transfer fails
+    self.internal_set_balance(&account_id, &token_account_id, balance - amount);
+
+    let transfer = match &token_account_id {
+      Some(token_account_id) => ext_ft::ft_transfer(
+        account_id.clone(),
+        U128(amount),
+        Some("Link3 withdrawal".to_string()),
+        token_account_id,
+        ONE_YOCTO,
+        GAS_FOR_FT_TRANSFER,
+      ),
+      None => Promise::new(account_id.clone()).transfer(amount),
+    };
+
+    transfer.then(ext_self::on_withdraw(
+      account_id,
+      token_account_id,
+      U128(amount),
+      &env::current_account_id(),
+      NO_DEPOSIT,
+      GAS_FOR_ON_WITHDRAW,
+    ))
+  }
+
+  pub fn add_accepted_token(&mut self, token_account_id: AccountId) {
+    self.assert_admin();
+    if !env::is_valid_account_id(token_account_id.as_bytes()) {
+      env::panic(b"Token account id is not valid");
+    }
+
+    self.accepted_tokens.insert(&token_account_id);
+  }
+
+  // Balances already earned in the token can still be withdrawn
+  pub fn remove_accepted_token(&mut self, token_account_id: AccountId) {
+    self.assert_admin();
+
+    self.accepted_tokens.remove(&token_account_id);
+  }
+
+  /*************
+   * CALLBACKS *
+   *************/
+  // Gives the amount back to the balance when the transfer failed
+  #[private]
+  pub fn on_withdraw(
+    &mut self,
+    account_id: AccountId,
+    token_account_id: Option<AccountId>,
+    amount: U128,
+  ) -> bool {
+    if let PromiseResult::Successful(_) = env::promise_result(0) {
+      return true;
+    }
+
+    log!(
+      "Withdrawal of {} {} failed, restoring balance",
+      amount.0,
+      token_account_id.as_deref().unwrap_or("NEAR")
+    );
+    self.internal_credit(&account_id, &token_account_id, amount.0);
+    false
+  }
+}
+
+impl MainHub {
+  /************
+   * INTERNAL *
+   ************/
+  // `token_account_id` is None for NEAR
+  pub(crate) fn internal_credit(
+    &mut self,
+    account_id: &AccountId,
+    token_account_id: &Option<AccountId>,
+    amount: u128,
+  ) {
+    let balance = self.internal_balance(account_id, token_account_id);
+    self.internal_set_balance(account_id, token_account_id, balance + amount);
+  }
+
+  pub(crate) fn assert_accepted_token(&self, token_account_id: &AccountId) {
+    if !self.accepted_tokens.contains(token_account_id) {
+      env::panic(b"Token is not accepted");
+    }
+  }
+
+  fn internal_balance(&self, account_id: &AccountId, token_account_id: &Option<AccountId>) -> u128 {
+    match token_account_id {
+      Some(token_account_id) => self
+        .token_balances
+        .get(account_id)
+        .and_then(|balances| balances.get(token_account_id).copied())
+        .unwrap_or(0),
+      None => self.near_balances.get(account_id).unwrap_or(0),
+    }
+  }
+
+  fn internal_set_balance(
+    &mut self,
+    account_id: &AccountId,
+    token_account_id: &Option<AccountId>,
+    amount: u128,
+  ) {
+    match token_account_id {
+      Some(token_account_id) => {
+        let mut balances = self.token_balances.get(account_id).unwrap_or_default();
+        balances.insert(token_account_id.clone(), amount);
+        self.token_balances.insert(account_id, &balances);
+      }
+      None => {
+        self.near_balances.insert(account_id, &amount);
+      }
+    }
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::*;
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  const USDC: &str = "usdc.testnet";
+
+  // Alice earned 10 yocto and 5 USDC
+  fn generate_hub() -> MainHub {
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    let mut main = MainHub::default();
+    main.add_accepted_token(USDC.to_string());
+    main.internal_credit(&"alice.testnet".to_string(), &None, 10);
+    main.internal_credit(&"alice.testnet".to_string(), &Some(USDC.to_string()), 5);
+    testing_env!(user_context("alice.testnet", None));
+    main
+  }
+
+  fn on_withdraw_failed(
+    main: &mut MainHub,
+    token_account_id: Option<AccountId>,
+    amount: u128,
+  ) -> bool {
+    testing_env!(
+      callback_context("alice.testnet"),
+      Default::default(),
+      Default::default(),
+      Default::default(),
+      vec![PromiseResult::Failed]
+    );
+    main.on_withdraw("alice.testnet".to_string(), token_account_id, U128(amount))
+  }
+
+  #[test]
+  fn get_balance_returns_every_currency() {
+    // Given
+    let main = generate_hub();
+    // When
+    let balance = main.get_balance("alice.testnet".to_string());
+    // Then
+    assert_eq!(balance.near, U128(10));
+    assert_eq!(balance.tokens.get(USDC), Some(&U128(5)));
+  }
+
+  #[test]
+  fn withdraw_near_transfers_balance() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.withdraw(Some(U128(4)), None);
+    // Then
+    assert_eq!(main.get_balance("alice.testnet".to_string()).near, U128(6));
+    let receipts = created_receipts();
+    assert!(receipts.contains(
+      r#""receiver_id":"alice.testnet","actions":[{"Transfer":{"deposit":4}}]"#
+    ));
+    assert!(receipts.contains("on_withdraw"));
+  }
+
+  #[test]
+  fn withdraw_token_transfers_whole_balance() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.withdraw(None, Some(USDC.to_string()));
+    // Then
+    assert!(main.get_balance("alice.testnet".to_string()).tokens.is_empty());
+    let receipts = created_receipts();
+    assert!(receipts.contains("ft_transfer"));
+    assert!(receipts.contains("on_withdraw"));
+  }
+
+  #[test]
+  #[should_panic(expected = "Not enough balance to withdraw")]
+  fn withdraw_over_balance_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.withdraw(Some(U128(11)), None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Nothing to withdraw")]
+  fn withdraw_without_balance_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    testing_env!(user_context("robert.testnet", None));
+    main.withdraw(None, None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Nothing to withdraw")]
+  fn withdraw_through_other_contract_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    testing_env!(get_context("alice.testnet", "evil.testnet", None));
+    main.withdraw(None, None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  fn on_withdraw_failure_restores_near_balance() {
+    // Given
+    let mut main = generate_hub();
+    main.withdraw(None, None);
+    // When
+    let withdrawn = on_withdraw_failed(&mut main, None, 10);
+    // Then
+    assert!(!withdrawn);
+    assert_eq!(main.get_balance("alice.testnet".to_string()).near, U128(10));
+  }
+
+  #[test]
+  fn on_withdraw_failure_restores_token_balance() {
+    // Given
+    let mut main = generate_hub();
+    main.withdraw(None, Some(USDC.to_string()));
+    // When
+    let withdrawn = on_withdraw_failed(&mut main, Some(USDC.to_string()), 5);
+    // Then
+    assert!(!withdrawn);
+    assert_eq!(
+      main.get_balance("alice.testnet".to_string()).tokens.get(USDC),
+      Some(&U128(5))
+    );
+  }
+
+  #[test]
+  fn removed_token_can_still_be_withdrawn() {
+    // Given
+    let mut main = generate_hub();
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    main.remove_accepted_token(USDC.to_string());
+    assert!(main.get_accepted_tokens().is_empty());
+    // When
+    testing_env!(user_context("alice.testnet", None));
+    main.withdraw(None, Some(USDC.to_string()));
+    // Then
+    assert!(created_receipts().contains("ft_transfer"));
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the admin can call this method")]
+  fn add_accepted_token_not_admin_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.add_accepted_token("dai.testnet".to_string());
+    // Then
+    // - Should panic
+  }
+}
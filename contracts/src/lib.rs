@@ -1,26 +1,167 @@
 // To conserve gas, efficient serialization is achieved through Borsh (http://borsh.io/)
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::LookupMap;
-use near_sdk::{env, near_bindgen, AccountId};
+use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet};
+use near_sdk::{env, near_bindgen, AccountId, Balance, PublicKey};
+use std::collections::HashMap;
 // Crates
+use crate::attestations::Attestation;
+use crate::drops::ProfileDrop;
+use crate::fees::Treasury;
+use crate::item::ItemInfo;
 use crate::link3::Link3;
+use crate::migration::LegacyLink3;
+use crate::nft::TokenId;
+use crate::passes::PassToken;
+use crate::profiles::{normalize_profile, ProfileId};
+use crate::sponsorship::Sponsorship;
+use crate::tags::LinkId;
+use crate::tips::TipJar;
+mod attestations;
+mod auctions;
+mod directory;
+mod drops;
+mod external;
+mod fees;
+mod follows;
+mod handles;
 mod item;
+mod ledger;
 mod link3;
+mod metadata;
+mod migration;
+mod nft;
+mod passes;
+mod payments;
+mod profiles;
+mod roles;
+mod sealing;
+mod splits;
+mod sponsorship;
+mod tags;
+#[cfg(test)]
+mod test_utils;
+mod tickets;
+mod tiers;
+mod tips;
+mod token_gate;
+mod verification;
 
 near_sdk::setup_alloc!();
 
 const LINK_LIMIT: i32 = 10;
+// Fees and shares are expressed in basis points of this total
+const BASIS_POINTS: u128 = 10_000;
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct MainHub {
+  admin_account_id: AccountId,
+  // Default profile of each account
   hub: LookupMap<AccountId, Link3>,
+  // Default profiles of a migrated hub not saved since, see `migrate`
+  legacy_hub: LookupMap<AccountId, LegacyLink3>,
+  // (account id, profile slug) -> any other profile of the account
+  profiles: LookupMap<(AccountId, String), Link3>,
+  profile_slugs: LookupMap<AccountId, Vec<String>>,
+  // Published profiles, enumerable for discovery
+  directory: UnorderedSet<ProfileId>,
+  // Account -> NEAR it earned and can withdraw
+  near_balances: LookupMap<AccountId, Balance>,
+  // Account -> token account id -> amount earned in that token
+  token_balances: LookupMap<AccountId, HashMap<AccountId, Balance>>,
+  // NEP-141 tokens links can be priced in
+  accepted_tokens: UnorderedSet<AccountId>,
+  // Buyer -> links it bought access to
+  purchases: LookupMap<AccountId, Vec<LinkId>>,
+  // Link -> account -> timestamp (ns) until which the account can read the uri
+  access_grants: LookupMap<LinkId, UnorderedMap<AccountId, u64>>,
+  // Grant maps created so far, numbers the prefix of the next one
+  access_grant_maps: u64,
+  // Account -> X25519 public key the content keys of sealed links are wrapped for
+  encryption_keys: LookupMap<AccountId, Vec<u8>>,
+  // Drop key -> profile waiting to be claimed
+  profile_drops: LookupMap<PublicKey, ProfileDrop>,
+  // Contract creating the accounts of claimed drops, derived from the hub's account when None
+  linkdrop_account_id: Option<AccountId>,
+  // Sponsor -> NEAR paying for its beneficiaries' writes, and beneficiary -> sponsor
+  sponsorships: LookupMap<AccountId, Sponsorship>,
+  sponsors: LookupMap<AccountId, AccountId>,
+  // Contracts allowed to write on behalf of sponsored accounts, managed by the admin
+  relayers: UnorderedSet<AccountId>,
+  // Accounts trusted to attest profiles, managed by the admin
+  verifiers: UnorderedSet<AccountId>,
+  // Contract checking self-service proofs, set by the admin
+  verification_oracle_account_id: Option<AccountId>,
+  attestations: LookupMap<ProfileId, Vec<Attestation>>,
+  // NEP-171 tokens of the minted profiles, the holder is the profile's account
+  profile_tokens: UnorderedMap<TokenId, ProfileId>,
+  token_of_profile: LookupMap<ProfileId, TokenId>,
+  tokens_per_owner: LookupMap<AccountId, UnorderedSet<TokenId>>,
+  // Access passes minted on purchases of links selling them
+  pass_tokens: UnorderedMap<TokenId, PassToken>,
+  next_token_id: u64,
+  // Handle -> account id, handles are stored normalized (lowercase, without the @)
+  handles: LookupMap<String, AccountId>,
+  account_handles: LookupMap<AccountId, String>,
+  // Handles nobody can claim, managed by the admin
+  reserved_handles: UnorderedSet<String>,
+  // Tag -> published profiles and links carrying it
+  profile_tags: LookupMap<String, UnorderedSet<ProfileId>>,
+  link_tags: LookupMap<String, UnorderedSet<LinkId>>,
+  // Account -> accounts it follows, and the reverse
+  following: LookupMap<AccountId, UnorderedSet<AccountId>>,
+  followers: LookupMap<AccountId, UnorderedSet<AccountId>>,
+  // Account -> tips received on its profile
+  tips: LookupMap<AccountId, TipJar>,
+  // Share of each sale, tip and subscription kept by the platform, in basis points
+  platform_fee_bps: u16,
+  // Plan -> fee overriding `platform_fee_bps` for accounts on it, and account -> its plan
+  plan_fees: HashMap<String, u16>,
+  account_plans: LookupMap<AccountId, String>,
+  treasury: Treasury,
 }
 
 impl Default for MainHub {
   fn default() -> Self {
     Self {
-      hub: LookupMap::new(b"a".to_vec()),
+      admin_account_id: env::current_account_id(),
+      hub: LookupMap::new(b"I".to_vec()),
+      legacy_hub: LookupMap::new(b"a".to_vec()),
+      token_balances: LookupMap::new(b"b".to_vec()),
+      handles: LookupMap::new(b"c".to_vec()),
+      account_handles: LookupMap::new(b"d".to_vec()),
+      reserved_handles: UnorderedSet::new(b"e".to_vec()),
+      profiles: LookupMap::new(b"f".to_vec()),
+      profile_slugs: LookupMap::new(b"g".to_vec()),
+      directory: UnorderedSet::new(b"h".to_vec()),
+      profile_tags: LookupMap::new(b"i".to_vec()),
+      link_tags: LookupMap::new(b"j".to_vec()),
+      following: LookupMap::new(b"m".to_vec()),
+      followers: LookupMap::new(b"n".to_vec()),
+      tips: LookupMap::new(b"q".to_vec()),
+      platform_fee_bps: 0,
+      plan_fees: HashMap::new(),
+      account_plans: LookupMap::new(b"G".to_vec()),
+      treasury: Treasury::default(),
+      near_balances: LookupMap::new(b"r".to_vec()),
+      accepted_tokens: UnorderedSet::new(b"s".to_vec()),
+      purchases: LookupMap::new(b"t".to_vec()),
+      access_grants: LookupMap::new(b"J".to_vec()),
+      access_grant_maps: 0,
+      encryption_keys: LookupMap::new(b"u".to_vec()),
+      profile_drops: LookupMap::new(b"v".to_vec()),
+      linkdrop_account_id: None,
+      sponsorships: LookupMap::new(b"w".to_vec()),
+      sponsors: LookupMap::new(b"x".to_vec()),
+      relayers: UnorderedSet::new(b"y".to_vec()),
+      verifiers: UnorderedSet::new(b"z".to_vec()),
+      verification_oracle_account_id: None,
+      attestations: LookupMap::new(b"A".to_vec()),
+      profile_tokens: UnorderedMap::new(b"B".to_vec()),
+      token_of_profile: LookupMap::new(b"C".to_vec()),
+      tokens_per_owner: LookupMap::new(b"D".to_vec()),
+      pass_tokens: UnorderedMap::new(b"F".to_vec()),
+      next_token_id: 0,
     }
   }
 }
@@ -31,12 +172,37 @@ impl MainHub {
    * VIEW METHODS *
    ****************/
   pub fn get(&self, account_id: AccountId) -> Option<Link3> {
-    self.hub.get(&account_id)
+    self.internal_get_profile(&account_id, &None)
+  }
+
+  // Links of the profile with the uris `reader_account_id` unlocked. Anyone can ask as any
+  // reader, only sealed uris stay private. The verified marks follow the active attestations
+  pub fn get_links(
+    &self,
+    account_id: AccountId,
+    profile: Option<String>,
+    reader_account_id: Option<AccountId>,
+  ) -> Vec<ItemInfo> {
+    let profile_id = ProfileId {
+      account_id,
+      profile: normalize_profile(profile),
+    };
+    let link3 = self.internal_expect_profile(&profile_id.account_id, &profile_id.profile);
+    let granted = match &reader_account_id {
+      Some(reader) => self.internal_granted_links(&profile_id, &link3, reader),
+      None => vec![],
+    };
+    let attestations = self.internal_active_attestations(&profile_id);
+    link3.list(reader_account_id.as_ref(), &granted, &attestations)
   }
 
   pub fn get_plan_limit(&self) -> i32 {
     // for now, we are hardcoding the plan limit
-    return LINK_LIMIT;
+    LINK_LIMIT
+  }
+
+  pub fn get_admin(&self) -> AccountId {
+    self.admin_account_id.clone()
   }
 
   /****************
@@ -49,14 +215,14 @@ impl MainHub {
     image_uri: Option<String>,
     is_published: Option<bool>,
   ) -> Link3 {
-    if Self::get(&self, env::signer_account_id()).is_some() {
+    if self.get(env::signer_account_id()).is_some() {
       env::panic(b"Can't create, account has Link3 already")
     }
 
     let link3 = Link3::new(title, description, image_uri, is_published);
-    self.hub.insert(&env::signer_account_id(), &link3);
+    self.internal_save_profile(&env::signer_account_id(), &None, &link3);
 
-    return link3;
+    link3
   }
 
   pub fn update_profile(
@@ -64,37 +230,45 @@ impl MainHub {
     title: String,
     description: String,
     image_uri: Option<String>,
+    profile: Option<String>,
   ) -> Link3 {
-    let mut link3 = Self::get(&self, env::signer_account_id()).unwrap();
+    let account_id = env::signer_account_id();
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
 
     link3.update(title, description, image_uri);
 
-    self.hub.insert(&env::signer_account_id(), &link3);
+    self.internal_save_profile(&account_id, &profile, &link3);
 
-    return link3;
+    link3
   }
 
+  #[allow(clippy::too_many_arguments)]
   pub fn add_link(
     &mut self,
     uri: String,
     title: String,
     description: String,
     image_uri: Option<String>,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
   ) -> Link3 {
-    let mut link3: Link3 = Self::get(&self, env::signer_account_id())
-      .unwrap_or_else(|| env::panic(b"Could not find link3 for this account."));
+    let account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
 
     // Add item
-    if link3.list_all().len() >= LINK_LIMIT as usize {
+    if link3.link_count() >= LINK_LIMIT as usize {
       panic!("You can only have {} links", LINK_LIMIT);
     }
     link3.create_link(uri, title, description, image_uri);
 
     // Save to hub state
-    self.hub.insert(&env::signer_account_id(), &link3);
-    return link3;
+    self.internal_save_profile(&account_id, &profile, &link3);
+    link3
   }
 
+  #[allow(clippy::too_many_arguments)]
   pub fn update_link(
     &mut self,
     id: u64,
@@ -102,30 +276,101 @@ impl MainHub {
     title: String,
     description: String,
     image_uri: Option<String>,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
   ) -> Link3 {
-    let mut link3: Link3 = Self::get(&self, env::signer_account_id())
-      .unwrap_or_else(|| env::panic(b"Could not find link3 for this account."));
+    let account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
 
     // Update item
     link3.update_link(id, uri, title, description, image_uri);
 
     // Save to hub state
-    self.hub.insert(&env::signer_account_id(), &link3);
-    return link3;
+    self.internal_save_profile(&account_id, &profile, &link3);
+    link3
   }
 
-  pub fn delete_link(&mut self, id: u64) -> Link3 {
-    let mut link3: Link3 = Self::get(&self, env::signer_account_id())
-      .unwrap_or_else(|| env::panic(b"Could not find link3 for this account."));
+  pub fn delete_link(
+    &mut self,
+    id: u64,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
+  ) -> Link3 {
+    let account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
 
-    // Delete item
+    // Delete item, its grants are left unreachable since ids aren't reused
     link3.delete_link(id);
+    self.access_grants.remove(&LinkId {
+      account_id: account_id.clone(),
+      profile: profile.clone(),
+      link_id: id,
+    });
 
     // Save to hub state
-    self.hub.insert(&env::signer_account_id(), &link3);
+    self.internal_save_profile(&account_id, &profile, &link3);
 
     // Return self
-    return link3;
+    link3
+  }
+
+  pub fn set_admin(&mut self, account_id: AccountId) {
+    self.assert_admin();
+    if !env::is_valid_account_id(account_id.as_bytes()) {
+      env::panic(b"Admin account id is not valid");
+    }
+
+    self.admin_account_id = account_id;
+  }
+}
+
+impl MainHub {
+  /************
+   * INTERNAL *
+   ************/
+  fn assert_admin(&self) {
+    if env::predecessor_account_id() != self.admin_account_id {
+      env::panic(b"Only the admin can call this method");
+    }
+  }
+}
+
+// Adds `value` to the set stored under `key`, creating the set under `prefix` + hash of the key
+pub(crate) fn nested_set_insert<T: BorshSerialize + BorshDeserialize>(
+  map: &mut LookupMap<String, UnorderedSet<T>>,
+  prefix: &[u8],
+  key: &str,
+  value: &T,
+) -> bool {
+  let key = key.to_string();
+  let mut set = map
+    .get(&key)
+    .unwrap_or_else(|| UnorderedSet::new([prefix, &env::sha256(key.as_bytes())].concat()));
+  let inserted = set.insert(value);
+  map.insert(&key, &set);
+  inserted
+}
+
+// Removes `value` from the set stored under `key`, dropping the set once it's empty
+pub(crate) fn nested_set_remove<T: BorshSerialize + BorshDeserialize>(
+  map: &mut LookupMap<String, UnorderedSet<T>>,
+  key: &str,
+  value: &T,
+) -> bool {
+  let key = key.to_string();
+  match map.get(&key) {
+    Some(mut set) => {
+      let removed = set.remove(value);
+      if set.is_empty() {
+        map.remove(&key);
+      } else {
+        map.insert(&key, &set);
+      }
+      removed
+    }
+    None => false,
   }
 }
 
@@ -136,6 +381,9 @@ impl MainHub {
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::item::Price;
+  use crate::test_utils::user_context;
+  use near_sdk::json_types::{Base64VecU8, U128};
   use near_sdk::{testing_env, VMContext};
   use near_sdk::{Balance, MockedBlockchain};
 
@@ -229,10 +477,12 @@ mod tests {
       "title".to_string(),
       "description".to_string(),
       Some(VALID_IMAGE_URI.to_string()),
+      None,
+      None,
     );
     // Then
     let link3 = main.get("alice.testnet".to_string());
-    assert!(link3.unwrap().list().len() > 0);
+    assert!(!link3.unwrap().list(None, &[], &[]).is_empty());
   }
 
   #[test]
@@ -252,6 +502,8 @@ mod tests {
         "title".to_string(),
         "description".to_string(),
         Some(VALID_IMAGE_URI.to_string()),
+        None,
+        None,
       );
     }
 
@@ -272,6 +524,8 @@ mod tests {
       "title".to_string(),
       "description".to_string(),
       Some(VALID_IMAGE_URI.to_string()),
+      None,
+      None,
     );
     // When
     let id = 1;
@@ -281,12 +535,14 @@ mod tests {
       "title".to_string(),
       "description".to_string(),
       Some(VALID_IMAGE_URI.to_string()),
+      None,
+      None,
     );
     // Then
     let link3 = main.get("alice.testnet".to_string());
 
     assert_eq!(
-      link3.unwrap().list().get(0).unwrap().title,
+      link3.unwrap().list(None, &[], &[]).first().unwrap().title,
       "title".to_string(),
       "title should be updated"
     );
@@ -305,11 +561,13 @@ mod tests {
       "title".to_string(),
       "description".to_string(),
       Some(VALID_IMAGE_URI.to_string()),
+      None,
+      None,
     );
 
     let id = 1;
     // When
-    main.delete_link(id);
+    main.delete_link(id, None, None);
     // Then
     let link3 = main.get("alice.testnet".to_string());
     assert!(
@@ -318,6 +576,30 @@ mod tests {
     );
   }
 
+  #[test]
+  fn admin_defaults_to_contract_account() {
+    // Given
+    let context = get_context(vec![], false, None);
+    testing_env!(context);
+    // When
+    let main = MainHub::default();
+    // Then
+    assert_eq!(main.get_admin(), "contract.testnet".to_string());
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the admin can call this method")]
+  fn set_admin_not_admin_panics() {
+    // Given
+    let context = get_context(vec![], false, None);
+    testing_env!(context);
+    let mut main = MainHub::default();
+    // When
+    main.set_admin("alice.testnet".to_string());
+    // Then
+    // - Should panic
+  }
+
   #[test]
   fn update_profile() {
     // Given
@@ -332,6 +614,7 @@ mod tests {
       "title".to_string(),
       "description".to_string(),
       Some(VALID_IMAGE_URI.to_string()),
+      None,
     );
     // Then
     let link3 = main.get("alice.testnet".to_string());
@@ -339,4 +622,60 @@ mod tests {
 
     assert_eq!(info.0, "title".to_string(), "Title should be updated");
   }
+
+  // Alice sells her only link for 10 yocto, robert bought it
+  fn generate_sold_hub() -> MainHub {
+    testing_env!(user_context("alice.testnet", None));
+    let mut main = MainHub::default();
+    main.create("Hello".to_string(), "World".to_string(), None, Some(true));
+    main.add_sealed_link(
+      Base64VecU8(b"https://secret.uri".to_vec()),
+      HashMap::new(),
+      "title".to_string(),
+      "description".to_string(),
+      None,
+      None,
+      None,
+    );
+    main.set_link_prices(
+      1,
+      vec![Price {
+        token_account_id: None,
+        amount: U128(10),
+      }],
+      None,
+      None,
+    );
+    testing_env!(user_context("robert.testnet", Some(10)));
+    main.buy_link("alice.testnet".to_string(), 1, None);
+    main
+  }
+
+  #[test]
+  fn get_does_not_serialize_gated_uri() {
+    // Given
+    let main = generate_sold_hub();
+    // When
+    let json = near_sdk::serde_json::to_string(&main.get("alice.testnet".to_string())).unwrap();
+    // Then
+    assert!(!json.contains("secret.uri"));
+    assert!(!json.contains("robert.testnet"));
+  }
+
+  #[test]
+  fn get_links_shows_sealed_uri_to_reader_in_view_call() {
+    // Given
+    let main = generate_sold_hub();
+    // When
+    let mut context = user_context("carol.testnet", None);
+    context.is_view = true;
+    testing_env!(context);
+    let reader = Some("robert.testnet".to_string());
+    let bought = main.get_links("alice.testnet".to_string(), None, reader);
+    let anonymous = main.get_links("alice.testnet".to_string(), None, None);
+    // Then
+    let sealed_uri = bought[0].sealed_uri.as_ref().map(|sealed_uri| sealed_uri.0.clone());
+    assert_eq!(sealed_uri, Some(b"https://secret.uri".to_vec()));
+    assert_eq!(anonymous[0].sealed_uri, None);
+  }
 }
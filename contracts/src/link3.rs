@@ -1,12 +1,27 @@
-use std::convert::TryFrom;
 use std::vec;
 // To conserve gas, efficient serialization is achieved through Borsh (http://borsh.io/)
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
 use near_sdk::{env, log, AccountId, PanicOnDefault};
 use serde::Serialize;
+use std::collections::HashMap;
 // Crates
+use crate::attestations::Attestation;
+use crate::item::Auction;
+use crate::item::AuctionSettings;
+use crate::item::Bid;
 use crate::item::Item;
 use crate::item::ItemInfo;
+use crate::item::PassSettings;
+use crate::item::Price;
+use crate::item::Split;
+use crate::item::TicketSettings;
+use crate::item::TokenGate;
+use crate::metadata::insert_metadata;
+use crate::migration::LegacyLink3;
+use crate::roles::Role;
+use crate::tags::normalize_tags;
+use crate::tiers::{Membership, Tier, TIERS_MAX_COUNT};
 
 #[derive(BorshSerialize, BorshDeserialize, Clone, PanicOnDefault, Serialize)]
 pub struct Link3 {
@@ -16,6 +31,17 @@ pub struct Link3 {
   owner_account_id: AccountId,
   links: Vec<Item>,
   is_published: bool,
+  metadata: HashMap<String, String>,
+  // Normalized tags, indexed by the hub while the profile is published
+  tags: Vec<String>,
+  // Collaborators, the owner isn't listed here
+  roles: HashMap<AccountId, Role>,
+  // Membership tiers, a tier unlocks the links of every tier up to its rank
+  tiers: Vec<Tier>,
+  // Member -> latest membership, kept once expired
+  memberships: HashMap<AccountId, Membership>,
+  // Id of the next link, ids of deleted links aren't reused since passes may still hold them
+  next_link_id: u64,
 }
 
 // Core Logic/Implementation
@@ -44,6 +70,35 @@ impl Link3 {
       owner_account_id: env::signer_account_id(),
       links: vec![],
       is_published: is_published.unwrap_or(true),
+      metadata: HashMap::new(),
+      tags: vec![],
+      roles: HashMap::new(),
+      tiers: vec![],
+      memberships: HashMap::new(),
+      next_link_id: 1,
+    }
+  }
+
+  // Profile stored by the first release, links keep their ids
+  pub fn from_legacy(legacy: LegacyLink3) -> Self {
+    let next_link_id = legacy.links.last().map_or(1, |last| last.id + 1);
+    Link3 {
+      title: legacy.title,
+      description: legacy.description,
+      image_uri: legacy.image_uri,
+      owner_account_id: legacy.owner_account_id,
+      links: legacy
+        .links
+        .into_iter()
+        .map(|item| Item::new(item.id, item.uri, item.title, item.description, item.image_uri))
+        .collect(),
+      is_published: legacy.is_published,
+      metadata: HashMap::new(),
+      tags: vec![],
+      roles: HashMap::new(),
+      tiers: vec![],
+      memberships: HashMap::new(),
+      next_link_id,
     }
   }
 
@@ -63,32 +118,133 @@ impl Link3 {
     )
   }
 
-  pub fn list(&self) -> Vec<ItemInfo> {
+  pub fn title(&self) -> &String {
+    &self.title
+  }
+
+  pub fn description(&self) -> &String {
+    &self.description
+  }
+
+  pub fn image_uri(&self) -> Option<&String> {
+    self.image_uri.as_ref()
+  }
+
+  pub fn metadata(&self) -> &HashMap<String, String> {
+    &self.metadata
+  }
+
+  pub fn owner_account_id(&self) -> &AccountId {
+    &self.owner_account_id
+  }
+
+  pub fn tags(&self) -> &Vec<String> {
+    &self.tags
+  }
+
+  pub fn is_published(&self) -> bool {
+    self.is_published
+  }
+
+  pub fn link_count(&self) -> usize {
+    self.links.len()
+  }
+
+  // Every link, gated or not, for the hub's own bookkeeping
+  pub fn links(&self) -> &Vec<Item> {
+    &self.links
+  }
+
+  pub fn roles(&self) -> &HashMap<AccountId, Role> {
+    &self.roles
+  }
+
+  pub fn role_of(&self, account_id: &AccountId) -> Option<Role> {
+    if account_id == &self.owner_account_id {
+      return Some(Role::Owner);
+    }
+    self.roles.get(account_id).copied()
+  }
+
+  // Links as `reader_account_id` sees them, views can't read the signer so it's passed in.
+  // `granted` are the ids of the links the reader holds an active grant of, `attestations`
+  // the profile's active ones, they mark the links they prove
+  pub fn list(
+    &self,
+    reader_account_id: Option<&AccountId>,
+    granted: &[u64],
+    attestations: &[Attestation],
+  ) -> Vec<ItemInfo> {
     if !self.is_published {
       env::panic(b"This contract is not published");
     }
 
+    let member_rank = reader_account_id.and_then(|reader| self.member_rank(reader));
     let links_ref = &self.links;
-    links_ref.iter().map(|item| item.read()).collect()
+    links_ref
+      .iter()
+      .map(|item| {
+        let is_unlocked =
+          granted.contains(&item.id()) || self.tier_unlocks(item, member_rank);
+        item.read(reader_account_id, is_unlocked, attestations)
+      })
+      .collect()
+  }
+
+  // Tiers from the lowest rank to the highest
+  pub fn tiers(&self) -> Vec<Tier> {
+    let mut tiers = self.tiers.clone();
+    tiers.sort_by_key(|tier| tier.rank);
+    tiers
+  }
+
+  pub fn get_tier(&self, id: u64) -> &Tier {
+    self
+      .tiers
+      .iter()
+      .find(|tier| tier.id == id)
+      .unwrap_or_else(|| panic!("Tier does not exist"))
+  }
+
+  // Whether the account can read the item, by grant (`is_granted`, kept by the hub), pass
+  // or membership
+  pub fn can_read(&self, item: &Item, account_id: &AccountId, is_granted: bool) -> bool {
+    !item.is_gated()
+      || is_granted
+      || item.holds_pass(account_id)
+      || self.tier_unlocks(item, self.member_rank(account_id))
+  }
+
+  // Accounts that subscribed, expired ones included until the next subscription
+  pub fn members(&self) -> impl Iterator<Item = &AccountId> {
+    self.memberships.keys()
+  }
+
+  // Membership of the account, None once it expired
+  pub fn membership_of(&self, account_id: &AccountId) -> Option<&Membership> {
+    self
+      .memberships
+      .get(account_id)
+      .filter(|membership| membership.expires_at.0 > env::block_timestamp())
   }
 
   /****************
    * CALL METHODS *
    ****************/
   pub fn update_published_status(&mut self, is_published: bool) {
-    if env::signer_account_id() != self.owner_account_id {
-      env::panic(b"Only the owner can change published state");
-    }
+    self.assert_role(Role::Owner, "Only the owner can change published state");
 
     if self.is_published != is_published {
       self.is_published = is_published;
     }
   }
 
+  pub fn has_auctions(&self) -> bool {
+    self.links.iter().any(|item| item.is_up_for_auction())
+  }
+
   pub fn list_all(&self) -> Vec<Item> {
-    if env::signer_account_id() != self.owner_account_id {
-      env::panic(b"Only the owner can view all items.");
-    }
+    self.assert_role(Role::Editor, "Only the owner or a collaborator can view all items.");
     self.links.clone()
   }
 
@@ -98,9 +254,7 @@ impl Link3 {
     description: String,
     image_uri: Option<String>,
   ) -> &Link3 {
-    if env::signer_account_id() != self.owner_account_id {
-      panic!("Only the owner can update the contract.");
-    }
+    self.assert_role(Role::Owner, "Only the owner can update the contract.");
 
     if self.title != title && self.is_valid_title(&title) {
       self.title = title;
@@ -117,7 +271,7 @@ impl Link3 {
       self.image_uri = image_uri;
     }
 
-    return self;
+    self
   }
 
   pub fn create_link(
@@ -127,17 +281,26 @@ impl Link3 {
     description: String,
     image_uri: Option<String>,
   ) -> &Item {
-    if env::signer_account_id() != self.owner_account_id {
-      env::panic(b"Only the owner can create a link");
-    }
+    self.create_link_as(&env::signer_account_id(), uri, title, description, image_uri)
+  }
+
+  // Same as `create_link`, for writes a relayer makes on the account's behalf
+  pub fn create_link_as(
+    &mut self,
+    account_id: &AccountId,
+    uri: String,
+    title: String,
+    description: String,
+    image_uri: Option<String>,
+  ) -> &Item {
+    self.assert_role_of(
+      account_id,
+      Role::Editor,
+      "Only the owner or a collaborator can create a link",
+    );
 
-    let last = self.links.last();
-    let id = u64::try_from(if last.is_some() {
-      last.unwrap().id() + 1
-    } else {
-      1
-    })
-    .unwrap();
+    let id = self.next_link_id;
+    self.next_link_id += 1;
     let item = Item::new(id, uri, title, description, image_uri);
 
     self.links.push(item);
@@ -153,25 +316,429 @@ impl Link3 {
     description: String,
     image_uri: Option<String>,
   ) -> &Item {
-    if env::signer_account_id() != self.owner_account_id {
-      env::panic(b"Only the owner can update a link");
-    }
-    let index = self.get_index(id);
+    self.update_link_as(&env::signer_account_id(), id, uri, title, description, image_uri)
+  }
 
-    let item = Item::new(id, uri, title, description, image_uri);
+  // Same as `update_link`, for writes a relayer makes on the account's behalf
+  pub fn update_link_as(
+    &mut self,
+    account_id: &AccountId,
+    id: u64,
+    uri: String,
+    title: String,
+    description: String,
+    image_uri: Option<String>,
+  ) -> &Item {
+    self.assert_role_of(
+      account_id,
+      Role::Editor,
+      "Only the owner or a collaborator can update a link",
+    );
+    let index = self.get_index(id);
 
     // Update item
-    self.links[index] = item;
+    self.links[index].update(uri, title, description, image_uri);
     // Return updated item
     &self.links[index]
   }
 
-  pub fn delete_link(&mut self, id: u64) {
-    if env::signer_account_id() != self.owner_account_id {
-      panic!("Only the owner can delete a link");
+  // Same as `create_link` with the uri sealed from the start, so it's never public
+  pub fn create_sealed_link(
+    &mut self,
+    sealed_uri: Vec<u8>,
+    key_envelopes: HashMap<AccountId, Vec<u8>>,
+    title: String,
+    description: String,
+    image_uri: Option<String>,
+  ) -> &Item {
+    let id = self.create_link(String::new(), title, description, image_uri).id();
+    let index = self.get_index(id);
+
+    let item = &mut self.links[index];
+    item.seal(sealed_uri);
+    for (account_id, key_envelope) in key_envelopes {
+      item.set_key_envelope(account_id, key_envelope);
+    }
+    &self.links[index]
+  }
+
+  pub fn seal_link(&mut self, id: u64, sealed_uri: Vec<u8>) -> &Item {
+    self.assert_role(Role::Editor, "Only the owner or a collaborator can update a link");
+    let index = self.get_index(id);
+
+    self.links[index].seal(sealed_uri);
+    &self.links[index]
+  }
+
+  // Publishes the uri of a sealed link in plaintext, dropping its ciphertext and envelopes
+  pub fn unseal_link(&mut self, id: u64, uri: String) -> &Item {
+    self.assert_role(Role::Admin, "Only the owner or an admin can unseal a link");
+    let index = self.get_index(id);
+    if !self.links[index].is_sealed() {
+      env::panic(b"Link is not sealed");
+    }
+    if self.links[index].is_gated() {
+      env::panic(b"Ungate the link before unsealing it");
+    }
+
+    self.links[index].unseal(uri);
+    &self.links[index]
+  }
+
+  // Hands the sealed link's content key, wrapped for the account, to a reader
+  pub fn deliver_key(
+    &mut self,
+    id: u64,
+    account_id: AccountId,
+    key_envelope: Vec<u8>,
+    is_granted: bool,
+  ) {
+    self.assert_role(Role::Admin, "Only the owner or an admin can deliver keys");
+    let index = self.get_index(id);
+    let item = &self.links[index];
+    if !item.is_sealed() {
+      env::panic(b"Link is not sealed");
+    }
+    if !self.can_read(item, &account_id, is_granted) {
+      env::panic(b"Account has no access to this link");
+    }
+
+    self.links[index].set_key_envelope(account_id, key_envelope);
+  }
+
+  pub fn update_token_gate(&mut self, id: u64, token_gate: Option<TokenGate>) -> &Item {
+    self.assert_role(Role::Admin, "Only the owner or an admin can gate a link");
+    let index = self.get_index(id);
+    if token_gate.is_some() {
+      self.assert_sealed(index);
+    }
+
+    self.links[index].set_token_gate(token_gate);
+    &self.links[index]
+  }
+
+  pub fn grant_role(&mut self, account_id: AccountId, role: Role) {
+    self.assert_role(Role::Admin, "Only the owner or an admin can manage collaborators");
+    if role == Role::Owner {
+      env::panic(b"The owner role can't be granted");
+    }
+    if account_id == self.owner_account_id {
+      env::panic(b"The owner already has every role");
+    }
+
+    self.roles.insert(account_id, role);
+  }
+
+  pub fn revoke_role(&mut self, account_id: &AccountId) {
+    self.assert_role(Role::Admin, "Only the owner or an admin can manage collaborators");
+    if self.roles.remove(account_id).is_none() {
+      env::panic(b"Account is not a collaborator");
+    }
+  }
+
+  pub fn set_metadata(&mut self, key: String, value: String) {
+    self.assert_role(Role::Admin, "Only the owner or an admin can change profile metadata");
+
+    insert_metadata(&mut self.metadata, key, value);
+  }
+
+  pub fn remove_metadata(&mut self, key: &str) {
+    self.assert_role(Role::Admin, "Only the owner or an admin can change profile metadata");
+
+    self.metadata.remove(key);
+  }
+
+  pub fn set_link_metadata(&mut self, id: u64, key: String, value: String) {
+    self.assert_role(Role::Editor, "Only the owner or a collaborator can change link metadata");
+    let index = self.get_index(id);
+
+    self.links[index].set_metadata(key, value);
+  }
+
+  pub fn remove_link_metadata(&mut self, id: u64, key: &str) {
+    self.assert_role(Role::Editor, "Only the owner or a collaborator can change link metadata");
+    let index = self.get_index(id);
+
+    self.links[index].remove_metadata(key);
+  }
+
+  pub fn set_tags(&mut self, tags: Vec<String>) {
+    self.assert_role(Role::Admin, "Only the owner or an admin can change profile tags");
+
+    self.tags = normalize_tags(tags);
+  }
+
+  pub fn set_link_tags(&mut self, id: u64, tags: Vec<String>) {
+    self.assert_role(Role::Editor, "Only the owner or a collaborator can change link tags");
+    let index = self.get_index(id);
+
+    self.links[index].set_tags(tags);
+  }
+
+  pub fn update_prices(&mut self, id: u64, prices: Vec<Price>) -> &Item {
+    self.assert_role(Role::Admin, "Only the owner or an admin can price a link");
+    let index = self.get_index(id);
+    if !prices.is_empty() {
+      self.assert_sealed(index);
+    }
+
+    self.links[index].set_prices(prices);
+    &self.links[index]
+  }
+
+  pub fn update_access_duration(&mut self, id: u64, access_duration: Option<u64>) -> &Item {
+    self.assert_role(Role::Admin, "Only the owner or an admin can price a link");
+    let index = self.get_index(id);
+
+    self.links[index].set_access_duration(access_duration);
+    &self.links[index]
+  }
+
+  pub fn update_pass(&mut self, id: u64, pass: Option<PassSettings>) -> &Item {
+    self.assert_role(Role::Admin, "Only the owner or an admin can price a link");
+    let index = self.get_index(id);
+    let supply_cap = pass.as_ref().and_then(|pass| pass.supply_cap);
+    if supply_cap.is_some_and(|supply_cap| supply_cap < self.links[index].passes_issued()) {
+      env::panic(b"Supply cap can't be below the passes already issued");
+    }
+
+    self.links[index].set_pass(pass);
+    &self.links[index]
+  }
+
+  pub fn update_ticket(&mut self, id: u64, ticket: Option<TicketSettings>) -> &Item {
+    self.assert_role(Role::Admin, "Only the owner or an admin can price a link");
+    let index = self.get_index(id);
+    if ticket.is_some() && self.links[index].is_up_for_auction() {
+      env::panic(b"Link is up for auction");
+    }
+    let supply = ticket.as_ref().map(|ticket| ticket.supply);
+    if supply.is_some_and(|supply| supply < self.links[index].tickets_sold()) {
+      env::panic(b"Supply can't be below the tickets already sold");
+    }
+
+    self.links[index].set_ticket(ticket);
+    &self.links[index]
+  }
+
+  pub fn update_min_tier(&mut self, id: u64, tier_id: Option<u64>) -> &Item {
+    self.assert_role(Role::Admin, "Only the owner or an admin can price a link");
+    if let Some(tier_id) = tier_id {
+      self.get_tier(tier_id);
+    }
+    let index = self.get_index(id);
+    if tier_id.is_some() {
+      self.assert_sealed(index);
+    }
+
+    self.links[index].set_min_tier(tier_id);
+    &self.links[index]
+  }
+
+  pub fn add_tier(&mut self, name: String, price: U128, period: U64, rank: u8) -> &Tier {
+    self.assert_role(Role::Admin, "Only the owner or an admin can manage tiers");
+    if self.tiers.len() >= TIERS_MAX_COUNT {
+      panic!("Can have at most {} tiers", TIERS_MAX_COUNT);
+    }
+    if self.tiers.iter().any(|tier| tier.rank == rank) {
+      env::panic(b"Another tier already has this rank");
+    }
+
+    let id = self.tiers.last().map_or(1, |last| last.id + 1);
+    self.tiers.push(Tier {
+      id,
+      name,
+      price,
+      period,
+      rank,
+    });
+    &self.tiers[self.tiers.len() - 1]
+  }
+
+  // Memberships of the tier end with it, so a later tier reusing its id starts empty
+  pub fn remove_tier(&mut self, id: u64) {
+    self.assert_role(Role::Admin, "Only the owner or an admin can manage tiers");
+    if self.links.iter().any(|item| item.min_tier() == Some(id)) {
+      env::panic(b"Tier is still required by a link");
+    }
+    let index = self
+      .tiers
+      .iter()
+      .position(|tier| tier.id == id)
+      .unwrap_or_else(|| panic!("Tier does not exist"));
+
+    self.tiers.remove(index);
+    self.memberships.retain(|_, membership| membership.tier_id != id);
+  }
+
+  // Called by the hub once the membership has been paid, renewing the same tier
+  // early adds to the remaining time while switching tiers starts from now.
+  // Expired memberships are dropped along the way
+  pub fn grant_membership(&mut self, account_id: AccountId, tier_id: u64) -> &Membership {
+    let period = self.get_tier(tier_id).period.0;
+    let now = env::block_timestamp();
+    self.memberships.retain(|_, membership| membership.expires_at.0 > now);
+    let starts_at = match self.membership_of(&account_id) {
+      Some(membership) if membership.tier_id == tier_id => membership.expires_at.0,
+      _ => now,
+    };
+
+    let membership = self.memberships.entry(account_id).or_insert(Membership {
+      tier_id,
+      expires_at: U64(now),
+    });
+    membership.tier_id = tier_id;
+    membership.expires_at = U64(starts_at.saturating_add(period));
+    membership
+  }
+
+  pub fn update_splits(&mut self, id: u64, splits: Vec<Split>) -> &Item {
+    self.assert_role(Role::Admin, "Only the owner or an admin can split a link");
+    let index = self.get_index(id);
+
+    self.links[index].set_splits(splits);
+    &self.links[index]
+  }
+
+  // Called by the hub when a profile drop is claimed, collaborators of the funder go
+  pub fn transfer_ownership(&mut self, owner_account_id: AccountId) {
+    self.owner_account_id = owner_account_id;
+    self.roles.clear();
+  }
+
+  pub fn start_auction(&mut self, id: u64, settings: AuctionSettings) -> &Item {
+    self.assert_role(Role::Admin, "Only the owner or an admin can auction a link");
+    let index = self.get_index(id);
+    self.assert_sealed(index);
+    let item = &mut self.links[index];
+    if item.is_up_for_auction() {
+      env::panic(b"Link is already up for auction");
+    }
+    if item.ticket().is_some() {
+      env::panic(b"Ticketed links can't be auctioned");
+    }
+
+    item.set_auction(Some(Auction {
+      settings,
+      highest_bid: None,
+      settled: false,
+    }));
+    &self.links[index]
+  }
+
+  // Called by the hub on each bid, returns the bid it outbids
+  pub fn place_bid(&mut self, id: u64, account_id: AccountId, amount: u128) -> Option<Bid> {
+    let index = self.get_index(id);
+    let item = &mut self.links[index];
+    let mut auction = item
+      .auction()
+      .filter(|auction| !auction.settled)
+      .cloned()
+      .unwrap_or_else(|| env::panic(b"Link is not up for auction"));
+
+    let now = env::block_timestamp();
+    let settings = &mut auction.settings;
+    if now >= settings.ends_at.0 {
+      env::panic(b"Auction has ended");
+    }
+    let min_bid = match &auction.highest_bid {
+      Some(bid) => bid.amount.0 + settings.min_increment.0,
+      None => settings.reserve_price.0,
+    };
+    if amount < min_bid {
+      panic!("Bid must be at least {}", min_bid);
+    }
+    // Anti-sniping
+    if settings.ends_at.0 - now < settings.extension.0 {
+      settings.ends_at = U64(now + settings.extension.0);
     }
 
+    let outbid = auction.highest_bid.replace(Bid {
+      account_id,
+      amount: U128(amount),
+      placed_at: U64(now),
+    });
+    item.set_auction(Some(auction));
+    outbid
+  }
+
+  // Called by the hub to settle the auction once it's over, returns the winning bid
+  pub fn close_auction(&mut self, id: u64) -> Option<Bid> {
+    let index = self.get_index(id);
+    let item = &mut self.links[index];
+    let mut auction = item
+      .auction()
+      .filter(|auction| !auction.settled)
+      .cloned()
+      .unwrap_or_else(|| env::panic(b"Link is not up for auction"));
+    if env::block_timestamp() < auction.settings.ends_at.0 {
+      env::panic(b"Auction hasn't ended");
+    }
+
+    auction.settled = true;
+    let winning_bid = auction.highest_bid.clone();
+    item.set_auction(Some(auction));
+    winning_bid
+  }
+
+  // Called by the hub on each purchase, counts the ticket of a ticketed link
+  pub fn sell_ticket(&mut self, id: u64, account_id: &AccountId) {
     let index = self.get_index(id);
+    let item = &mut self.links[index];
+    let ticket = match item.ticket() {
+      Some(ticket) => ticket,
+      None => return,
+    };
+
+    let now = env::block_timestamp();
+    if ticket.sale_start.is_some_and(|sale_start| now < sale_start.0) {
+      env::panic(b"Ticket sales haven't started");
+    }
+    if ticket.sale_end.is_some_and(|sale_end| now >= sale_end.0) {
+      env::panic(b"Ticket sales have ended");
+    }
+    if item.tickets_sold() >= ticket.supply {
+      env::panic(b"Tickets are sold out");
+    }
+    if item.tickets_of(account_id) >= ticket.max_per_buyer {
+      panic!("Can buy at most {} tickets", ticket.max_per_buyer);
+    }
+
+    item.add_ticket(account_id.clone());
+  }
+
+  // Called by the hub when a purchase mints a pass
+  pub fn issue_pass(&mut self, id: u64, token_id: String, account_id: AccountId) {
+    let index = self.get_index(id);
+    let item = &mut self.links[index];
+    let supply_cap = item.pass().and_then(|pass| pass.supply_cap);
+    if supply_cap.is_some_and(|supply_cap| item.passes_issued() >= supply_cap) {
+      env::panic(b"Passes of this link are sold out");
+    }
+
+    item.issue_pass(token_id, account_id);
+  }
+
+  // Called by the hub when a pass changes hands
+  pub fn move_pass(&mut self, id: u64, token_id: String, account_id: AccountId) {
+    let index = self.get_index(id);
+
+    self.links[index].set_pass_holder(token_id, account_id);
+  }
+
+  pub fn get_link(&self, id: u64) -> &Item {
+    let index = self.get_index(id);
+
+    &self.links[index]
+  }
+
+  pub fn delete_link(&mut self, id: u64) {
+    self.assert_role(Role::Admin, "Only the owner or an admin can delete a link");
+
+    let index = self.get_index(id);
+    if self.links[index].is_up_for_auction() {
+      env::panic(b"Link is up for auction");
+    }
 
     // Remove item
     self.links.remove(index);
@@ -181,7 +748,46 @@ impl Link3 {
    * PRIVATE METHODS *
    *******************/
 
-  fn get_index(&mut self, id: u64) -> usize {
+  // Panics with `message` unless the signer has at least `role` on this Link3
+  fn assert_role(&self, role: Role, message: &str) {
+    self.assert_role_of(&env::signer_account_id(), role, message);
+  }
+
+  fn assert_role_of(&self, account_id: &AccountId, role: Role, message: &str) {
+    match self.role_of(account_id) {
+      Some(account_role) if account_role >= role => {}
+      _ => panic!("{}", message),
+    }
+  }
+
+  // Rank of the account's tier while its membership lasts
+  fn member_rank(&self, account_id: &AccountId) -> Option<u8> {
+    let membership = self.membership_of(account_id)?;
+    self
+      .tiers
+      .iter()
+      .find(|tier| tier.id == membership.tier_id)
+      .map(|tier| tier.rank)
+  }
+
+  fn tier_unlocks(&self, item: &Item, member_rank: Option<u8>) -> bool {
+    match (item.min_tier(), member_rank) {
+      (Some(min_tier), Some(member_rank)) => self
+        .tiers
+        .iter()
+        .any(|tier| tier.id == min_tier && tier.rank <= member_rank),
+      _ => false,
+    }
+  }
+
+  // Gated uris only live in state as ciphertext, so a link is sealed before it's gated
+  fn assert_sealed(&self, index: usize) {
+    if !self.links[index].is_sealed() {
+      env::panic(b"Seal the link before gating it");
+    }
+  }
+
+  fn get_index(&self, id: u64) -> usize {
     self
       .links
       .iter()
@@ -191,7 +797,7 @@ impl Link3 {
       })
   }
 
-  fn is_valid_title(&mut self, title: &String) -> bool {
+  fn is_valid_title(&mut self, title: &str) -> bool {
     if title.is_empty() {
       panic!("Title cannot be empty");
     }
@@ -204,10 +810,10 @@ impl Link3 {
       panic!("Title must be at most 20 characters long");
     }
 
-    return true;
+    true
   }
 
-  fn is_valid_description(&mut self, description: &String) -> bool {
+  fn is_valid_description(&mut self, description: &str) -> bool {
     if description.is_empty() {
       panic!("Description cannot be empty");
     }
@@ -220,10 +826,10 @@ impl Link3 {
       panic!("Description must be at most 200 characters long");
     }
 
-    return true;
+    true
   }
 
-  fn is_valid_image_uri(&mut self, image_uri: &String) -> bool {
+  fn is_valid_image_uri(&mut self, image_uri: &str) -> bool {
     if image_uri.is_empty() {
       panic!("Image uri cannot be empty");
     }
@@ -231,7 +837,7 @@ impl Link3 {
       panic!("Image uri must be a valid ipfs hash");
     }
 
-    return true;
+    true
   }
 }
 
@@ -344,7 +950,7 @@ mod tests {
       "This is the perfect description".to_string()
     );
     assert_eq!(contract.image_uri, Some(VALID_IMAGE_URI.to_string()));
-    assert_eq!(contract.is_published, true);
+    assert!(contract.is_published);
   }
 
   #[test]
@@ -355,7 +961,7 @@ mod tests {
     // When
     let contract = generate_contract(Some(false));
     // Then
-    assert_eq!(contract.is_published, false);
+    assert!(!contract.is_published);
   }
 
   #[test]
@@ -440,11 +1046,11 @@ mod tests {
     );
 
     // Then
-    assert!(contract.list().len() == 1, "Should have at one item");
+    assert!(contract.list(None, &[], &[]).len() == 1, "Should have at one item");
   }
 
   #[test]
-  #[should_panic(expected = "Only the owner can create a link")]
+  #[should_panic(expected = "Only the owner or a collaborator can create a link")]
   fn create_link_with_wrong_owner_panics() {
     // Given
     let context = get_context(vec![], false, Some(1));
@@ -472,7 +1078,7 @@ mod tests {
     testing_env!(context);
     let contract = generate_contract(Some(false));
     // When
-    contract.list();
+    contract.list(None, &[], &[]);
     // Then
     // - Should panic
   }
@@ -513,7 +1119,7 @@ mod tests {
   }
 
   #[test]
-  #[should_panic(expected = "Only the owner can view all items")]
+  #[should_panic(expected = "Only the owner or a collaborator can view all items")]
   fn list_all_with_wrong_owner_panics() {
     // Given
     let context = get_context(vec![], false, Some(1));
@@ -550,7 +1156,7 @@ mod tests {
   }
 
   #[test]
-  #[should_panic(expected = "Only the owner can update a link")]
+  #[should_panic(expected = "Only the owner or a collaborator can update a link")]
   fn update_item_with_wrong_owner_panics() {
     // Given
     let context = get_context(vec![], false, Some(1));
@@ -631,14 +1237,177 @@ mod tests {
     );
     // Then
     assert_eq!(
-      item.read().title,
+      item.read(None, false, &[]).title,
       "another_title".to_string(),
       "Should've returned an item"
     );
   }
 
   #[test]
-  #[should_panic(expected = "Only the owner can delete a link")]
+  fn update_item_keeps_token_gate() {
+    // Given
+    let context = get_context(vec![], false, Some(1));
+    testing_env!(context);
+    let mut contract = generate_contract(Some(true));
+    contract.create_sealed_link(
+      vec![1],
+      HashMap::new(),
+      "some_title".to_string(),
+      "some_description".to_string(),
+      Some("image".to_string()),
+    );
+    contract.update_token_gate(1, Some(generate_token_gate()));
+
+    // When
+    let item = contract.update_link(
+      1,
+      String::new(),
+      "another_title".to_string(),
+      "some_description".to_string(),
+      Some("image".to_string()),
+    );
+    // Then
+    assert_eq!(item.token_gate(), Some(&generate_token_gate()));
+  }
+
+  fn generate_token_gate() -> TokenGate {
+    TokenGate {
+      token_account_id: "token.testnet".to_string(),
+      min_balance: U128(100),
+    }
+  }
+
+  #[test]
+  fn update_token_gate_gates_link() {
+    // Given
+    let context = get_context(vec![], false, Some(1));
+    testing_env!(context);
+    let mut contract = generate_contract(Some(true));
+    contract.create_sealed_link(
+      vec![1],
+      HashMap::new(),
+      "some_title".to_string(),
+      "some_description".to_string(),
+      Some("image".to_string()),
+    );
+
+    // When
+    contract.update_token_gate(1, Some(generate_token_gate()));
+
+    // Then
+    let link = &contract.list(None, &[], &[])[0];
+    assert_eq!(link.sealed_uri, None, "Sealed uri should be hidden");
+  }
+
+  #[test]
+  #[should_panic(expected = "Seal the link before gating it")]
+  fn update_token_gate_on_unsealed_link_panics() {
+    // Given
+    let context = get_context(vec![], false, Some(1));
+    testing_env!(context);
+    let mut contract = generate_contract(Some(true));
+    contract.create_link(
+      "some_uri".to_string(),
+      "some_title".to_string(),
+      "some_description".to_string(),
+      Some("image".to_string()),
+    );
+    // When
+    contract.update_token_gate(1, Some(generate_token_gate()));
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the owner or an admin can gate a link")]
+  fn update_token_gate_with_wrong_owner_panics() {
+    // Given
+    let context = get_context(vec![], false, Some(1));
+    testing_env!(context);
+    let mut contract = generate_contract(Some(true));
+    contract.create_link(
+      "some_uri".to_string(),
+      "some_title".to_string(),
+      "some_description".to_string(),
+      Some("image".to_string()),
+    );
+
+    // When
+    let alt_context = get_alternative_context(vec![], false, Some(1));
+    testing_env!(alt_context);
+    contract.update_token_gate(1, Some(generate_token_gate()));
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the owner or an admin can change profile metadata")]
+  fn set_metadata_with_wrong_owner_panics() {
+    // Given
+    let context = get_context(vec![], false, Some(1));
+    testing_env!(context);
+    let mut contract = generate_contract(Some(true));
+
+    // When
+    let alt_context = get_alternative_context(vec![], false, Some(1));
+    testing_env!(alt_context);
+    contract.set_metadata("theme_color".to_string(), "#000000".to_string());
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the owner or a collaborator can change link metadata")]
+  fn set_link_metadata_with_wrong_owner_panics() {
+    // Given
+    let context = get_context(vec![], false, Some(1));
+    testing_env!(context);
+    let mut contract = generate_contract(Some(true));
+    contract.create_link(
+      "some_uri".to_string(),
+      "some_title".to_string(),
+      "some_description".to_string(),
+      Some("image".to_string()),
+    );
+
+    // When
+    let alt_context = get_alternative_context(vec![], false, Some(1));
+    testing_env!(alt_context);
+    contract.set_link_metadata(1, "badge".to_string(), "hot".to_string());
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the owner or an admin can price a link")]
+  fn update_prices_with_wrong_owner_panics() {
+    // Given
+    let context = get_context(vec![], false, Some(1));
+    testing_env!(context);
+    let mut contract = generate_contract(Some(true));
+    contract.create_link(
+      "some_uri".to_string(),
+      "some_title".to_string(),
+      "some_description".to_string(),
+      Some("image".to_string()),
+    );
+
+    // When
+    let alt_context = get_alternative_context(vec![], false, Some(1));
+    testing_env!(alt_context);
+    contract.update_prices(
+      1,
+      vec![Price {
+        token_account_id: None,
+        amount: U128(1),
+      }],
+    );
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the owner or an admin can delete a link")]
   fn delete_item_not_own() {
     // Given
     let context = get_context(vec![], false, Some(1));
@@ -720,7 +1489,7 @@ mod tests {
     // Given
     let context = get_context(vec![], false, Some(1));
     testing_env!(context);
-    let mut contract = generate_contract(Some(false));
+    let contract = generate_contract(Some(false));
 
     let id = 1;
     // When
@@ -759,7 +1528,7 @@ mod tests {
     let mut contract = generate_contract(Some(false));
 
     // When
-    contract.is_valid_title(&"".to_string());
+    contract.is_valid_title("");
     // Then
     // - Should panic
   }
@@ -773,7 +1542,7 @@ mod tests {
     let mut contract = generate_contract(Some(false));
 
     // When
-    contract.is_valid_title(&"ab".to_string());
+    contract.is_valid_title("ab");
     // Then
     // - Should panic
   }
@@ -787,7 +1556,7 @@ mod tests {
     let mut contract = generate_contract(Some(false));
 
     // When
-    contract.is_valid_title(&"abcdefghijklmnopqrstuvwxyz".to_string());
+    contract.is_valid_title("abcdefghijklmnopqrstuvwxyz");
     // Then
     // - Should panic
   }
@@ -800,7 +1569,7 @@ mod tests {
     let mut contract = generate_contract(Some(false));
 
     // When
-    contract.is_valid_title(&"abc".to_string());
+    contract.is_valid_title("abc");
     // Then
     // - Should not panic
   }
@@ -814,7 +1583,7 @@ mod tests {
     let mut contract = generate_contract(Some(false));
 
     // When
-    contract.is_valid_description(&"".to_string());
+    contract.is_valid_description("");
     // Then
     // - Should panic
   }
@@ -828,7 +1597,7 @@ mod tests {
     let mut contract = generate_contract(Some(false));
 
     // When
-    contract.is_valid_description(&"ab".to_string());
+    contract.is_valid_description("ab");
     // Then
     // - Should panic
   }
@@ -842,7 +1611,7 @@ mod tests {
     let mut contract = generate_contract(Some(false));
 
     // When
-    contract.is_valid_description(&"200JVqNGFrN3R8OC0DreT9yUEw6dkzCgyzLv9a6QslWC2wqdRkfjRD6ErbgUFYKHZdCzgFn1l9U719ANtFw6uDoNoIXoN0Q8c8RINKEZPDbpohxhDTnjl2YljFbP4JX2blOdpoCqglKxL6kZjPkqn2TXy6b9R54B8vmSDX3bQD6pnzdfR7l6MaFssnjsW7hLgp1mo61Gzy".to_string());
+    contract.is_valid_description("200JVqNGFrN3R8OC0DreT9yUEw6dkzCgyzLv9a6QslWC2wqdRkfjRD6ErbgUFYKHZdCzgFn1l9U719ANtFw6uDoNoIXoN0Q8c8RINKEZPDbpohxhDTnjl2YljFbP4JX2blOdpoCqglKxL6kZjPkqn2TXy6b9R54B8vmSDX3bQD6pnzdfR7l6MaFssnjsW7hLgp1mo61Gzy");
     // Then
     // - Should panic
   }
@@ -855,7 +1624,7 @@ mod tests {
     let mut contract = generate_contract(Some(false));
 
     // When
-    contract.is_valid_description(&"abc".to_string());
+    contract.is_valid_description("abc");
     // Then
     // - Should not panic
   }
@@ -869,7 +1638,7 @@ mod tests {
     let mut contract = generate_contract(Some(false));
 
     // When
-    contract.is_valid_image_uri(&"".to_string());
+    contract.is_valid_image_uri("");
     // Then
     // - Should panic
   }
@@ -883,7 +1652,7 @@ mod tests {
     let mut contract = generate_contract(Some(false));
 
     // When
-    contract.is_valid_image_uri(&"3r131r31r31".to_string());
+    contract.is_valid_image_uri("3r131r31r31");
     // Then
     // - Should panic
   }
@@ -896,7 +1665,7 @@ mod tests {
     let mut contract = generate_contract(Some(false));
 
     // When
-    contract.is_valid_image_uri(&VALID_IMAGE_URI.to_string());
+    contract.is_valid_image_uri(VALID_IMAGE_URI);
     // Then
     // - Should not panic
   }
@@ -0,0 +1,231 @@
+use near_sdk::{env, near_bindgen};
+use std::collections::HashMap;
+// Crates
+use crate::link3::Link3;
+use crate::profiles::normalize_profile;
+use crate::*;
+
+pub const METADATA_MAX_ENTRIES: usize = 20;
+pub const METADATA_KEY_MAX_LENGTH: usize = 32;
+pub const METADATA_VALUE_MAX_LENGTH: usize = 256;
+// Keys under these namespaces are written by the hub only
+pub const RESERVED_METADATA_NAMESPACES: [&str; 1] = ["link3:"];
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * CALL METHODS *
+   ****************/
+  // Sets a metadata entry on the profile, or on one of its links when `link_id` is given
+  pub fn set_metadata(
+    &mut self,
+    key: String,
+    value: String,
+    link_id: Option<u64>,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
+  ) -> Link3 {
+    assert_not_reserved(&key);
+
+    let account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
+
+    match link_id {
+      Some(id) => link3.set_link_metadata(id, key, value),
+      None => link3.set_metadata(key, value),
+    }
+
+    // Save to hub state
+    self.internal_save_profile(&account_id, &profile, &link3);
+    link3
+  }
+
+  pub fn remove_metadata(
+    &mut self,
+    key: String,
+    link_id: Option<u64>,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
+  ) -> Link3 {
+    assert_not_reserved(&key);
+
+    let account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
+
+    match link_id {
+      Some(id) => link3.remove_link_metadata(id, &key),
+      None => link3.remove_metadata(&key),
+    }
+
+    // Save to hub state
+    self.internal_save_profile(&account_id, &profile, &link3);
+    link3
+  }
+}
+
+// Validates and inserts the entry, keeping the map under the entry limit
+pub fn insert_metadata(metadata: &mut HashMap<String, String>, key: String, value: String) {
+  assert_valid_metadata(&key, &value);
+
+  if !metadata.contains_key(&key) && metadata.len() >= METADATA_MAX_ENTRIES {
+    panic!("Metadata can have at most {} entries", METADATA_MAX_ENTRIES);
+  }
+
+  metadata.insert(key, value);
+}
+
+fn assert_valid_metadata(key: &str, value: &str) {
+  if key.is_empty() {
+    panic!("Metadata key cannot be empty");
+  }
+
+  if key.len() > METADATA_KEY_MAX_LENGTH {
+    panic!(
+      "Metadata key must be at most {} characters long",
+      METADATA_KEY_MAX_LENGTH
+    );
+  }
+
+  if !key
+    .chars()
+    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.' || c == ':')
+  {
+    panic!("Metadata key can only contain letters, numbers, _, -, . and :");
+  }
+
+  if value.len() > METADATA_VALUE_MAX_LENGTH {
+    panic!(
+      "Metadata value must be at most {} characters long",
+      METADATA_VALUE_MAX_LENGTH
+    );
+  }
+}
+
+fn assert_not_reserved(key: &str) {
+  if RESERVED_METADATA_NAMESPACES
+    .iter()
+    .any(|namespace| key.starts_with(namespace))
+  {
+    panic!("Metadata key is in a reserved namespace");
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::*;
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  fn generate_hub() -> MainHub {
+    testing_env!(user_context("alice.testnet", None));
+    let mut main = MainHub::default();
+    main.create("Hello".to_string(), "World".to_string(), None, Some(true));
+    main.add_link(
+      "uri".to_string(),
+      "title".to_string(),
+      "description".to_string(),
+      None,
+      None,
+      None,
+    );
+    main
+  }
+
+  #[test]
+  fn set_metadata_on_profile() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    let link3 = main.set_metadata("theme_color".to_string(), "#ff0000".to_string(), None, None, None);
+    // Then
+    assert_eq!(
+      link3.metadata().get("theme_color"),
+      Some(&"#ff0000".to_string())
+    );
+  }
+
+  #[test]
+  fn set_metadata_on_link_is_returned_by_item_info() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.set_metadata("button_style".to_string(), "outline".to_string(), Some(1), None, None);
+    // Then
+    let link3 = main.get("alice.testnet".to_string()).unwrap();
+    assert_eq!(
+      link3.list(None, &[], &[])[0].metadata.get("button_style"),
+      Some(&"outline".to_string())
+    );
+    assert!(link3.metadata().is_empty(), "Profile should be untouched");
+  }
+
+  #[test]
+  fn remove_metadata_removes_entry() {
+    // Given
+    let mut main = generate_hub();
+    main.set_metadata("location".to_string(), "Lisbon".to_string(), None, None, None);
+    main.set_metadata("badge".to_string(), "gold".to_string(), Some(1), None, None);
+    // When
+    main.remove_metadata("location".to_string(), None, None, None);
+    let link3 = main.remove_metadata("badge".to_string(), Some(1), None, None);
+    // Then
+    assert!(link3.metadata().is_empty());
+    assert!(link3.list(None, &[], &[])[0].metadata.is_empty());
+  }
+
+  #[test]
+  #[should_panic(expected = "Metadata key is in a reserved namespace")]
+  fn set_metadata_in_reserved_namespace_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.set_metadata("link3:verified".to_string(), "true".to_string(), None, None, None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Metadata key must be at most 32 characters long")]
+  fn insert_metadata_with_long_key_panics() {
+    let mut metadata = HashMap::new();
+    insert_metadata(&mut metadata, "k".repeat(33), "value".to_string());
+  }
+
+  #[test]
+  #[should_panic(expected = "Metadata value must be at most 256 characters long")]
+  fn insert_metadata_with_long_value_panics() {
+    let mut metadata = HashMap::new();
+    insert_metadata(&mut metadata, "key".to_string(), "v".repeat(257));
+  }
+
+  #[test]
+  #[should_panic(expected = "Metadata key can only contain letters, numbers, _, -, . and :")]
+  fn insert_metadata_with_invalid_key_panics() {
+    let mut metadata = HashMap::new();
+    insert_metadata(&mut metadata, "theme color".to_string(), "red".to_string());
+  }
+
+  #[test]
+  #[should_panic(expected = "Metadata can have at most 20 entries")]
+  fn insert_metadata_over_limit_panics() {
+    let mut metadata = HashMap::new();
+    for i in 0..=METADATA_MAX_ENTRIES {
+      insert_metadata(&mut metadata, format!("key{}", i), "value".to_string());
+    }
+  }
+
+  #[test]
+  fn insert_metadata_overwrites_existing_key_at_limit() {
+    let mut metadata = HashMap::new();
+    for i in 0..METADATA_MAX_ENTRIES {
+      insert_metadata(&mut metadata, format!("key{}", i), "value".to_string());
+    }
+    insert_metadata(&mut metadata, "key0".to_string(), "another".to_string());
+    assert_eq!(metadata.get("key0"), Some(&"another".to_string()));
+  }
+}
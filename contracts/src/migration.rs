@@ -0,0 +1,132 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::LookupMap;
+use near_sdk::{env, near_bindgen, AccountId};
+// Crates
+use crate::*;
+
+// Hub as deployed before gated links, its only state was the default profiles
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct LegacyMainHub {
+  pub hub: LookupMap<AccountId, LegacyLink3>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct LegacyLink3 {
+  pub title: String,
+  pub description: String,
+  pub image_uri: Option<String>,
+  pub owner_account_id: AccountId,
+  pub links: Vec<LegacyItem>,
+  pub is_published: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct LegacyItem {
+  pub id: u64,
+  pub uri: String,
+  pub title: String,
+  pub description: String,
+  pub image_uri: Option<String>,
+}
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * CALL METHODS *
+   ****************/
+  // Run with the deploy of this version over a hub deployed before gated links. Its
+  // profiles keep their prefix and move to the current layout the next time they're
+  // saved, they show up in the directory and tag search from then on
+  #[private]
+  #[init(ignore_state)]
+  pub fn migrate() -> Self {
+    let legacy: LegacyMainHub =
+      env::state_read().unwrap_or_else(|| env::panic(b"There is no hub to migrate"));
+
+    Self {
+      legacy_hub: legacy.hub,
+      ..Self::default()
+    }
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::*;
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  // Alice's profile as the first release stored it
+  fn deploy_legacy_hub() {
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    let mut hub = LookupMap::new(b"a".to_vec());
+    hub.insert(
+      &"alice.testnet".to_string(),
+      &LegacyLink3 {
+        title: "Hello".to_string(),
+        description: "World".to_string(),
+        image_uri: None,
+        owner_account_id: "alice.testnet".to_string(),
+        links: vec![LegacyItem {
+          id: 3,
+          uri: "https://github.com/alice".to_string(),
+          title: "GitHub".to_string(),
+          description: "My code".to_string(),
+          image_uri: None,
+        }],
+        is_published: true,
+      },
+    );
+    env::state_write(&LegacyMainHub { hub });
+  }
+
+  #[test]
+  fn migrate_keeps_legacy_profiles_readable() {
+    // Given
+    deploy_legacy_hub();
+    // When
+    let main = MainHub::migrate();
+    // Then
+    let link3 = main.get("alice.testnet".to_string()).unwrap();
+    assert_eq!(link3.owner_account_id(), &"alice.testnet".to_string());
+    let links = main.get_links("alice.testnet".to_string(), None, None);
+    assert_eq!(links[0].id, 3);
+    assert_eq!(links[0].uri, Some("https://github.com/alice".to_string()));
+  }
+
+  #[test]
+  fn saving_legacy_profile_moves_it_to_current_layout() {
+    // Given
+    deploy_legacy_hub();
+    let mut main = MainHub::migrate();
+    // When
+    testing_env!(user_context("alice.testnet", None));
+    main.add_link(
+      "https://x.com/alice".to_string(),
+      "X".to_string(),
+      "My posts".to_string(),
+      None,
+      None,
+      None,
+    );
+    // Then
+    assert!(main.legacy_hub.get(&"alice.testnet".to_string()).is_none());
+    let links = main.get_links("alice.testnet".to_string(), None, None);
+    assert_eq!(links.iter().map(|link| link.id).collect::<Vec<u64>>(), vec![3, 4]);
+    assert_eq!(main.get_profiles(0, 10)[0].account_id, "alice.testnet".to_string());
+  }
+
+  #[test]
+  #[should_panic(expected = "There is no hub to migrate")]
+  fn migrate_without_state_panics() {
+    // Given
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    // When
+    MainHub::migrate();
+    // Then
+    // - Should panic
+  }
+}
@@ -0,0 +1,872 @@
+use near_sdk::json_types::{Base64VecU8, U128};
+use near_sdk::serde_json::{self, json};
+use near_sdk::{
+  assert_one_yocto, env, log, near_bindgen, AccountId, Gas, PromiseOrValue, PromiseResult,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+// Crates
+use crate::external::{ext_nft_approval_receiver, ext_nft_receiver, ext_self};
+use crate::passes::PassToken;
+use crate::profiles::{normalize_profile, ProfileId};
+use crate::*;
+
+pub type TokenId = String;
+
+pub const NFT_METADATA_SPEC: &str = "nft-1.0.0";
+// Profile images are ipfs CIDs, token media is relative to this
+const IPFS_BASE_URI: &str = "https://ipfs.io/ipfs";
+// Prefix of the per-owner token sets, followed by the owner's hash
+pub(crate) const TOKENS_PER_OWNER_SET_PREFIX: &[u8] = b"E";
+const GAS_FOR_NFT_ON_TRANSFER: Gas = 25_000_000_000_000;
+const GAS_FOR_NFT_RESOLVE_TRANSFER: Gas = 15_000_000_000_000;
+const GAS_FOR_NFT_ON_APPROVE: Gas = 25_000_000_000_000;
+const NO_DEPOSIT: u128 = 0;
+
+// NEP-177 contract metadata
+#[derive(Serialize, Debug)]
+pub struct NFTContractMetadata {
+  pub spec: String,
+  pub name: String,
+  pub symbol: String,
+  pub icon: Option<String>,
+  pub base_uri: Option<String>,
+  pub reference: Option<String>,
+  pub reference_hash: Option<Base64VecU8>,
+}
+
+// NEP-177 token metadata, derived from the profile (or link) whenever it's read
+#[derive(Serialize, Debug, Default)]
+pub struct TokenMetadata {
+  pub title: Option<String>,
+  pub description: Option<String>,
+  pub media: Option<String>,
+  pub media_hash: Option<Base64VecU8>,
+  pub copies: Option<u64>,
+  pub issued_at: Option<String>,
+  pub expires_at: Option<String>,
+  pub starts_at: Option<String>,
+  pub updated_at: Option<String>,
+  pub extra: Option<String>,
+  pub reference: Option<String>,
+  pub reference_hash: Option<Base64VecU8>,
+}
+
+// Tokens are either profiles or access passes of links, sharing the id sequence
+#[derive(Serialize, Debug)]
+pub struct Token {
+  pub token_id: TokenId,
+  pub owner_id: AccountId,
+  pub metadata: Option<TokenMetadata>,
+  pub approved_account_ids: HashMap<AccountId, u64>,
+}
+
+// NEP-199 payout of a sale
+#[derive(Serialize, Debug)]
+pub struct Payout {
+  pub payout: HashMap<AccountId, U128>,
+}
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  pub fn nft_token(&self, token_id: TokenId) -> Option<Token> {
+    self.internal_token(&token_id)
+  }
+
+  pub fn nft_metadata(&self) -> NFTContractMetadata {
+    NFTContractMetadata {
+      spec: NFT_METADATA_SPEC.to_string(),
+      name: "Link3".to_string(),
+      symbol: "LINK3".to_string(),
+      icon: None,
+      base_uri: Some(IPFS_BASE_URI.to_string()),
+      reference: None,
+      reference_hash: None,
+    }
+  }
+
+  pub fn nft_total_supply(&self) -> U128 {
+    U128((self.profile_tokens.len() + self.pass_tokens.len()) as u128)
+  }
+
+  // Profile tokens first, then passes
+  pub fn nft_tokens(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<Token> {
+    self
+      .profile_tokens
+      .keys_as_vector()
+      .iter()
+      .chain(self.pass_tokens.keys_as_vector().iter())
+      .skip(from_index.map_or(0, |from_index| from_index.0 as usize))
+      .take(limit.map_or(usize::MAX, |limit| limit as usize))
+      .filter_map(|token_id| self.internal_token(&token_id))
+      .collect()
+  }
+
+  pub fn nft_supply_for_owner(&self, account_id: AccountId) -> U128 {
+    U128(self.tokens_per_owner.get(&account_id).map_or(0, |set| set.len()) as u128)
+  }
+
+  pub fn nft_tokens_for_owner(
+    &self,
+    account_id: AccountId,
+    from_index: Option<U128>,
+    limit: Option<u64>,
+  ) -> Vec<Token> {
+    match self.tokens_per_owner.get(&account_id) {
+      Some(tokens) => tokens
+        .as_vector()
+        .iter()
+        .skip(from_index.map_or(0, |from_index| from_index.0 as usize))
+        .take(limit.map_or(usize::MAX, |limit| limit as usize))
+        .filter_map(|token_id| self.internal_token(&token_id))
+        .collect(),
+      None => vec![],
+    }
+  }
+
+  pub fn get_profile_token(
+    &self,
+    account_id: AccountId,
+    profile: Option<String>,
+  ) -> Option<TokenId> {
+    self.token_of_profile.get(&ProfileId {
+      account_id,
+      profile: normalize_profile(profile),
+    })
+  }
+
+  pub fn nft_is_approved(
+    &self,
+    token_id: TokenId,
+    approved_account_id: AccountId,
+    approval_id: Option<u64>,
+  ) -> bool {
+    self.pass_tokens.get(&token_id).is_some_and(|pass| {
+      pass
+        .approvals
+        .get(&approved_account_id)
+        .is_some_and(|id| approval_id.is_none_or(|approval_id| approval_id == *id))
+    })
+  }
+
+  // Royalty of the link's owner on passes, profile tokens pay their owner everything
+  pub fn nft_payout(&self, token_id: TokenId, balance: U128, max_len_payout: u32) -> Payout {
+    let owner_id = self
+      .internal_token_owner(&token_id)
+      .unwrap_or_else(|| env::panic(b"Token does not exist"));
+
+    let mut payout: HashMap<AccountId, u128> = HashMap::new();
+    let mut royalty = 0;
+    if let Some((creator_account_id, royalty_bps)) = self.internal_pass_royalty(&token_id) {
+      royalty = balance.0 * royalty_bps as u128 / BASIS_POINTS;
+      if royalty > 0 {
+        payout.insert(creator_account_id, royalty);
+      }
+    }
+    *payout.entry(owner_id).or_insert(0) += balance.0 - royalty;
+    if payout.len() > max_len_payout as usize {
+      env::panic(b"Payout has more receivers than max_len_payout");
+    }
+
+    Payout {
+      payout: payout
+        .into_iter()
+        .map(|(account_id, amount)| (account_id, U128(amount)))
+        .collect(),
+    }
+  }
+
+  /****************
+   * CALL METHODS *
+   ****************/
+  // Represents the signer's profile as a token, whoever holds it controls the profile
+  pub fn mint_profile(&mut self, profile: Option<String>) -> Token {
+    let profile_id = ProfileId {
+      account_id: env::signer_account_id(),
+      profile: normalize_profile(profile),
+    };
+    self.internal_expect_profile(&profile_id.account_id, &profile_id.profile);
+    if self.token_of_profile.get(&profile_id).is_some() {
+      env::panic(b"Profile is already minted");
+    }
+
+    let token_id = self.internal_next_token_id();
+    self.profile_tokens.insert(&token_id, &profile_id);
+    self.token_of_profile.insert(&profile_id, &token_id);
+    nested_set_insert(
+      &mut self.tokens_per_owner,
+      TOKENS_PER_OWNER_SET_PREFIX,
+      &profile_id.account_id,
+      &token_id,
+    );
+
+    emit_event(
+      "nft_mint",
+      json!({ "owner_id": profile_id.account_id, "token_ids": [token_id] }),
+    );
+    self.internal_token(&token_id).unwrap()
+  }
+
+  // A profile moves under the receiver, who can't already have one with the same slug,
+  // a pass takes the access to its link along
+  #[payable]
+  pub fn nft_transfer(
+    &mut self,
+    receiver_id: AccountId,
+    token_id: TokenId,
+    approval_id: Option<u64>,
+    memo: Option<String>,
+  ) {
+    assert_one_yocto();
+
+    let sender_id = env::predecessor_account_id();
+    self.internal_transfer(&sender_id, &receiver_id, &token_id, approval_id, memo);
+  }
+
+  #[payable]
+  pub fn nft_transfer_call(
+    &mut self,
+    receiver_id: AccountId,
+    token_id: TokenId,
+    approval_id: Option<u64>,
+    memo: Option<String>,
+    msg: String,
+  ) -> PromiseOrValue<bool> {
+    assert_one_yocto();
+
+    let sender_id = env::predecessor_account_id();
+    let previous_owner_id =
+      self.internal_transfer(&sender_id, &receiver_id, &token_id, approval_id, memo);
+
+    ext_nft_receiver::nft_on_transfer(
+      sender_id,
+      previous_owner_id.clone(),
+      token_id.clone(),
+      msg,
+      &receiver_id,
+      NO_DEPOSIT,
+      GAS_FOR_NFT_ON_TRANSFER,
+    )
+    .then(ext_self::nft_resolve_transfer(
+      previous_owner_id,
+      receiver_id,
+      token_id,
+      &env::current_account_id(),
+      NO_DEPOSIT,
+      GAS_FOR_NFT_RESOLVE_TRANSFER,
+    ))
+    .into()
+  }
+
+  // What marketplaces call on a sale, the payout is worked out before the transfer
+  #[payable]
+  pub fn nft_transfer_payout(
+    &mut self,
+    receiver_id: AccountId,
+    token_id: TokenId,
+    approval_id: Option<u64>,
+    memo: Option<String>,
+    balance: U128,
+    max_len_payout: u32,
+  ) -> Payout {
+    assert_one_yocto();
+
+    let payout = self.nft_payout(token_id.clone(), balance, max_len_payout);
+    let sender_id = env::predecessor_account_id();
+    self.internal_transfer(&sender_id, &receiver_id, &token_id, approval_id, memo);
+    payout
+  }
+
+  // Lets `account_id` transfer the pass (e.g. a marketplace listing it), notified when
+  // there's a `msg`. Profile tokens can't be approved
+  #[payable]
+  pub fn nft_approve(&mut self, token_id: TokenId, account_id: AccountId, msg: Option<String>) {
+    if env::attached_deposit() == 0 {
+      env::panic(b"Requires attached deposit of at least 1 yoctoNEAR");
+    }
+    let mut pass = self.internal_expect_owned_pass(&token_id);
+
+    pass.next_approval_id += 1;
+    let approval_id = pass.next_approval_id;
+    pass.approvals.insert(account_id.clone(), approval_id);
+    self.pass_tokens.insert(&token_id, &pass);
+
+    if let Some(msg) = msg {
+      ext_nft_approval_receiver::nft_on_approve(
+        token_id,
+        pass.owner_id,
+        approval_id,
+        msg,
+        &account_id,
+        NO_DEPOSIT,
+        GAS_FOR_NFT_ON_APPROVE,
+      );
+    }
+  }
+
+  #[payable]
+  pub fn nft_revoke(&mut self, token_id: TokenId, account_id: AccountId) {
+    assert_one_yocto();
+    let mut pass = self.internal_expect_owned_pass(&token_id);
+
+    pass.approvals.remove(&account_id);
+    self.pass_tokens.insert(&token_id, &pass);
+  }
+
+  #[payable]
+  pub fn nft_revoke_all(&mut self, token_id: TokenId) {
+    assert_one_yocto();
+    let mut pass = self.internal_expect_owned_pass(&token_id);
+
+    pass.approvals.clear();
+    self.pass_tokens.insert(&token_id, &pass);
+  }
+
+  /*************
+   * CALLBACKS *
+   *************/
+  // Returns whether the receiver kept the token, it goes back when the receiver asked for
+  // it or failed, unless the previous owner has taken the profile's slot since
+  #[private]
+  pub fn nft_resolve_transfer(
+    &mut self,
+    previous_owner_id: AccountId,
+    receiver_id: AccountId,
+    token_id: TokenId,
+  ) -> bool {
+    let must_return = match env::promise_result(0) {
+      PromiseResult::Successful(value) => serde_json::from_slice::<bool>(&value).unwrap_or(true),
+      _ => true,
+    };
+    if !must_return {
+      return true;
+    }
+
+    // Burned, or already passed on by the receiver
+    if self.internal_token_owner(&token_id) != Some(receiver_id.clone()) {
+      return true;
+    }
+    if let Some(profile_id) = self.profile_tokens.get(&token_id) {
+      if self
+        .internal_get_profile(&previous_owner_id, &profile_id.profile)
+        .is_some()
+      {
+        return true;
+      }
+    }
+
+    self.internal_transfer(&receiver_id, &previous_owner_id, &token_id, None, None);
+    false
+  }
+}
+
+impl MainHub {
+  /************
+   * INTERNAL *
+   ************/
+  pub(crate) fn internal_next_token_id(&mut self) -> TokenId {
+    self.next_token_id += 1;
+    self.next_token_id.to_string()
+  }
+
+  fn internal_token_owner(&self, token_id: &TokenId) -> Option<AccountId> {
+    match self.profile_tokens.get(token_id) {
+      Some(profile_id) => Some(profile_id.account_id),
+      None => self.pass_tokens.get(token_id).map(|pass| pass.owner_id),
+    }
+  }
+
+  fn internal_token(&self, token_id: &TokenId) -> Option<Token> {
+    if let Some(pass) = self.pass_tokens.get(token_id) {
+      return Some(Token {
+        token_id: token_id.clone(),
+        owner_id: pass.owner_id,
+        metadata: self.internal_pass_metadata(&pass.link),
+        approved_account_ids: pass.approvals,
+      });
+    }
+
+    let profile_id = self.profile_tokens.get(token_id)?;
+    let link3 = self.internal_get_profile(&profile_id.account_id, &profile_id.profile)?;
+
+    Some(Token {
+      token_id: token_id.clone(),
+      owner_id: profile_id.account_id,
+      metadata: Some(TokenMetadata {
+        title: Some(link3.title().clone()),
+        description: Some(link3.description().clone()),
+        media: link3.image_uri().cloned(),
+        copies: Some(1),
+        ..Default::default()
+      }),
+      approved_account_ids: HashMap::new(),
+    })
+  }
+
+  // Returns the owner the token was taken from
+  fn internal_transfer(
+    &mut self,
+    sender_id: &AccountId,
+    receiver_id: &AccountId,
+    token_id: &TokenId,
+    approval_id: Option<u64>,
+    memo: Option<String>,
+  ) -> AccountId {
+    if !env::is_valid_account_id(receiver_id.as_bytes()) {
+      env::panic(b"Receiver account id is not valid");
+    }
+
+    if self.pass_tokens.get(token_id).is_some() {
+      return self.internal_transfer_pass(sender_id, receiver_id, token_id, approval_id, memo);
+    }
+    if approval_id.is_some() {
+      env::panic(b"Profile tokens can't be approved");
+    }
+    self.internal_transfer_profile_token(sender_id, receiver_id, token_id, memo);
+    sender_id.clone()
+  }
+
+  // Re-keys the profile under the receiver along with its buyers' passes, and its handle
+  // for the default profile. Collaborators and attestations are the previous owner's,
+  // so they stay behind
+  fn internal_transfer_profile_token(
+    &mut self,
+    sender_id: &AccountId,
+    receiver_id: &AccountId,
+    token_id: &TokenId,
+    memo: Option<String>,
+  ) {
+    let from = self
+      .profile_tokens
+      .get(token_id)
+      .unwrap_or_else(|| env::panic(b"Token does not exist"));
+    if &from.account_id != sender_id {
+      env::panic(b"Only the token owner can transfer it");
+    }
+    if sender_id == receiver_id {
+      env::panic(b"Can't transfer a token to its owner");
+    }
+    if self.internal_get_profile(receiver_id, &from.profile).is_some() {
+      env::panic(b"Receiver already has this profile");
+    }
+
+    let mut link3 = self.internal_remove_profile(&from).unwrap();
+    link3.transfer_ownership(receiver_id.clone());
+
+    let to = ProfileId {
+      account_id: receiver_id.clone(),
+      profile: from.profile.clone(),
+    };
+    self.internal_save_profile(&to.account_id, &to.profile, &link3);
+    self.token_of_profile.remove(&from);
+    self.token_of_profile.insert(&to, token_id);
+    self.profile_tokens.insert(token_id, &to);
+    self.internal_move_owned_token(sender_id, receiver_id, token_id);
+
+    self.internal_move_purchases(&link3, &from, &to);
+    if to.profile.is_none() {
+      self.internal_move_handle(sender_id, receiver_id);
+    }
+
+    // Passes of the profile's links follow it, royalties go to the new owner
+    for item in link3.links() {
+      for pass_token_id in item.pass_token_ids() {
+        if let Some(mut pass) = self.pass_tokens.get(pass_token_id) {
+          pass.link.account_id = receiver_id.clone();
+          self.pass_tokens.insert(pass_token_id, &pass);
+        }
+      }
+    }
+
+    emit_event(
+      "nft_transfer",
+      json!({
+        "old_owner_id": sender_id,
+        "new_owner_id": receiver_id,
+        "token_ids": [token_id],
+        "memo": memo,
+      }),
+    );
+  }
+
+  // Moves the pass and the link's access along with it, approvals are cleared
+  fn internal_transfer_pass(
+    &mut self,
+    sender_id: &AccountId,
+    receiver_id: &AccountId,
+    token_id: &TokenId,
+    approval_id: Option<u64>,
+    memo: Option<String>,
+  ) -> AccountId {
+    let mut pass = self.pass_tokens.get(token_id).unwrap();
+    let owner_id = pass.owner_id.clone();
+    let is_approved = self.nft_is_approved(token_id.clone(), sender_id.clone(), approval_id);
+    if sender_id != &owner_id && !is_approved {
+      env::panic(b"Only the token owner or an approved account can transfer it");
+    }
+    if receiver_id == &owner_id {
+      env::panic(b"Can't transfer a token to its owner");
+    }
+
+    pass.owner_id = receiver_id.clone();
+    pass.approvals.clear();
+    self.pass_tokens.insert(token_id, &pass);
+    self.internal_move_owned_token(&owner_id, receiver_id, token_id);
+
+    let link = &pass.link;
+    if let Some(mut link3) = self.internal_get_profile(&link.account_id, &link.profile) {
+      if link3.links().iter().any(|item| item.id() == link.link_id) {
+        link3.move_pass(link.link_id, token_id.clone(), receiver_id.clone());
+        self.internal_save_profile(&link.account_id, &link.profile, &link3);
+      }
+    }
+
+    let authorized_id = if sender_id != &owner_id { Some(sender_id) } else { None };
+    emit_event(
+      "nft_transfer",
+      json!({
+        "authorized_id": authorized_id,
+        "old_owner_id": owner_id,
+        "new_owner_id": receiver_id,
+        "token_ids": [token_id],
+        "memo": memo,
+      }),
+    );
+    owner_id
+  }
+
+  fn internal_move_owned_token(
+    &mut self,
+    owner_id: &AccountId,
+    receiver_id: &AccountId,
+    token_id: &TokenId,
+  ) {
+    nested_set_remove(&mut self.tokens_per_owner, owner_id, token_id);
+    nested_set_insert(
+      &mut self.tokens_per_owner,
+      TOKENS_PER_OWNER_SET_PREFIX,
+      receiver_id,
+      token_id,
+    );
+  }
+
+  fn internal_expect_owned_pass(&self, token_id: &TokenId) -> PassToken {
+    let pass = self
+      .pass_tokens
+      .get(token_id)
+      .unwrap_or_else(|| env::panic(b"Only access passes can be approved"));
+    if pass.owner_id != env::predecessor_account_id() {
+      env::panic(b"Only the token owner can manage its approvals");
+    }
+    pass
+  }
+
+  // Called when the profile is deleted
+  pub(crate) fn internal_burn_profile_token(&mut self, profile_id: &ProfileId) {
+    let token_id = match self.token_of_profile.remove(profile_id) {
+      Some(token_id) => token_id,
+      None => return,
+    };
+
+    self.profile_tokens.remove(&token_id);
+    nested_set_remove(&mut self.tokens_per_owner, &profile_id.account_id, &token_id);
+    emit_event(
+      "nft_burn",
+      json!({ "owner_id": profile_id.account_id, "token_ids": [token_id] }),
+    );
+  }
+}
+
+// NEP-297 event log of the NEP-171 standard
+pub(crate) fn emit_event(event: &str, data: serde_json::Value) {
+  let event = json!({
+    "standard": "nep171",
+    "version": "1.0.0",
+    "event": event,
+    "data": [data],
+  });
+  log!("EVENT_JSON:{}", event);
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::item::Price;
+  use crate::test_utils::*;
+  use near_sdk::json_types::U64;
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  const IMAGE_CID: &str = "QmPChd2hVbrJ6bfo3WBcTW4iZnpHm8TEzWkLHmLpXhF68A";
+
+  // Alice has a published profile with an image
+  fn generate_hub() -> MainHub {
+    testing_env!(user_context("alice.testnet", None));
+    let mut main = MainHub::default();
+    main.create(
+      "Hello".to_string(),
+      "World".to_string(),
+      Some(IMAGE_CID.to_string()),
+      Some(true),
+    );
+    main
+  }
+
+  fn transfer(main: &mut MainHub, sender: &str, receiver: &str, token_id: &str) {
+    testing_env!(user_context(sender, Some(1)));
+    main.nft_transfer(receiver.to_string(), token_id.to_string(), None, None);
+  }
+
+  fn resolve(main: &mut MainHub, result: PromiseResult) -> bool {
+    testing_env!(
+      callback_context("alice.testnet"),
+      Default::default(),
+      Default::default(),
+      Default::default(),
+      vec![result]
+    );
+    main.nft_resolve_transfer(
+      "alice.testnet".to_string(),
+      "market.testnet".to_string(),
+      "1".to_string(),
+    )
+  }
+
+  #[test]
+  fn mint_profile_derives_metadata_from_profile() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    let token = main.mint_profile(None);
+    // Then
+    assert_eq!(token.token_id, "1".to_string());
+    assert_eq!(token.owner_id, "alice.testnet".to_string());
+    let metadata = token.metadata.unwrap();
+    assert_eq!(metadata.title, Some("Hello".to_string()));
+    assert_eq!(metadata.description, Some("World".to_string()));
+    assert_eq!(metadata.media, Some(IMAGE_CID.to_string()));
+    assert_eq!(main.get_profile_token("alice.testnet".to_string(), None), Some(token.token_id));
+    assert_eq!(main.nft_total_supply(), U128(1));
+  }
+
+  #[test]
+  fn nft_transfer_moves_profile_to_receiver() {
+    // Given
+    let mut main = generate_hub();
+    main.mint_profile(None);
+    // When
+    transfer(&mut main, "alice.testnet", "bob.testnet", "1");
+    // Then
+    assert!(main.get("alice.testnet".to_string()).is_none());
+    let link3 = main.get("bob.testnet".to_string()).unwrap();
+    assert_eq!(link3.owner_account_id(), &"bob.testnet".to_string());
+    assert_eq!(main.nft_token("1".to_string()).unwrap().owner_id, "bob.testnet".to_string());
+    assert_eq!(main.nft_supply_for_owner("alice.testnet".to_string()), U128(0));
+    assert_eq!(
+      main.nft_tokens_for_owner("bob.testnet".to_string(), None, None)[0].token_id,
+      "1".to_string()
+    );
+    assert_eq!(main.get_profiles(0, 10)[0].account_id, "bob.testnet".to_string());
+  }
+
+  #[test]
+  fn nft_transfer_of_named_profile_keeps_slug() {
+    // Given
+    let mut main = generate_hub();
+    main.create_profile(
+      "events".to_string(),
+      "Events".to_string(),
+      "Meetups".to_string(),
+      None,
+      Some(true),
+    );
+    main.mint_profile(Some("events".to_string()));
+    // When
+    transfer(&mut main, "alice.testnet", "bob.testnet", "1");
+    // Then
+    assert!(main
+      .get_profile("bob.testnet".to_string(), Some("events".to_string()))
+      .is_some());
+    assert_eq!(main.list_profiles("alice.testnet".to_string()), vec!["default".to_string()]);
+  }
+
+  #[test]
+  fn nft_transfer_moves_buyers_passes_to_receiver() {
+    // Given
+    let mut main = generate_hub();
+    add_sealed_link(&mut main);
+    main.set_link_prices(
+      1,
+      vec![Price {
+        token_account_id: None,
+        amount: U128(10),
+      }],
+      None,
+      None,
+    );
+    main.set_link_access_duration(1, Some(U64(100)), None, None);
+    testing_env!(user_context("robert.testnet", Some(10)));
+    main.buy_link("alice.testnet".to_string(), 1, None);
+    testing_env!(user_context("alice.testnet", None));
+    main.mint_profile(None);
+    // When
+    transfer(&mut main, "alice.testnet", "bob.testnet", "1");
+    // Then
+    let passes = main.get_passes("robert.testnet".to_string());
+    assert_eq!(passes.len(), 1);
+    assert_eq!(passes[0].owner_account_id, "bob.testnet".to_string());
+    testing_env!(user_context("robert.testnet", Some(10)));
+    main.buy_link("bob.testnet".to_string(), 1, None);
+    testing_env!(user_context("carol.testnet", Some(10)));
+    main.buy_link("bob.testnet".to_string(), 1, None);
+    assert_eq!(main.get_passes("robert.testnet".to_string()).len(), 1);
+    let passes = main.get_passes("carol.testnet".to_string());
+    assert_eq!(passes[0].owner_account_id, "bob.testnet".to_string());
+    assert_eq!(main.get_balance("bob.testnet".to_string()).near, U128(20));
+  }
+
+  #[test]
+  fn nft_transfer_of_default_profile_moves_handle() {
+    // Given
+    let mut main = generate_hub();
+    main.claim_handle("alice".to_string());
+    main.mint_profile(None);
+    // When
+    transfer(&mut main, "alice.testnet", "bob.testnet", "1");
+    // Then
+    let link3 = main.get_by_handle("alice".to_string()).unwrap();
+    assert_eq!(link3.owner_account_id(), &"bob.testnet".to_string());
+    assert_eq!(main.get_handle("bob.testnet".to_string()), Some("alice".to_string()));
+    assert_eq!(main.get_handle("alice.testnet".to_string()), None);
+  }
+
+  #[test]
+  fn nft_tokens_pages_all_tokens() {
+    // Given
+    let mut main = generate_hub();
+    main.mint_profile(None);
+    testing_env!(user_context("bob.testnet", None));
+    main.create("Bob".to_string(), "Builder".to_string(), None, Some(true));
+    main.mint_profile(None);
+    // When
+    let tokens = main.nft_tokens(Some(U128(1)), Some(10));
+    // Then
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].owner_id, "bob.testnet".to_string());
+  }
+
+  #[test]
+  fn nft_transfer_call_notifies_receiver() {
+    // Given
+    let mut main = generate_hub();
+    main.mint_profile(None);
+    // When
+    testing_env!(user_context("alice.testnet", Some(1)));
+    main.nft_transfer_call(
+      "market.testnet".to_string(),
+      "1".to_string(),
+      None,
+      None,
+      "list".to_string(),
+    );
+    // Then
+    let receipts = created_receipts();
+    assert!(receipts.contains(r#""receiver_id":"market.testnet""#));
+    assert!(receipts.contains("nft_on_transfer"));
+    assert!(receipts.contains("nft_resolve_transfer"));
+  }
+
+  #[test]
+  fn resolve_transfer_returns_token_when_receiver_asks() {
+    // Given
+    let mut main = generate_hub();
+    main.mint_profile(None);
+    transfer(&mut main, "alice.testnet", "market.testnet", "1");
+    // When
+    let is_kept = resolve(&mut main, promise_success(&true));
+    // Then
+    assert!(!is_kept);
+    assert!(main.get("alice.testnet".to_string()).is_some());
+    assert_eq!(main.nft_token("1".to_string()).unwrap().owner_id, "alice.testnet".to_string());
+  }
+
+  #[test]
+  fn resolve_transfer_keeps_token_when_receiver_accepts() {
+    // Given
+    let mut main = generate_hub();
+    main.mint_profile(None);
+    transfer(&mut main, "alice.testnet", "market.testnet", "1");
+    // When
+    let is_kept = resolve(&mut main, promise_success(&false));
+    // Then
+    assert!(is_kept);
+    assert!(main.get("market.testnet".to_string()).is_some());
+  }
+
+  #[test]
+  fn delete_profile_burns_token() {
+    // Given
+    let mut main = generate_hub();
+    main.mint_profile(None);
+    // When
+    main.delete_profile(None);
+    // Then
+    assert!(main.nft_token("1".to_string()).is_none());
+    assert_eq!(main.nft_total_supply(), U128(0));
+  }
+
+  #[test]
+  #[should_panic(expected = "Receiver already has this profile")]
+  fn nft_transfer_to_account_with_profile_panics() {
+    // Given
+    let mut main = generate_hub();
+    main.mint_profile(None);
+    testing_env!(user_context("bob.testnet", None));
+    main.create("Bob".to_string(), "Builder".to_string(), None, Some(true));
+    // When
+    transfer(&mut main, "alice.testnet", "bob.testnet", "1");
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the token owner can transfer it")]
+  fn nft_transfer_not_owner_panics() {
+    // Given
+    let mut main = generate_hub();
+    main.mint_profile(None);
+    // When
+    transfer(&mut main, "eve.testnet", "eve2.testnet", "1");
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Requires attached deposit of exactly 1 yoctoNEAR")]
+  fn nft_transfer_without_yocto_panics() {
+    // Given
+    let mut main = generate_hub();
+    main.mint_profile(None);
+    // When
+    main.nft_transfer("bob.testnet".to_string(), "1".to_string(), None, None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Profile is already minted")]
+  fn mint_profile_twice_panics() {
+    // Given
+    let mut main = generate_hub();
+    main.mint_profile(None);
+    // When
+    main.mint_profile(None);
+    // Then
+    // - Should panic
+  }
+}
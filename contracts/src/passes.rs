@@ -0,0 +1,724 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::UnorderedMap;
+use near_sdk::json_types::U64;
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, AccountId};
+use serde::Serialize;
+use std::collections::HashMap;
+// Crates
+use crate::item::{PassSettings, PERMANENT_ACCESS};
+use crate::link3::Link3;
+use crate::nft::{emit_event, TokenId, TokenMetadata, TOKENS_PER_OWNER_SET_PREFIX};
+use crate::profiles::{normalize_profile, ProfileId, DEFAULT_PROFILE};
+use crate::tags::LinkId;
+use crate::*;
+
+// Creators can ask for at most half of each resale
+pub const PASS_ROYALTY_MAX_BPS: u16 = 5_000;
+const ACCESS_GRANTS_MAP_PREFIX: &[u8] = b"K";
+// Grants checked for expiry on each new grant of the link
+const ACCESS_GRANTS_PRUNED: u64 = 5;
+
+// A link the account bought access to
+#[derive(Serialize, Debug)]
+pub struct Pass {
+  pub owner_account_id: AccountId,
+  pub profile: String,
+  pub link_id: u64,
+  // None when the purchase never expires
+  pub expires_at: Option<U64>,
+}
+
+// NFT minted on the purchase of a link selling passes, whoever holds it can read the uri
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct PassToken {
+  pub owner_id: AccountId,
+  pub link: LinkId,
+  // NEP-178 approvals, cleared on each transfer
+  pub approvals: HashMap<AccountId, u64>,
+  pub next_approval_id: u64,
+}
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  // Purchases of the account that still give access
+  pub fn get_passes(&self, account_id: AccountId) -> Vec<Pass> {
+    self
+      .purchases
+      .get(&account_id)
+      .unwrap_or_default()
+      .into_iter()
+      .filter_map(|link_id| {
+        let expires_at = self.internal_access_expiry(&link_id, &account_id)?;
+        Some(Pass {
+          owner_account_id: link_id.account_id,
+          profile: link_id
+            .profile
+            .unwrap_or_else(|| DEFAULT_PROFILE.to_string()),
+          link_id: link_id.link_id,
+          expires_at: match expires_at {
+            PERMANENT_ACCESS => None,
+            expires_at => Some(U64(expires_at)),
+          },
+        })
+      })
+      .collect()
+  }
+
+  /****************
+   * CALL METHODS *
+   ****************/
+  // Makes purchases of the link expire after `access_duration` (ns), None sells it forever
+  pub fn set_link_access_duration(
+    &mut self,
+    id: u64,
+    access_duration: Option<U64>,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
+  ) -> Link3 {
+    if access_duration == Some(U64(0)) {
+      env::panic(b"Access duration must be greater than 0");
+    }
+
+    let account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
+
+    // Update item
+    link3.update_access_duration(id, access_duration.map(|duration| duration.0));
+
+    // Save to hub state
+    self.internal_save_profile(&account_id, &profile, &link3);
+    link3
+  }
+
+  // Purchases of the link mint a pass instead of granting the buyer, None stops it.
+  // Passes already minted keep giving access
+  pub fn set_link_pass(
+    &mut self,
+    id: u64,
+    pass: Option<PassSettings>,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
+  ) -> Link3 {
+    if let Some(pass) = &pass {
+      if pass.supply_cap == Some(0) {
+        env::panic(b"Supply cap must be greater than 0");
+      }
+      if pass.royalty_bps > PASS_ROYALTY_MAX_BPS {
+        panic!("Royalty can be at most {} basis points", PASS_ROYALTY_MAX_BPS);
+      }
+    }
+
+    let account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
+
+    // Update item
+    link3.update_pass(id, pass);
+
+    // Save to hub state
+    self.internal_save_profile(&account_id, &profile, &link3);
+    link3
+  }
+}
+
+impl MainHub {
+  /************
+   * INTERNAL *
+   ************/
+  // Unlocks the link for the buyer and keeps track of it in the buyer's passes,
+  // saving `link3` is left to the caller
+  pub(crate) fn internal_grant_purchase(
+    &mut self,
+    link3: &mut Link3,
+    owner_account_id: &AccountId,
+    profile: &Option<String>,
+    link_id: u64,
+    buyer_account_id: &AccountId,
+  ) {
+    link3.sell_ticket(link_id, buyer_account_id);
+
+    let pass = LinkId {
+      account_id: owner_account_id.clone(),
+      profile: profile.clone(),
+      link_id,
+    };
+    if link3.get_link(link_id).pass().is_some() {
+      self.internal_issue_pass(link3, pass, buyer_account_id);
+      return;
+    }
+
+    let current = self.internal_access_expiry(&pass, buyer_account_id);
+    let expires_at = link3.get_link(link_id).purchase_expiry(current);
+    self.internal_grant_access(&pass, buyer_account_id, expires_at);
+
+    // Expired passes are dropped along the way, with their grants
+    let (mut purchases, expired): (Vec<LinkId>, Vec<LinkId>) = self
+      .purchases
+      .get(buyer_account_id)
+      .unwrap_or_default()
+      .into_iter()
+      .filter(|other| other != &pass)
+      .partition(|other| {
+        self
+          .internal_access_expiry(other, buyer_account_id)
+          .is_some()
+      });
+    for link in expired {
+      self.internal_drop_expired_grant(&link, buyer_account_id);
+    }
+    purchases.push(pass);
+    self.purchases.insert(buyer_account_id, &purchases);
+  }
+
+  // Mints a pass of the link to the buyer, saving `link3` is left to the caller
+  fn internal_issue_pass(
+    &mut self,
+    link3: &mut Link3,
+    link: LinkId,
+    buyer_account_id: &AccountId,
+  ) {
+    let token_id = self.internal_next_token_id();
+    link3.issue_pass(link.link_id, token_id.clone(), buyer_account_id.clone());
+
+    self.pass_tokens.insert(
+      &token_id,
+      &PassToken {
+        owner_id: buyer_account_id.clone(),
+        link,
+        approvals: HashMap::new(),
+        next_approval_id: 0,
+      },
+    );
+    nested_set_insert(
+      &mut self.tokens_per_owner,
+      TOKENS_PER_OWNER_SET_PREFIX,
+      buyer_account_id,
+      &token_id,
+    );
+    emit_event(
+      "nft_mint",
+      json!({ "owner_id": buyer_account_id, "token_ids": [token_id] }),
+    );
+  }
+
+  // Metadata of the link's passes, None once the link is gone
+  pub(crate) fn internal_pass_metadata(&self, link: &LinkId) -> Option<TokenMetadata> {
+    let link3 = self.internal_get_profile(&link.account_id, &link.profile)?;
+    let item = link3.links().iter().find(|item| item.id() == link.link_id)?;
+
+    Some(TokenMetadata {
+      title: Some(format!("{} pass", item.title())),
+      description: Some(format!("Access to a link of {}", link3.title())),
+      media: item.image_uri().cloned(),
+      copies: item.pass().and_then(|pass| pass.supply_cap),
+      ..Default::default()
+    })
+  }
+
+  // Account owning the pass's link and its royalty, when the link still sells passes
+  pub(crate) fn internal_pass_royalty(&self, token_id: &TokenId) -> Option<(AccountId, u16)> {
+    let link = self.pass_tokens.get(token_id)?.link;
+    let link3 = self.internal_get_profile(&link.account_id, &link.profile)?;
+    let item = link3.links().iter().find(|item| item.id() == link.link_id)?;
+
+    item.pass().map(|pass| (link.account_id.clone(), pass.royalty_bps))
+  }
+
+  // Points the buyers' passes and the grants of the profile's links at the profile's new key
+  pub(crate) fn internal_move_purchases(
+    &mut self,
+    link3: &Link3,
+    from: &ProfileId,
+    to: &ProfileId,
+  ) {
+    for item in link3.links() {
+      let link_of = |profile_id: &ProfileId| LinkId {
+        account_id: profile_id.account_id.clone(),
+        profile: profile_id.profile.clone(),
+        link_id: item.id(),
+      };
+      let (old_link, new_link) = (link_of(from), link_of(to));
+      let grants = match self.access_grants.remove(&old_link) {
+        Some(grants) => grants,
+        None => continue,
+      };
+
+      for buyer_account_id in grants.keys() {
+        let mut purchases = self.purchases.get(&buyer_account_id).unwrap_or_default();
+        for purchase in purchases.iter_mut().filter(|purchase| **purchase == old_link) {
+          *purchase = new_link.clone();
+        }
+        self.purchases.insert(&buyer_account_id, &purchases);
+      }
+      self.access_grants.insert(&new_link, &grants);
+    }
+  }
+
+  // Expiry of the account's access to the link, None once it's gone
+  pub(crate) fn internal_access_expiry(
+    &self,
+    link_id: &LinkId,
+    account_id: &AccountId,
+  ) -> Option<u64> {
+    self
+      .access_grants
+      .get(link_id)?
+      .get(account_id)
+      .filter(|expires_at| *expires_at > env::block_timestamp())
+  }
+
+  // Ids of the profile's links the account holds an active grant of
+  pub(crate) fn internal_granted_links(
+    &self,
+    profile_id: &ProfileId,
+    link3: &Link3,
+    account_id: &AccountId,
+  ) -> Vec<u64> {
+    link3
+      .links()
+      .iter()
+      .map(|item| item.id())
+      .filter(|link_id| {
+        let link = LinkId {
+          account_id: profile_id.account_id.clone(),
+          profile: profile_id.profile.clone(),
+          link_id: *link_id,
+        };
+        self.internal_access_expiry(&link, account_id).is_some()
+      })
+      .collect()
+  }
+
+  // Accounts holding a grant of the link, expired or not
+  pub(crate) fn internal_grantees(&self, link_id: &LinkId) -> Vec<AccountId> {
+    self
+      .access_grants
+      .get(link_id)
+      .map(|grants| grants.keys().collect())
+      .unwrap_or_default()
+  }
+
+  // Grants access until `expires_at`, never shortening an existing grant. A few expired
+  // grants of the link go along the way
+  pub(crate) fn internal_grant_access(
+    &mut self,
+    link_id: &LinkId,
+    account_id: &AccountId,
+    expires_at: u64,
+  ) {
+    let mut grants = match self.access_grants.get(link_id) {
+      Some(grants) => grants,
+      None => {
+        self.access_grant_maps += 1;
+        let id = self.access_grant_maps.to_le_bytes();
+        UnorderedMap::new([ACCESS_GRANTS_MAP_PREFIX, &id].concat())
+      }
+    };
+
+    prune_access_grants(&mut grants);
+    if expires_at > grants.get(account_id).unwrap_or(0) {
+      grants.insert(account_id, &expires_at);
+    }
+    self.access_grants.insert(link_id, &grants);
+  }
+
+  // Drops the account's grant of the link, if it has expired
+  pub(crate) fn internal_drop_expired_grant(&mut self, link_id: &LinkId, account_id: &AccountId) {
+    let mut grants = match self.access_grants.get(link_id) {
+      Some(grants) => grants,
+      None => return,
+    };
+
+    let now = env::block_timestamp();
+    if grants.get(account_id).is_some_and(|expires_at| expires_at <= now) {
+      grants.remove(account_id);
+      self.access_grants.insert(link_id, &grants);
+    }
+  }
+}
+
+// Checks up to `ACCESS_GRANTS_PRUNED` grants from a random one and drops the expired ones,
+// so the grants of past buyers don't pile up
+fn prune_access_grants(grants: &mut UnorderedMap<AccountId, u64>) {
+  let len = grants.len();
+  if len == 0 {
+    return;
+  }
+  let seed = env::random_seed()
+    .iter()
+    .fold(0u64, |seed, byte| seed.wrapping_mul(31).wrapping_add(*byte as u64));
+  let now = env::block_timestamp();
+
+  let expired: Vec<AccountId> = (0..ACCESS_GRANTS_PRUNED.min(len))
+    .filter_map(|offset| grants.keys_as_vector().get(seed.wrapping_add(offset) % len))
+    .filter(|account_id| grants.get(account_id).is_some_and(|expires_at| expires_at <= now))
+    .collect();
+  for account_id in expired {
+    grants.remove(&account_id);
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::item::Price;
+  use crate::test_utils::*;
+  use near_sdk::json_types::U128;
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  const DAY: u64 = 86_400_000_000_000;
+
+  fn robert() -> Option<AccountId> {
+    Some("robert.testnet".to_string())
+  }
+
+  // Alice rents her first link for 10 yocto a day and sells the second one for 20
+  fn generate_hub() -> MainHub {
+    testing_env!(user_context("alice.testnet", None));
+    let mut main = MainHub::default();
+    create_alice_profile(&mut main);
+    main
+  }
+
+  fn create_alice_profile(main: &mut MainHub) {
+    testing_env!(user_context("alice.testnet", None));
+    main.create("Hello".to_string(), "World".to_string(), None, Some(true));
+    for (id, amount) in [(1, 10), (2, 20)] {
+      add_sealed_link(main);
+      main.set_link_prices(
+        id,
+        vec![Price {
+          token_account_id: None,
+          amount: U128(amount),
+        }],
+        None,
+        None,
+      );
+    }
+    main.set_link_access_duration(1, Some(U64(DAY)), None, None);
+  }
+
+  fn buy_at(main: &mut MainHub, link_id: u64, deposit: u128, block_timestamp: u64) {
+    let mut context = user_context("robert.testnet", Some(deposit));
+    context.block_timestamp = block_timestamp;
+    testing_env!(context);
+    main.buy_link("alice.testnet".to_string(), link_id, None);
+  }
+
+  fn robert_can_read_at(main: &MainHub, block_timestamp: u64) -> bool {
+    let mut context = user_context("robert.testnet", None);
+    context.block_timestamp = block_timestamp;
+    testing_env!(context);
+    let links = main.get_links("alice.testnet".to_string(), None, robert());
+    links[0].sealed_uri.is_some()
+  }
+
+  #[test]
+  fn rented_link_expires_after_duration() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    buy_at(&mut main, 1, 10, 5);
+    // Then
+    assert!(robert_can_read_at(&main, DAY));
+    assert!(!robert_can_read_at(&main, DAY + 5));
+  }
+
+  #[test]
+  fn extending_early_adds_remaining_time() {
+    // Given
+    let mut main = generate_hub();
+    buy_at(&mut main, 1, 10, 0);
+    // When
+    buy_at(&mut main, 1, 10, DAY / 2);
+    // Then
+    assert!(robert_can_read_at(&main, 2 * DAY - 1));
+    assert!(!robert_can_read_at(&main, 2 * DAY));
+  }
+
+  #[test]
+  fn renting_after_expiry_starts_from_now() {
+    // Given
+    let mut main = generate_hub();
+    buy_at(&mut main, 1, 10, 0);
+    // When
+    buy_at(&mut main, 1, 10, 3 * DAY);
+    // Then
+    assert!(robert_can_read_at(&main, 4 * DAY - 1));
+    assert!(!robert_can_read_at(&main, 4 * DAY));
+  }
+
+  fn alice_link(link_id: u64) -> LinkId {
+    LinkId {
+      account_id: "alice.testnet".to_string(),
+      profile: None,
+      link_id,
+    }
+  }
+
+  #[test]
+  fn granting_access_never_shortens_grant() {
+    // Given
+    let mut main = generate_hub();
+    buy_at(&mut main, 1, 10, 0);
+    // When
+    main.internal_grant_access(&alice_link(1), &"robert.testnet".to_string(), DAY / 2);
+    // Then
+    assert!(robert_can_read_at(&main, DAY - 1));
+  }
+
+  #[test]
+  fn granting_access_prunes_expired_grants() {
+    // Given
+    let mut main = generate_hub();
+    buy_at(&mut main, 1, 10, 0);
+    // When
+    let mut context = user_context("carol.testnet", Some(10));
+    context.block_timestamp = 2 * DAY;
+    testing_env!(context);
+    main.buy_link("alice.testnet".to_string(), 1, None);
+    // Then
+    assert_eq!(main.internal_grantees(&alice_link(1)), vec!["carol.testnet".to_string()]);
+  }
+
+  #[test]
+  fn grants_are_kept_out_of_profile_state() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    buy_at(&mut main, 2, 20, 0);
+    // Then
+    let link3 = main.get("alice.testnet".to_string()).unwrap();
+    let state = link3.try_to_vec().unwrap();
+    assert!(!state.windows(14).any(|bytes| bytes == b"robert.testnet"));
+  }
+
+  #[test]
+  fn get_passes_lists_active_passes_with_expiry() {
+    // Given
+    let mut main = generate_hub();
+    buy_at(&mut main, 1, 10, 0);
+    buy_at(&mut main, 2, 20, 0);
+    // When
+    let passes = main.get_passes("robert.testnet".to_string());
+    // Then
+    assert_eq!(passes.len(), 2);
+    assert_eq!(passes[0].owner_account_id, "alice.testnet".to_string());
+    assert_eq!(passes[0].link_id, 1);
+    assert_eq!(passes[0].expires_at, Some(U64(DAY)));
+    assert_eq!(passes[1].link_id, 2);
+    assert_eq!(passes[1].expires_at, None);
+  }
+
+  #[test]
+  fn get_passes_skips_expired_passes() {
+    // Given
+    let mut main = generate_hub();
+    buy_at(&mut main, 1, 10, 0);
+    buy_at(&mut main, 2, 20, 0);
+    // When
+    let mut context = user_context("robert.testnet", None);
+    context.block_timestamp = 2 * DAY;
+    testing_env!(context);
+    let passes = main.get_passes("robert.testnet".to_string());
+    // Then
+    assert_eq!(passes.len(), 1);
+    assert_eq!(passes[0].link_id, 2);
+  }
+
+  // Alice sells passes of her second link, with a 10% royalty on resales
+  fn sell_passes(main: &mut MainHub, supply_cap: Option<u64>) {
+    testing_env!(user_context("alice.testnet", None));
+    main.set_link_pass(
+      2,
+      Some(PassSettings {
+        supply_cap,
+        royalty_bps: 1_000,
+      }),
+      None,
+      None,
+    );
+  }
+
+  fn can_read_pass_link(main: &MainHub, account_id: &str) -> bool {
+    let reader = Some(account_id.to_string());
+    main.get_links("alice.testnet".to_string(), None, reader)[1].sealed_uri.is_some()
+  }
+
+  #[test]
+  fn buying_pass_link_mints_pass_to_buyer() {
+    // Given
+    let mut main = generate_hub();
+    sell_passes(&mut main, Some(100));
+    // When
+    buy_at(&mut main, 2, 20, 0);
+    // Then
+    let tokens = main.nft_tokens_for_owner("robert.testnet".to_string(), None, None);
+    assert_eq!(tokens.len(), 1);
+    let metadata = tokens[0].metadata.as_ref().unwrap();
+    assert_eq!(metadata.title, Some("title pass".to_string()));
+    assert_eq!(metadata.copies, Some(100));
+    assert!(can_read_pass_link(&main, "robert.testnet"));
+    assert_eq!(main.get_links("alice.testnet".to_string(), None, None)[1].passes_issued, 1);
+  }
+
+  #[test]
+  fn transferring_pass_moves_access() {
+    // Given
+    let mut main = generate_hub();
+    sell_passes(&mut main, None);
+    buy_at(&mut main, 2, 20, 0);
+    // When
+    testing_env!(user_context("robert.testnet", Some(1)));
+    main.nft_transfer("carol.testnet".to_string(), "1".to_string(), None, None);
+    // Then
+    assert!(can_read_pass_link(&main, "carol.testnet"));
+    assert!(!can_read_pass_link(&main, "robert.testnet"));
+  }
+
+  #[test]
+  fn pass_of_deleted_link_does_not_unlock_next_link() {
+    // Given
+    let mut main = generate_hub();
+    sell_passes(&mut main, None);
+    buy_at(&mut main, 2, 20, 0);
+    testing_env!(user_context("alice.testnet", None));
+    main.delete_link(2, None, None);
+    // When
+    add_sealed_link(&mut main);
+    main.set_link_prices(
+      3,
+      vec![Price {
+        token_account_id: None,
+        amount: U128(30),
+      }],
+      None,
+      None,
+    );
+    testing_env!(user_context("robert.testnet", Some(1)));
+    main.nft_transfer("carol.testnet".to_string(), "1".to_string(), None, None);
+    // Then
+    assert_eq!(main.get_links("alice.testnet".to_string(), None, None)[1].id, 3);
+    assert!(!can_read_pass_link(&main, "carol.testnet"));
+  }
+
+  #[test]
+  fn pass_of_deleted_profile_does_not_unlock_recreated_link() {
+    // Given
+    let mut main = generate_hub();
+    sell_passes(&mut main, None);
+    buy_at(&mut main, 2, 20, 0);
+    testing_env!(user_context("alice.testnet", None));
+    main.delete_profile(None);
+    // When
+    create_alice_profile(&mut main);
+    testing_env!(user_context("robert.testnet", Some(1)));
+    main.nft_transfer("carol.testnet".to_string(), "1".to_string(), None, None);
+    // Then
+    assert!(!can_read_pass_link(&main, "carol.testnet"));
+  }
+
+  #[test]
+  fn marketplace_resale_pays_royalty_to_owner() {
+    // Given
+    let mut main = generate_hub();
+    sell_passes(&mut main, None);
+    buy_at(&mut main, 2, 20, 0);
+    testing_env!(user_context("robert.testnet", Some(1)));
+    main.nft_approve("1".to_string(), "market.testnet".to_string(), None);
+    // When
+    testing_env!(user_context("market.testnet", Some(1)));
+    let payout = main.nft_transfer_payout(
+      "carol.testnet".to_string(),
+      "1".to_string(),
+      Some(1),
+      None,
+      U128(1_000),
+      10,
+    );
+    // Then
+    assert_eq!(payout.payout.get("alice.testnet"), Some(&U128(100)));
+    assert_eq!(payout.payout.get("robert.testnet"), Some(&U128(900)));
+    assert!(can_read_pass_link(&main, "carol.testnet"));
+    assert!(!main.nft_is_approved("1".to_string(), "market.testnet".to_string(), None));
+  }
+
+  #[test]
+  #[should_panic(expected = "Passes of this link are sold out")]
+  fn buying_sold_out_pass_panics() {
+    // Given
+    let mut main = generate_hub();
+    sell_passes(&mut main, Some(1));
+    buy_at(&mut main, 2, 20, 0);
+    // When
+    testing_env!(user_context("carol.testnet", Some(20)));
+    main.buy_link("alice.testnet".to_string(), 2, None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the token owner or an approved account can transfer it")]
+  fn transferring_pass_without_approval_panics() {
+    // Given
+    let mut main = generate_hub();
+    sell_passes(&mut main, None);
+    buy_at(&mut main, 2, 20, 0);
+    // When
+    testing_env!(user_context("market.testnet", Some(1)));
+    main.nft_transfer("carol.testnet".to_string(), "1".to_string(), Some(1), None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Royalty can be at most 5000 basis points")]
+  fn set_link_pass_with_high_royalty_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.set_link_pass(
+      2,
+      Some(PassSettings {
+        supply_cap: None,
+        royalty_bps: 5_001,
+      }),
+      None,
+      None,
+    );
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Link was already bought")]
+  fn buying_permanent_link_twice_panics() {
+    // Given
+    let mut main = generate_hub();
+    buy_at(&mut main, 2, 20, 0);
+    // When
+    buy_at(&mut main, 2, 20, 0);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the owner or an admin can price a link")]
+  fn set_link_access_duration_as_stranger_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    testing_env!(user_context("eve.testnet", None));
+    main.set_link_access_duration(2, None, None, Some("alice.testnet".to_string()));
+    // Then
+    // - Should panic
+  }
+}
@@ -0,0 +1,442 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde_json;
+use near_sdk::{env, log, near_bindgen, AccountId, Promise, PromiseOrValue};
+use serde::Deserialize;
+// Crates
+use crate::fees::FeeSource;
+use crate::item::Price;
+use crate::link3::Link3;
+use crate::profiles::normalize_profile;
+use crate::tags::LinkId;
+use crate::*;
+
+// `msg` of the `ft_transfer_call` buying a link
+#[derive(Deserialize)]
+pub struct LinkPurchaseMsg {
+  pub owner_account_id: AccountId,
+  pub link_id: u64,
+  pub profile: Option<String>,
+}
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * CALL METHODS *
+   ****************/
+  pub fn set_link_prices(
+    &mut self,
+    id: u64,
+    prices: Vec<Price>,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
+  ) -> Link3 {
+    for price in &prices {
+      if let Some(token_account_id) = &price.token_account_id {
+        self.assert_accepted_token(token_account_id);
+      }
+      if price.amount.0 == 0 {
+        env::panic(b"Price must be greater than 0");
+      }
+    }
+
+    let account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
+
+    // Update item
+    link3.update_prices(id, prices);
+
+    // Save to hub state
+    self.internal_save_profile(&account_id, &profile, &link3);
+    link3
+  }
+
+  // Buys access to a link paying in NEAR, any excess deposit is refunded
+  #[payable]
+  pub fn buy_link(
+    &mut self,
+    owner_account_id: AccountId,
+    link_id: u64,
+    profile: Option<String>,
+  ) -> Link3 {
+    let buyer_account_id = env::predecessor_account_id();
+    let deposit = env::attached_deposit();
+
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&owner_account_id, &profile);
+
+    let item = link3.get_link(link_id);
+    let price = item
+      .price(&None)
+      .unwrap_or_else(|| env::panic(b"Link can't be bought with NEAR"));
+    if item.is_up_for_auction() {
+      env::panic(b"Link is up for auction");
+    }
+    let link = LinkId {
+      account_id: owner_account_id.clone(),
+      profile: profile.clone(),
+      link_id,
+    };
+    let has_access = item.holds_pass(&buyer_account_id)
+      || self.internal_access_expiry(&link, &buyer_account_id).is_some();
+    let can_rebuy = item.access_duration().is_some() || item.ticket().is_some();
+    if !can_rebuy && has_access {
+      env::panic(b"Link was already bought");
+    }
+    if deposit < price {
+      env::panic(b"Not enough deposit to buy the link");
+    }
+    let splits = item.splits().clone();
+
+    self.internal_grant_purchase(
+      &mut link3,
+      &owner_account_id,
+      &profile,
+      link_id,
+      &buyer_account_id,
+    );
+    self.internal_save_profile(&owner_account_id, &profile, &link3);
+
+    let proceeds = self.internal_take_fee(FeeSource::Sale, &owner_account_id, &None, price);
+    self.internal_credit_proceeds(&owner_account_id, &splits, &None, proceeds);
+    if deposit > price {
+      Promise::new(buyer_account_id).transfer(deposit - price);
+    }
+
+    link3
+  }
+
+  // NEP-141 receiver, buys the link named in `msg` and returns the unused amount
+  pub fn ft_on_transfer(
+    &mut self,
+    sender_id: AccountId,
+    amount: U128,
+    msg: String,
+  ) -> PromiseOrValue<U128> {
+    let token_account_id = env::predecessor_account_id();
+    self.assert_accepted_token(&token_account_id);
+    let purchase: LinkPurchaseMsg = serde_json::from_str(&msg)
+      .unwrap_or_else(|_| env::panic(b"Message must name the owner_account_id and link_id"));
+
+    let profile = normalize_profile(purchase.profile);
+    let mut link3 = self.internal_expect_profile(&purchase.owner_account_id, &profile);
+
+    let item = link3.get_link(purchase.link_id);
+    let price = item
+      .price(&Some(token_account_id.clone()))
+      .unwrap_or_else(|| env::panic(b"Link can't be bought with this token"));
+    if item.is_up_for_auction() {
+      env::panic(b"Link is up for auction");
+    }
+    let link = LinkId {
+      account_id: purchase.owner_account_id.clone(),
+      profile: profile.clone(),
+      link_id: purchase.link_id,
+    };
+    let has_access = item.holds_pass(&sender_id)
+      || self.internal_access_expiry(&link, &sender_id).is_some();
+    let can_rebuy = item.access_duration().is_some() || item.ticket().is_some();
+    if !can_rebuy && has_access {
+      env::panic(b"Link was already bought");
+    }
+    if amount.0 < price {
+      log!(
+        "Not enough tokens to buy the link, {} needed and {} sent",
+        price,
+        amount.0
+      );
+      return PromiseOrValue::Value(amount);
+    }
+    let splits = item.splits().clone();
+
+    self.internal_grant_purchase(
+      &mut link3,
+      &purchase.owner_account_id,
+      &profile,
+      purchase.link_id,
+      &sender_id,
+    );
+    self.internal_save_profile(&purchase.owner_account_id, &profile, &link3);
+    let token_account_id = Some(token_account_id);
+    let proceeds = self.internal_take_fee(
+      FeeSource::Sale,
+      &purchase.owner_account_id,
+      &token_account_id,
+      price,
+    );
+    self.internal_credit_proceeds(&purchase.owner_account_id, &splits, &token_account_id, proceeds);
+
+    PromiseOrValue::Value(U128(amount.0 - price))
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::*;
+  use near_sdk::json_types::Base64VecU8;
+  use near_sdk::{testing_env, MockedBlockchain};
+  use std::collections::HashMap;
+
+  const USDC: &str = "usdc.testnet";
+
+  fn robert() -> Option<AccountId> {
+    Some("robert.testnet".to_string())
+  }
+
+  fn generate_priced_hub() -> MainHub {
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    let mut main = MainHub::default();
+    main.add_accepted_token(USDC.to_string());
+    testing_env!(user_context("alice.testnet", None));
+    main.create("Hello".to_string(), "World".to_string(), None, Some(true));
+    add_sealed_link(&mut main);
+    main.set_link_prices(
+      1,
+      vec![
+        Price {
+          token_account_id: None,
+          amount: U128(10),
+        },
+        Price {
+          token_account_id: Some(USDC.to_string()),
+          amount: U128(5),
+        },
+      ],
+      None,
+      None,
+    );
+    main
+  }
+
+  fn purchase_msg() -> String {
+    r#"{"owner_account_id":"alice.testnet","link_id":1}"#.to_string()
+  }
+
+  // The mocked token contract calling back after `ft_transfer_call`
+  fn ft_on_transfer_from(main: &mut MainHub, token: &str, amount: u128) -> u128 {
+    testing_env!(get_context("robert.testnet", token, None));
+    match main.ft_on_transfer("robert.testnet".to_string(), U128(amount), purchase_msg()) {
+      PromiseOrValue::Value(unused) => unused.0,
+      PromiseOrValue::Promise(_) => panic!("Expected a value"),
+    }
+  }
+
+  fn robert_can_read(main: &MainHub) -> bool {
+    testing_env!(user_context("robert.testnet", None));
+    let links = main.get_links("alice.testnet".to_string(), None, robert());
+    links[0].sealed_uri.is_some()
+  }
+
+  fn usdc_balance(main: &MainHub) -> u128 {
+    main
+      .get_balance("alice.testnet".to_string())
+      .tokens
+      .get(USDC)
+      .map_or(0, |amount| amount.0)
+  }
+
+  #[test]
+  fn buy_link_with_near_grants_access() {
+    // Given
+    let mut main = generate_priced_hub();
+    // When
+    testing_env!(user_context("robert.testnet", Some(10)));
+    main.buy_link("alice.testnet".to_string(), 1, None);
+    // Then
+    assert!(robert_can_read(&main));
+  }
+
+  #[test]
+  fn buy_link_with_near_credits_owner_and_refunds_excess() {
+    // Given
+    let mut main = generate_priced_hub();
+    // When
+    testing_env!(user_context("robert.testnet", Some(15)));
+    main.buy_link("alice.testnet".to_string(), 1, None);
+    // Then
+    assert_eq!(main.get_balance("alice.testnet".to_string()).near, U128(10));
+    let receipts = created_receipts();
+    assert!(!receipts.contains(r#""receiver_id":"alice.testnet""#));
+    assert!(receipts.contains(
+      r#""receiver_id":"robert.testnet","actions":[{"Transfer":{"deposit":5}}]"#
+    ));
+  }
+
+  #[test]
+  #[should_panic(expected = "Not enough deposit to buy the link")]
+  fn buy_link_with_low_deposit_panics() {
+    // Given
+    let mut main = generate_priced_hub();
+    // When
+    testing_env!(user_context("robert.testnet", Some(9)));
+    main.buy_link("alice.testnet".to_string(), 1, None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Link was already bought")]
+  fn buy_link_twice_panics() {
+    // Given
+    let mut main = generate_priced_hub();
+    testing_env!(user_context("robert.testnet", Some(10)));
+    main.buy_link("alice.testnet".to_string(), 1, None);
+    // When
+    main.buy_link("alice.testnet".to_string(), 1, None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Price must be greater than 0")]
+  fn set_link_prices_with_zero_amount_panics() {
+    // Given
+    let mut main = generate_priced_hub();
+    // When
+    main.set_link_prices(
+      1,
+      vec![Price {
+        token_account_id: None,
+        amount: U128(0),
+      }],
+      None,
+      None,
+    );
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  fn ft_on_transfer_with_exact_amount_credits_owner() {
+    // Given
+    let mut main = generate_priced_hub();
+    // When
+    let unused = ft_on_transfer_from(&mut main, USDC, 5);
+    // Then
+    assert_eq!(unused, 0);
+    assert_eq!(usdc_balance(&main), 5);
+    assert!(robert_can_read(&main));
+  }
+
+  #[test]
+  fn ft_on_transfer_with_overpayment_returns_unused_amount() {
+    // Given
+    let mut main = generate_priced_hub();
+    // When
+    let unused = ft_on_transfer_from(&mut main, USDC, 8);
+    // Then
+    assert_eq!(unused, 3);
+    assert_eq!(usdc_balance(&main), 5);
+  }
+
+  #[test]
+  fn ft_on_transfer_with_underpayment_refunds_everything() {
+    // Given
+    let mut main = generate_priced_hub();
+    // When
+    let unused = ft_on_transfer_from(&mut main, USDC, 4);
+    // Then
+    assert_eq!(unused, 4, "The whole amount should be refunded");
+    assert_eq!(usdc_balance(&main), 0);
+    assert!(!robert_can_read(&main));
+  }
+
+  #[test]
+  fn ft_on_transfer_with_profile_buys_link_on_that_profile() {
+    // Given
+    let mut main = generate_priced_hub();
+    main.create_profile(
+      "events".to_string(),
+      "Events".to_string(),
+      "My events".to_string(),
+      None,
+      Some(true),
+    );
+    main.add_sealed_link(
+      Base64VecU8(b"ciphertext".to_vec()),
+      HashMap::new(),
+      "title".to_string(),
+      "description".to_string(),
+      None,
+      Some("events".to_string()),
+      None,
+    );
+    main.set_link_prices(
+      1,
+      vec![Price {
+        token_account_id: Some(USDC.to_string()),
+        amount: U128(2),
+      }],
+      Some("events".to_string()),
+      None,
+    );
+    // When
+    testing_env!(get_context("robert.testnet", USDC, None));
+    let msg = r#"{"owner_account_id":"alice.testnet","link_id":1,"profile":"events"}"#;
+    main.ft_on_transfer("robert.testnet".to_string(), U128(2), msg.to_string());
+    // Then
+    assert_eq!(usdc_balance(&main), 2);
+    assert!(!robert_can_read(&main), "Default profile link should stay locked");
+    let events = main.get_links("alice.testnet".to_string(), Some("events".to_string()), robert());
+    assert!(events[0].sealed_uri.is_some());
+  }
+
+  #[test]
+  #[should_panic(expected = "Link can't be bought with this token")]
+  fn ft_on_transfer_with_unlisted_token_panics() {
+    // Given
+    let mut main = generate_priced_hub();
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    main.add_accepted_token("dai.testnet".to_string());
+    // When
+    ft_on_transfer_from(&mut main, "dai.testnet", 5);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Token is not accepted")]
+  fn ft_on_transfer_with_token_not_accepted_panics() {
+    // Given
+    let mut main = generate_priced_hub();
+    // When
+    ft_on_transfer_from(&mut main, "dai.testnet", 5);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Token is not accepted")]
+  fn set_link_prices_with_token_not_accepted_panics() {
+    // Given
+    let mut main = generate_priced_hub();
+    // When
+    main.set_link_prices(
+      1,
+      vec![Price {
+        token_account_id: Some("dai.testnet".to_string()),
+        amount: U128(5),
+      }],
+      None,
+      None,
+    );
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Message must name the owner_account_id and link_id")]
+  fn ft_on_transfer_with_invalid_msg_panics() {
+    // Given
+    let mut main = generate_priced_hub();
+    // When
+    testing_env!(get_context("robert.testnet", USDC, None));
+    main.ft_on_transfer("robert.testnet".to_string(), U128(5), "1".to_string());
+    // Then
+    // - Should panic
+  }
+}
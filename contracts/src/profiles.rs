@@ -0,0 +1,423 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{env, near_bindgen, AccountId};
+use serde::{Deserialize, Serialize};
+// Crates
+use crate::attestations::Attestation;
+use crate::link3::Link3;
+use crate::tags::LinkId;
+use crate::*;
+
+// Slug of the profile stored in `hub`, the one `get(account_id)` returns
+pub const DEFAULT_PROFILE: &str = "default";
+const PROFILE_SLUG_MIN_LENGTH: usize = 3;
+const PROFILE_SLUG_MAX_LENGTH: usize = 30;
+
+// Any profile of the hub, `profile` is None for the account's default profile
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct ProfileId {
+  pub account_id: AccountId,
+  pub profile: Option<String>,
+}
+
+// The profile's fields along with the attestations vouching for it
+#[derive(Serialize)]
+pub struct ProfileView {
+  #[serde(flatten)]
+  pub link3: Link3,
+  // Active attestations only
+  pub attestations: Vec<Attestation>,
+}
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  pub fn get_profile(&self, account_id: AccountId, profile: Option<String>) -> Option<ProfileView> {
+    let profile_id = ProfileId {
+      account_id,
+      profile: normalize_profile(profile),
+    };
+    let link3 = self.internal_get_profile(&profile_id.account_id, &profile_id.profile)?;
+
+    Some(ProfileView {
+      link3,
+      attestations: self.internal_active_attestations(&profile_id),
+    })
+  }
+
+  // Slugs of the account's profiles, starting with the default one
+  pub fn list_profiles(&self, account_id: AccountId) -> Vec<String> {
+    let mut profiles = vec![];
+    if self.internal_get_profile(&account_id, &None).is_some() {
+      profiles.push(DEFAULT_PROFILE.to_string());
+    }
+    profiles.extend(self.profile_slugs.get(&account_id).unwrap_or_default());
+    profiles
+  }
+
+  /****************
+   * CALL METHODS *
+   ****************/
+  pub fn create_profile(
+    &mut self,
+    profile: String,
+    title: String,
+    description: String,
+    image_uri: Option<String>,
+    is_published: Option<bool>,
+  ) -> Link3 {
+    let account_id = env::signer_account_id();
+    let profile = normalize_profile(Some(profile));
+    if self.internal_get_profile(&account_id, &profile).is_some() {
+      env::panic(b"Can't create, account has this profile already")
+    }
+
+    let link3 = Link3::new(title, description, image_uri, is_published);
+    self.internal_save_profile(&account_id, &profile, &link3);
+
+    link3
+  }
+
+  pub fn update_published_status(&mut self, is_published: bool, profile: Option<String>) -> Link3 {
+    let account_id = env::signer_account_id();
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
+
+    link3.update_published_status(is_published);
+
+    self.internal_save_profile(&account_id, &profile, &link3);
+    link3
+  }
+
+  // Burns the profile's token too, if it was minted, and frees the default profile's handle
+  pub fn delete_profile(&mut self, profile: Option<String>) {
+    let profile_id = ProfileId {
+      account_id: env::signer_account_id(),
+      profile: normalize_profile(profile),
+    };
+
+    // Bids held for the profile's auctions would have nowhere to go
+    let link3 = self.internal_expect_profile(&profile_id.account_id, &profile_id.profile);
+    if link3.has_auctions() {
+      env::panic(b"Profile has links up for auction");
+    }
+
+    self.internal_remove_profile(&profile_id);
+    for item in link3.links() {
+      self.access_grants.remove(&LinkId {
+        account_id: profile_id.account_id.clone(),
+        profile: profile_id.profile.clone(),
+        link_id: item.id(),
+      });
+    }
+    self.internal_burn_profile_token(&profile_id);
+    if profile_id.profile.is_none() {
+      self.internal_release_handle(&profile_id.account_id);
+    }
+  }
+}
+
+impl MainHub {
+  /************
+   * INTERNAL *
+   ************/
+  // `profile` is a normalized slug, None being the default profile
+  pub(crate) fn internal_get_profile(
+    &self,
+    account_id: &AccountId,
+    profile: &Option<String>,
+  ) -> Option<Link3> {
+    match profile {
+      Some(slug) => self.profiles.get(&(account_id.clone(), slug.clone())),
+      None => self
+        .hub
+        .get(account_id)
+        .or_else(|| self.legacy_hub.get(account_id).map(Link3::from_legacy)),
+    }
+  }
+
+  pub(crate) fn internal_expect_profile(
+    &self,
+    account_id: &AccountId,
+    profile: &Option<String>,
+  ) -> Link3 {
+    self
+      .internal_get_profile(account_id, profile)
+      .unwrap_or_else(|| env::panic(b"Could not find link3 for this account."))
+  }
+
+  // Takes the profile out of every index, attestations about it go too
+  pub(crate) fn internal_remove_profile(&mut self, profile_id: &ProfileId) -> Option<Link3> {
+    let ProfileId { account_id, profile } = profile_id;
+    let previous = match profile {
+      Some(slug) => {
+        let previous = self.profiles.remove(&(account_id.clone(), slug.clone()));
+        let mut slugs = self.profile_slugs.get(account_id).unwrap_or_default();
+        slugs.retain(|other| other != slug);
+        self.profile_slugs.insert(account_id, &slugs);
+        previous
+      }
+      None => self
+        .hub
+        .remove(account_id)
+        .or_else(|| self.legacy_hub.remove(account_id).map(Link3::from_legacy)),
+    };
+
+    if previous.is_some() {
+      self.internal_sync_directory(profile_id, false);
+      self.internal_sync_tags(profile_id, previous.as_ref(), None);
+      self.internal_set_attestations(profile_id, vec![]);
+    }
+    previous
+  }
+
+  pub(crate) fn internal_save_profile(
+    &mut self,
+    account_id: &AccountId,
+    profile: &Option<String>,
+    link3: &Link3,
+  ) {
+    let previous = match profile {
+      Some(slug) => {
+        let key = (account_id.clone(), slug.clone());
+        let previous = self.profiles.insert(&key, link3);
+        if previous.is_none() {
+          let mut slugs = self.profile_slugs.get(account_id).unwrap_or_default();
+          slugs.push(slug.clone());
+          self.profile_slugs.insert(account_id, &slugs);
+        }
+        previous
+      }
+      None => self
+        .hub
+        .insert(account_id, link3)
+        .or_else(|| self.legacy_hub.remove(account_id).map(Link3::from_legacy)),
+    };
+
+    let profile_id = ProfileId {
+      account_id: account_id.clone(),
+      profile: profile.clone(),
+    };
+    self.internal_sync_directory(&profile_id, link3.is_published());
+    self.internal_sync_tags(&profile_id, previous.as_ref(), Some(link3));
+  }
+}
+
+// Lowercases the selector and maps the default profile to None, panics when it isn't a valid slug
+pub fn normalize_profile(profile: Option<String>) -> Option<String> {
+  let slug = profile?.to_lowercase();
+  if slug == DEFAULT_PROFILE {
+    return None;
+  }
+
+  if slug.len() < PROFILE_SLUG_MIN_LENGTH {
+    panic!(
+      "Profile must be at least {} characters long",
+      PROFILE_SLUG_MIN_LENGTH
+    );
+  }
+
+  if slug.len() > PROFILE_SLUG_MAX_LENGTH {
+    panic!(
+      "Profile must be at most {} characters long",
+      PROFILE_SLUG_MAX_LENGTH
+    );
+  }
+
+  if !slug
+    .chars()
+    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+  {
+    panic!("Profile can only contain letters, numbers, _ and -");
+  }
+
+  Some(slug)
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::*;
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  fn generate_hub() -> MainHub {
+    testing_env!(user_context("alice.testnet", None));
+    let mut main = MainHub::default();
+    main.create("Hello".to_string(), "World".to_string(), None, Some(true));
+    main.create_profile(
+      "events".to_string(),
+      "Events".to_string(),
+      "My events".to_string(),
+      None,
+      Some(true),
+    );
+    main
+  }
+
+  fn add_link(main: &mut MainHub, title: &str, profile: Option<String>) -> Link3 {
+    main.add_link(
+      "uri".to_string(),
+      title.to_string(),
+      "description".to_string(),
+      None,
+      profile,
+      None,
+    )
+  }
+
+  #[test]
+  fn normalize_profile_maps_default_to_none() {
+    assert_eq!(normalize_profile(None), None);
+    assert_eq!(normalize_profile(Some("Default".to_string())), None);
+    assert_eq!(
+      normalize_profile(Some("Events".to_string())),
+      Some("events".to_string())
+    );
+  }
+
+  #[test]
+  #[should_panic(expected = "Profile can only contain letters, numbers, _ and -")]
+  fn normalize_profile_with_invalid_chars_panics() {
+    normalize_profile(Some("my events".to_string()));
+  }
+
+  #[test]
+  fn create_profile_keeps_default_profile() {
+    // Given
+    let main = generate_hub();
+    // Then
+    let default = main.get("alice.testnet".to_string()).unwrap();
+    let events = main
+      .get_profile("alice.testnet".to_string(), Some("events".to_string()))
+      .unwrap()
+      .link3;
+    assert_eq!(default.info().0, "Hello".to_string());
+    assert_eq!(events.info().0, "Events".to_string());
+  }
+
+  #[test]
+  #[should_panic(expected = "Can't create, account has this profile already")]
+  fn create_profile_twice_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.create_profile(
+      "EVENTS".to_string(),
+      "Events".to_string(),
+      "My events".to_string(),
+      None,
+      Some(true),
+    );
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  fn list_profiles_returns_default_first() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.create_profile(
+      "project".to_string(),
+      "Project".to_string(),
+      "My project".to_string(),
+      None,
+      Some(true),
+    );
+    // Then
+    assert_eq!(
+      main.list_profiles("alice.testnet".to_string()),
+      vec![
+        "default".to_string(),
+        "events".to_string(),
+        "project".to_string()
+      ]
+    );
+    assert!(main.list_profiles("robert.testnet".to_string()).is_empty());
+  }
+
+  #[test]
+  fn add_link_with_selector_only_changes_that_profile() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    add_link(&mut main, "event link", Some("events".to_string()));
+    // Then
+    let default = main.get("alice.testnet".to_string()).unwrap();
+    let events = main
+      .get_profile("alice.testnet".to_string(), Some("events".to_string()))
+      .unwrap()
+      .link3;
+    assert!(default.list(None, &[], &[]).is_empty());
+    assert_eq!(events.list(None, &[], &[])[0].title, "event link".to_string());
+  }
+
+  #[test]
+  fn add_link_with_default_selector_changes_default_profile() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    add_link(&mut main, "personal link", Some("default".to_string()));
+    // Then
+    let default = main.get("alice.testnet".to_string()).unwrap();
+    assert_eq!(default.list(None, &[], &[])[0].title, "personal link".to_string());
+  }
+
+  #[test]
+  #[should_panic(expected = "Could not find link3 for this account.")]
+  fn add_link_to_missing_profile_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    add_link(&mut main, "link", Some("meetups".to_string()));
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  fn link_limit_is_per_profile() {
+    // Given
+    let mut main = generate_hub();
+    for _i in 0..LINK_LIMIT {
+      add_link(&mut main, "link", None);
+    }
+    // When
+    let events = add_link(&mut main, "link", Some("events".to_string()));
+    // Then
+    assert_eq!(events.list(None, &[], &[]).len(), 1);
+  }
+
+  #[test]
+  fn delete_profile_removes_named_profile() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.delete_profile(Some("events".to_string()));
+    // Then
+    assert!(main
+      .get_profile("alice.testnet".to_string(), Some("events".to_string()))
+      .is_none());
+    assert_eq!(
+      main.list_profiles("alice.testnet".to_string()),
+      vec!["default".to_string()]
+    );
+  }
+
+  #[test]
+  fn delete_default_profile_keeps_named_profiles() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.delete_profile(None);
+    // Then
+    assert!(main.get("alice.testnet".to_string()).is_none());
+    assert_eq!(
+      main.list_profiles("alice.testnet".to_string()),
+      vec!["events".to_string()]
+    );
+  }
+}
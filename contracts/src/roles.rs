@@ -0,0 +1,347 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{env, near_bindgen, AccountId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+// Crates
+use crate::link3::Link3;
+use crate::profiles::normalize_profile;
+use crate::*;
+
+// Ordered from the least to the most privileged role
+#[derive(
+  BorshSerialize,
+  BorshDeserialize,
+  Serialize,
+  Deserialize,
+  Clone,
+  Copy,
+  PartialEq,
+  PartialOrd,
+  Debug,
+)]
+pub enum Role {
+  // Can add and update links
+  Editor,
+  // Can also delete and price links, and manage collaborators
+  Admin,
+  Owner,
+}
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  pub fn get_roles(
+    &self,
+    account_id: AccountId,
+    profile: Option<String>,
+  ) -> HashMap<AccountId, Role> {
+    self
+      .internal_expect_profile(&account_id, &normalize_profile(profile))
+      .roles()
+      .clone()
+  }
+
+  /****************
+   * CALL METHODS *
+   ****************/
+  pub fn grant_role(
+    &mut self,
+    account_id: AccountId,
+    role: Role,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
+  ) -> Link3 {
+    if !env::is_valid_account_id(account_id.as_bytes()) {
+      env::panic(b"Collaborator account id is not valid");
+    }
+
+    let owner_account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&owner_account_id, &profile);
+
+    link3.grant_role(account_id, role);
+
+    // Save to hub state
+    self.internal_save_profile(&owner_account_id, &profile, &link3);
+    link3
+  }
+
+  pub fn revoke_role(
+    &mut self,
+    account_id: AccountId,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
+  ) -> Link3 {
+    let owner_account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&owner_account_id, &profile);
+
+    link3.revoke_role(&account_id);
+
+    // Save to hub state
+    self.internal_save_profile(&owner_account_id, &profile, &link3);
+    link3
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::item::Price;
+  use crate::test_utils::*;
+  use near_sdk::json_types::{Base64VecU8, U128};
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  const OWNER: &str = "alice.testnet";
+  const ADMIN: &str = "jane.testnet";
+  const EDITOR: &str = "robert.testnet";
+  const STRANGER: &str = "eve.testnet";
+
+  fn owner() -> Option<AccountId> {
+    Some(OWNER.to_string())
+  }
+
+  // Alice owns a Link3 with one link, Jane is an admin and Robert an editor
+  fn generate_hub() -> MainHub {
+    testing_env!(user_context(OWNER, None));
+    let mut main = MainHub::default();
+    main.create("Hello".to_string(), "World".to_string(), None, Some(true));
+    main.add_link(
+      "uri".to_string(),
+      "title".to_string(),
+      "description".to_string(),
+      None,
+      None,
+      None,
+    );
+    main.grant_role(ADMIN.to_string(), Role::Admin, None, None);
+    main.grant_role(EDITOR.to_string(), Role::Editor, None, None);
+    main
+  }
+
+  fn add_link_as(main: &mut MainHub, account_id: &str) -> Link3 {
+    testing_env!(user_context(account_id, None));
+    main.add_link(
+      "another_uri".to_string(),
+      "another title".to_string(),
+      "description".to_string(),
+      None,
+      None,
+      owner(),
+    )
+  }
+
+  fn update_link_as(main: &mut MainHub, account_id: &str) -> Link3 {
+    testing_env!(user_context(account_id, None));
+    main.update_link(
+      1,
+      "uri".to_string(),
+      "updated title".to_string(),
+      "description".to_string(),
+      None,
+      None,
+      owner(),
+    )
+  }
+
+  fn delete_link_as(main: &mut MainHub, account_id: &str) -> Link3 {
+    testing_env!(user_context(account_id, None));
+    main.delete_link(1, None, owner())
+  }
+
+  fn price_link_as(main: &mut MainHub, account_id: &str) -> Link3 {
+    testing_env!(user_context(account_id, None));
+    main.set_link_prices(
+      1,
+      vec![Price {
+        token_account_id: None,
+        amount: U128(10),
+      }],
+      None,
+      owner(),
+    )
+  }
+
+  #[test]
+  fn grant_role_adds_collaborator() {
+    // Given
+    let main = generate_hub();
+    // Then
+    let roles = main.get_roles(OWNER.to_string(), None);
+    assert_eq!(roles.get(ADMIN), Some(&Role::Admin));
+    assert_eq!(roles.get(EDITOR), Some(&Role::Editor));
+  }
+
+  #[test]
+  fn owner_has_owner_role() {
+    // Given
+    let main = generate_hub();
+    // Then
+    let link3 = main.get(OWNER.to_string()).unwrap();
+    assert_eq!(link3.role_of(&OWNER.to_string()), Some(Role::Owner));
+    assert_eq!(link3.role_of(&STRANGER.to_string()), None);
+  }
+
+  #[test]
+  #[should_panic(expected = "The owner role can't be granted")]
+  fn grant_owner_role_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.grant_role(STRANGER.to_string(), Role::Owner, None, None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  fn editor_can_add_link() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    let link3 = add_link_as(&mut main, EDITOR);
+    // Then
+    assert_eq!(link3.list(None, &[], &[]).len(), 2);
+  }
+
+  #[test]
+  fn editor_can_update_link() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    let link3 = update_link_as(&mut main, EDITOR);
+    // Then
+    assert_eq!(link3.list(None, &[], &[])[0].title, "updated title".to_string());
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the owner or an admin can delete a link")]
+  fn editor_cannot_delete_link() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    delete_link_as(&mut main, EDITOR);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the owner or an admin can price a link")]
+  fn editor_cannot_price_link() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    price_link_as(&mut main, EDITOR);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the owner or an admin can manage collaborators")]
+  fn editor_cannot_grant_roles() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    testing_env!(user_context(EDITOR, None));
+    main.grant_role(STRANGER.to_string(), Role::Editor, None, owner());
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  fn admin_can_delete_link() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    let link3 = delete_link_as(&mut main, ADMIN);
+    // Then
+    assert!(link3.list(None, &[], &[]).is_empty());
+  }
+
+  #[test]
+  fn admin_can_price_link() {
+    // Given
+    let mut main = generate_hub();
+    main.seal_link(1, Base64VecU8(b"ciphertext".to_vec()), None, None);
+    // When
+    let link3 = price_link_as(&mut main, ADMIN);
+    // Then
+    assert_eq!(link3.get_link(1).price(&None), Some(10));
+  }
+
+  #[test]
+  fn admin_can_manage_collaborators() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    testing_env!(user_context(ADMIN, None));
+    main.grant_role(STRANGER.to_string(), Role::Editor, None, owner());
+    main.revoke_role(EDITOR.to_string(), None, owner());
+    // Then
+    let roles = main.get_roles(OWNER.to_string(), None);
+    assert_eq!(roles.get(STRANGER), Some(&Role::Editor));
+    assert_eq!(roles.get(EDITOR), None);
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the owner can update the contract.")]
+  fn admin_cannot_update_profile() {
+    // Given
+    let main = generate_hub();
+    // When
+    testing_env!(user_context(ADMIN, None));
+    let mut link3 = main.get(OWNER.to_string()).unwrap();
+    link3.update("title".to_string(), "description".to_string(), None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the owner or a collaborator can create a link")]
+  fn stranger_cannot_add_link() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    add_link_as(&mut main, STRANGER);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the owner or a collaborator can update a link")]
+  fn stranger_cannot_update_link() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    update_link_as(&mut main, STRANGER);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the owner or a collaborator can create a link")]
+  fn revoked_editor_cannot_add_link() {
+    // Given
+    let mut main = generate_hub();
+    main.revoke_role(EDITOR.to_string(), None, None);
+    // When
+    add_link_as(&mut main, EDITOR);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Account is not a collaborator")]
+  fn revoke_role_of_stranger_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.revoke_role(STRANGER.to_string(), None, None);
+    // Then
+    // - Should panic
+  }
+}
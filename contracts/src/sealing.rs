@@ -0,0 +1,540 @@
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::{env, near_bindgen, AccountId};
+use serde::Serialize;
+use std::collections::HashMap;
+// Crates
+use crate::link3::Link3;
+use crate::profiles::normalize_profile;
+use crate::tags::LinkId;
+use crate::*;
+
+// Length of an X25519 public key
+pub const ENCRYPTION_KEY_LENGTH: usize = 32;
+
+// A reader of a sealed link still waiting for its wrapped content key
+#[derive(Serialize, Debug)]
+pub struct KeyRequest {
+  pub link_id: u64,
+  pub account_id: AccountId,
+  pub public_key: Base64VecU8,
+}
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  pub fn get_encryption_key(&self, account_id: AccountId) -> Option<Base64VecU8> {
+    self.encryption_keys.get(&account_id).map(Base64VecU8)
+  }
+
+  // Readers of the profile's sealed links with a registered key and no envelope yet,
+  // what the owner or their key service has to deliver
+  pub fn get_pending_keys(
+    &self,
+    account_id: AccountId,
+    profile: Option<String>,
+  ) -> Vec<KeyRequest> {
+    let profile = normalize_profile(profile);
+    let link3 = self.internal_expect_profile(&account_id, &profile);
+
+    let mut requests = vec![];
+    for item in link3.links().iter().filter(|item| item.is_sealed()) {
+      let link = LinkId {
+        account_id: account_id.clone(),
+        profile: profile.clone(),
+        link_id: item.id(),
+      };
+      let mut readers = self.internal_grantees(&link);
+      readers.extend(link3.members().cloned());
+      readers.sort();
+      readers.dedup();
+
+      for reader in readers.iter() {
+        let is_granted = self.internal_access_expiry(&link, reader).is_some();
+        if item.key_envelope(reader).is_some() || !link3.can_read(item, reader, is_granted) {
+          continue;
+        }
+        if let Some(public_key) = self.encryption_keys.get(reader) {
+          requests.push(KeyRequest {
+            link_id: item.id(),
+            account_id: reader.clone(),
+            public_key: Base64VecU8(public_key),
+          });
+        }
+      }
+    }
+    requests
+  }
+
+  /****************
+   * CALL METHODS *
+   ****************/
+  // X25519 public key sealed links' content keys get wrapped for
+  pub fn register_encryption_key(&mut self, public_key: Base64VecU8) {
+    if public_key.0.len() != ENCRYPTION_KEY_LENGTH {
+      panic!("Encryption key must be {} bytes long", ENCRYPTION_KEY_LENGTH);
+    }
+
+    self
+      .encryption_keys
+      .insert(&env::predecessor_account_id(), &public_key.0);
+  }
+
+  // Adds a link whose uri is sealed from the start, with the content key already wrapped
+  // for the readers in `key_envelopes`. The link is gated afterwards like any other
+  #[allow(clippy::too_many_arguments)]
+  pub fn add_sealed_link(
+    &mut self,
+    sealed_uri: Base64VecU8,
+    key_envelopes: HashMap<AccountId, Base64VecU8>,
+    title: String,
+    description: String,
+    image_uri: Option<String>,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
+  ) -> Link3 {
+    if sealed_uri.0.is_empty() {
+      env::panic(b"Sealed uri cannot be empty");
+    }
+    for (account_id, key_envelope) in key_envelopes.iter() {
+      if key_envelope.0.is_empty() {
+        env::panic(b"Key envelope cannot be empty");
+      }
+      if self.encryption_keys.get(account_id).is_none() {
+        env::panic(b"Account has not registered an encryption key");
+      }
+    }
+
+    let account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
+
+    // Add item
+    if link3.link_count() >= LINK_LIMIT as usize {
+      panic!("You can only have {} links", LINK_LIMIT);
+    }
+    let key_envelopes = key_envelopes
+      .into_iter()
+      .map(|(account_id, key_envelope)| (account_id, key_envelope.0))
+      .collect();
+    link3.create_sealed_link(sealed_uri.0, key_envelopes, title, description, image_uri);
+
+    // Save to hub state
+    self.internal_save_profile(&account_id, &profile, &link3);
+    link3
+  }
+
+  // Replaces the uri of a link with its ciphertext, readers need a new envelope. Links are
+  // sealed before they're gated, so a gated uri is never in state in plaintext
+  pub fn seal_link(
+    &mut self,
+    id: u64,
+    sealed_uri: Base64VecU8,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
+  ) -> Link3 {
+    if sealed_uri.0.is_empty() {
+      env::panic(b"Sealed uri cannot be empty");
+    }
+
+    let account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
+
+    // Update item
+    link3.seal_link(id, sealed_uri.0);
+
+    // Save to hub state
+    self.internal_save_profile(&account_id, &profile, &link3);
+    link3
+  }
+
+  // Turns a sealed link back into a plain one once it is ungated, `uri` is public from then on
+  pub fn unseal_link(
+    &mut self,
+    id: u64,
+    uri: String,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
+  ) -> Link3 {
+    let account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
+
+    // Update item
+    link3.unseal_link(id, uri);
+
+    // Save to hub state
+    self.internal_save_profile(&account_id, &profile, &link3);
+    link3
+  }
+
+  // Posts the link's content key wrapped for the reader's registered encryption key
+  pub fn deliver_key(
+    &mut self,
+    id: u64,
+    account_id: AccountId,
+    key_envelope: Base64VecU8,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
+  ) -> Link3 {
+    if key_envelope.0.is_empty() {
+      env::panic(b"Key envelope cannot be empty");
+    }
+    if self.encryption_keys.get(&account_id).is_none() {
+      env::panic(b"Account has not registered an encryption key");
+    }
+
+    let owner_account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&owner_account_id, &profile);
+
+    // Update item
+    let link = LinkId {
+      account_id: owner_account_id.clone(),
+      profile: profile.clone(),
+      link_id: id,
+    };
+    let is_granted = self.internal_access_expiry(&link, &account_id).is_some();
+    link3.deliver_key(id, account_id, key_envelope.0, is_granted);
+
+    // Save to hub state
+    self.internal_save_profile(&owner_account_id, &profile, &link3);
+    link3
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::item::{Price, TokenGate};
+  use crate::test_utils::*;
+  use link3_crypto::{
+    decrypt_uri, encrypt_uri, generate_content_key, generate_secret, unwrap_key, wrap_key,
+    ContentKey, PublicKey, StaticSecret,
+  };
+  use near_sdk::borsh::BorshSerialize;
+  use near_sdk::json_types::U128;
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  const SECRET_URI: &str = "https://example.com/members-only";
+
+  // Alice sells a link for 10 yocto with its uri sealed under `content_key`
+  fn generate_hub(content_key: &ContentKey) -> MainHub {
+    testing_env!(user_context("alice.testnet", None));
+    let mut main = MainHub::default();
+    main.create("Hello".to_string(), "World".to_string(), None, Some(true));
+    main.add_sealed_link(
+      Base64VecU8(encrypt_uri(SECRET_URI, content_key)),
+      HashMap::new(),
+      "title".to_string(),
+      "description".to_string(),
+      None,
+      None,
+      None,
+    );
+    main.set_link_prices(
+      1,
+      vec![Price {
+        token_account_id: None,
+        amount: U128(10),
+      }],
+      None,
+      None,
+    );
+    main
+  }
+
+  fn update_uri(main: &mut MainHub, uri: &str) {
+    testing_env!(user_context("alice.testnet", None));
+    main.update_link(
+      1,
+      uri.to_string(),
+      "new title".to_string(),
+      "description".to_string(),
+      None,
+      None,
+      None,
+    );
+  }
+
+  // Robert registers his key and buys the link
+  fn register_and_buy(main: &mut MainHub) -> StaticSecret {
+    let secret = generate_secret();
+    testing_env!(user_context("robert.testnet", Some(10)));
+    main.register_encryption_key(Base64VecU8(PublicKey::from(&secret).as_bytes().to_vec()));
+    main.buy_link("alice.testnet".to_string(), 1, None);
+    secret
+  }
+
+  // Stand-in key service: wraps the content key for every pending reader
+  fn run_key_service(main: &mut MainHub, content_key: &ContentKey) {
+    testing_env!(user_context("alice.testnet", None));
+    for request in main.get_pending_keys("alice.testnet".to_string(), None) {
+      let mut public_key = [0u8; ENCRYPTION_KEY_LENGTH];
+      public_key.copy_from_slice(&request.public_key.0);
+      let envelope = wrap_key(content_key, &PublicKey::from(public_key));
+      main.deliver_key(
+        request.link_id,
+        request.account_id,
+        Base64VecU8(envelope),
+        None,
+        None,
+      );
+    }
+  }
+
+  #[test]
+  fn sealed_uri_is_not_in_contract_state() {
+    // Given
+    let main = generate_hub(&generate_content_key());
+    // When
+    let state = main.get("alice.testnet".to_string()).unwrap().try_to_vec().unwrap();
+    // Then
+    assert!(!state
+      .windows(SECRET_URI.len())
+      .any(|window| window == SECRET_URI.as_bytes()));
+  }
+
+  #[test]
+  fn gated_links_have_no_plaintext_uri_in_state() {
+    // Given
+    let content_key = generate_content_key();
+    let mut main = generate_hub(&content_key);
+    main.add_link(
+      SECRET_URI.to_string(),
+      "title".to_string(),
+      "description".to_string(),
+      None,
+      None,
+      None,
+    );
+    // When
+    main.seal_link(2, Base64VecU8(encrypt_uri(SECRET_URI, &content_key)), None, None);
+    main.set_token_gate(
+      2,
+      Some(TokenGate {
+        token_account_id: "token.testnet".to_string(),
+        min_balance: U128(100),
+      }),
+      None,
+      None,
+    );
+    // Then
+    let link3 = main.get("alice.testnet".to_string()).unwrap();
+    assert!(link3.links().iter().all(|item| item.is_gated() && item.uri().is_empty()));
+    let state = link3.try_to_vec().unwrap();
+    assert!(!state
+      .windows(SECRET_URI.len())
+      .any(|window| window == SECRET_URI.as_bytes()));
+  }
+
+  #[test]
+  fn buyer_decrypts_uri_with_delivered_key() {
+    // Given
+    let content_key = generate_content_key();
+    let mut main = generate_hub(&content_key);
+    let secret = register_and_buy(&mut main);
+    // When
+    run_key_service(&mut main, &content_key);
+    // Then
+    let reader = Some("robert.testnet".to_string());
+    let link = &main.get_links("alice.testnet".to_string(), None, reader)[0];
+    assert!(link.is_sealed);
+    assert_eq!(link.uri, None);
+    let content_key = unwrap_key(&link.key_envelope.as_ref().unwrap().0, &secret).unwrap();
+    let uri = decrypt_uri(&link.sealed_uri.as_ref().unwrap().0, &content_key).unwrap();
+    assert_eq!(uri, SECRET_URI);
+  }
+
+  #[test]
+  fn get_pending_keys_lists_readers_without_envelope() {
+    // Given
+    let content_key = generate_content_key();
+    let mut main = generate_hub(&content_key);
+    register_and_buy(&mut main);
+    // When
+    let pending = main.get_pending_keys("alice.testnet".to_string(), None);
+    // Then
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].account_id, "robert.testnet".to_string());
+    run_key_service(&mut main, &content_key);
+    assert!(main
+      .get_pending_keys("alice.testnet".to_string(), None)
+      .is_empty());
+  }
+
+  #[test]
+  fn resealing_drops_envelopes() {
+    // Given
+    let content_key = generate_content_key();
+    let mut main = generate_hub(&content_key);
+    register_and_buy(&mut main);
+    run_key_service(&mut main, &content_key);
+    // When
+    testing_env!(user_context("alice.testnet", None));
+    main.seal_link(
+      1,
+      Base64VecU8(encrypt_uri(SECRET_URI, &generate_content_key())),
+      None,
+      None,
+    );
+    // Then
+    assert_eq!(main.get_pending_keys("alice.testnet".to_string(), None).len(), 1);
+  }
+
+  #[test]
+  fn sealed_link_is_created_with_envelopes() {
+    // Given
+    let content_key = generate_content_key();
+    let mut main = generate_hub(&content_key);
+    let secret = generate_secret();
+    main.register_encryption_key(Base64VecU8(PublicKey::from(&secret).as_bytes().to_vec()));
+    let envelope = wrap_key(&content_key, &PublicKey::from(&secret));
+    let mut key_envelopes = HashMap::new();
+    key_envelopes.insert("alice.testnet".to_string(), Base64VecU8(envelope));
+    // When
+    main.add_sealed_link(
+      Base64VecU8(encrypt_uri(SECRET_URI, &content_key)),
+      key_envelopes,
+      "title".to_string(),
+      "description".to_string(),
+      None,
+      None,
+      None,
+    );
+    // Then
+    let reader = Some("alice.testnet".to_string());
+    let link = &main.get_links("alice.testnet".to_string(), None, reader)[1];
+    assert!(link.is_sealed);
+    let content_key = unwrap_key(&link.key_envelope.as_ref().unwrap().0, &secret).unwrap();
+    let uri = decrypt_uri(&link.sealed_uri.as_ref().unwrap().0, &content_key).unwrap();
+    assert_eq!(uri, SECRET_URI);
+  }
+
+  #[test]
+  fn updating_sealed_link_with_empty_uri_keeps_it_sealed() {
+    // Given
+    let content_key = generate_content_key();
+    let mut main = generate_hub(&content_key);
+    register_and_buy(&mut main);
+    run_key_service(&mut main, &content_key);
+    // When
+    update_uri(&mut main, "");
+    // Then
+    let reader = Some("robert.testnet".to_string());
+    let link = &main.get_links("alice.testnet".to_string(), None, reader)[0];
+    assert!(link.is_sealed);
+    assert!(link.key_envelope.is_some());
+    assert_eq!(link.title, "new title".to_string());
+  }
+
+  #[test]
+  fn unsealing_publishes_uri_and_drops_envelopes() {
+    // Given
+    let content_key = generate_content_key();
+    let mut main = generate_hub(&content_key);
+    register_and_buy(&mut main);
+    run_key_service(&mut main, &content_key);
+    testing_env!(user_context("alice.testnet", None));
+    main.set_link_prices(1, vec![], None, None);
+    // When
+    main.unseal_link(1, SECRET_URI.to_string(), None, None);
+    // Then
+    let reader = Some("robert.testnet".to_string());
+    let link = &main.get_links("alice.testnet".to_string(), None, reader)[0];
+    assert!(!link.is_sealed);
+    assert_eq!(link.uri, Some(SECRET_URI.to_string()));
+    assert_eq!(link.key_envelope, None);
+  }
+
+  #[test]
+  #[should_panic(expected = "Unseal the link to give it a plaintext uri")]
+  fn update_sealed_link_with_plaintext_uri_panics() {
+    // Given
+    let mut main = generate_hub(&generate_content_key());
+    // When
+    update_uri(&mut main, SECRET_URI);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the owner or an admin can unseal a link")]
+  fn unseal_link_not_admin_panics() {
+    // Given
+    let mut main = generate_hub(&generate_content_key());
+    testing_env!(user_context("eve.testnet", None));
+    // When
+    main.unseal_link(1, SECRET_URI.to_string(), None, Some("alice.testnet".to_string()));
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Account has no access to this link")]
+  fn deliver_key_to_account_without_access_panics() {
+    // Given
+    let mut main = generate_hub(&generate_content_key());
+    testing_env!(user_context("eve.testnet", None));
+    main.register_encryption_key(Base64VecU8(vec![1; ENCRYPTION_KEY_LENGTH]));
+    // When
+    testing_env!(user_context("alice.testnet", None));
+    main.deliver_key(1, "eve.testnet".to_string(), Base64VecU8(vec![1]), None, None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Ungate the link before unsealing it")]
+  fn unseal_gated_link_panics() {
+    // Given
+    let mut main = generate_hub(&generate_content_key());
+    // When
+    main.unseal_link(1, SECRET_URI.to_string(), None, None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Seal the link before gating it")]
+  fn price_unsealed_link_panics() {
+    // Given
+    let mut main = generate_hub(&generate_content_key());
+    main.add_link(
+      SECRET_URI.to_string(),
+      "title".to_string(),
+      "description".to_string(),
+      None,
+      None,
+      None,
+    );
+    // When
+    main.set_link_prices(
+      2,
+      vec![Price {
+        token_account_id: None,
+        amount: U128(10),
+      }],
+      None,
+      None,
+    );
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Encryption key must be 32 bytes long")]
+  fn register_short_encryption_key_panics() {
+    // Given
+    let mut main = generate_hub(&generate_content_key());
+    // When
+    main.register_encryption_key(Base64VecU8(vec![1; 16]));
+    // Then
+    // - Should panic
+  }
+}
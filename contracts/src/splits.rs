@@ -0,0 +1,313 @@
+use near_sdk::{env, near_bindgen, AccountId};
+// Crates
+use crate::item::Split;
+use crate::link3::Link3;
+use crate::profiles::normalize_profile;
+use crate::*;
+
+pub const SPLITS_MAX_BENEFICIARIES: usize = 10;
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  pub fn get_link_splits(
+    &self,
+    owner_account_id: AccountId,
+    link_id: u64,
+    profile: Option<String>,
+  ) -> Vec<Split> {
+    self
+      .internal_expect_profile(&owner_account_id, &normalize_profile(profile))
+      .get_link(link_id)
+      .splits()
+      .clone()
+  }
+
+  /****************
+   * CALL METHODS *
+   ****************/
+  // Shares the link's sales between beneficiaries, an empty table pays the owner only
+  pub fn set_link_splits(
+    &mut self,
+    id: u64,
+    splits: Vec<Split>,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
+  ) -> Link3 {
+    assert_valid_splits(&splits);
+
+    let account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
+
+    // Update item
+    link3.update_splits(id, splits);
+
+    // Save to hub state
+    self.internal_save_profile(&account_id, &profile, &link3);
+    link3
+  }
+}
+
+impl MainHub {
+  /************
+   * INTERNAL *
+   ************/
+  // Credits the proceeds of a sale to the beneficiaries, `token_account_id` is None for NEAR
+  pub(crate) fn internal_credit_proceeds(
+    &mut self,
+    owner_account_id: &AccountId,
+    splits: &[Split],
+    token_account_id: &Option<AccountId>,
+    amount: u128,
+  ) {
+    for (account_id, share) in split_proceeds(owner_account_id, splits, amount) {
+      self.internal_credit(&account_id, token_account_id, share);
+    }
+  }
+}
+
+// Amount owed to each beneficiary, the rounding remainder goes to the owner
+pub fn split_proceeds(
+  owner_account_id: &AccountId,
+  splits: &[Split],
+  amount: u128,
+) -> Vec<(AccountId, u128)> {
+  let mut proceeds: Vec<(AccountId, u128)> = splits
+    .iter()
+    .map(|split| (split.account_id.clone(), amount * split.bps as u128 / BASIS_POINTS))
+    .collect();
+  let remainder = amount - proceeds.iter().map(|(_, share)| share).sum::<u128>();
+
+  match proceeds
+    .iter_mut()
+    .find(|(account_id, _)| account_id == owner_account_id)
+  {
+    Some((_, share)) => *share += remainder,
+    None => proceeds.push((owner_account_id.clone(), remainder)),
+  }
+
+  proceeds.retain(|(_, share)| *share > 0);
+  proceeds
+}
+
+fn assert_valid_splits(splits: &[Split]) {
+  if splits.is_empty() {
+    return;
+  }
+
+  if splits.len() > SPLITS_MAX_BENEFICIARIES {
+    panic!(
+      "Can have at most {} beneficiaries",
+      SPLITS_MAX_BENEFICIARIES
+    );
+  }
+
+  for (index, split) in splits.iter().enumerate() {
+    if !env::is_valid_account_id(split.account_id.as_bytes()) {
+      env::panic(b"Beneficiary account id is not valid");
+    }
+    if split.bps == 0 {
+      env::panic(b"Share must be greater than 0");
+    }
+    if splits[..index]
+      .iter()
+      .any(|other| other.account_id == split.account_id)
+    {
+      env::panic(b"Beneficiary appears more than once");
+    }
+  }
+
+  let total: u128 = splits.iter().map(|split| split.bps as u128).sum();
+  if total != BASIS_POINTS {
+    panic!("Shares must add up to {} basis points", BASIS_POINTS);
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::item::Price;
+  use crate::test_utils::*;
+  use near_sdk::json_types::U128;
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  const USDC: &str = "usdc.testnet";
+
+  fn split(account_id: &str, bps: u16) -> Split {
+    Split {
+      account_id: account_id.to_string(),
+      bps,
+    }
+  }
+
+  // Alice sells her link for 100 yocto or 100 USDC, shared by thirds with Robert and Jane
+  fn generate_split_hub() -> MainHub {
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    let mut main = MainHub::default();
+    main.add_accepted_token(USDC.to_string());
+    testing_env!(user_context("alice.testnet", None));
+    main.create("Hello".to_string(), "World".to_string(), None, Some(true));
+    add_sealed_link(&mut main);
+    main.set_link_prices(
+      1,
+      vec![
+        Price {
+          token_account_id: None,
+          amount: U128(100),
+        },
+        Price {
+          token_account_id: Some(USDC.to_string()),
+          amount: U128(100),
+        },
+      ],
+      None,
+      None,
+    );
+    main.set_link_splits(
+      1,
+      vec![
+        split("alice.testnet", 3333),
+        split("robert.testnet", 3333),
+        split("jane.testnet", 3334),
+      ],
+      None,
+      None,
+    );
+    main
+  }
+
+  fn near_balance(main: &MainHub, account_id: &str) -> U128 {
+    main.get_balance(account_id.to_string()).near
+  }
+
+  #[test]
+  fn split_proceeds_gives_rounding_remainder_to_owner() {
+    // Given
+    let owner = "alice.testnet".to_string();
+    let splits = vec![split("robert.testnet", 5000), split("jane.testnet", 5000)];
+    // When
+    let proceeds = split_proceeds(&owner, &splits, 101);
+    // Then
+    assert_eq!(
+      proceeds,
+      vec![
+        ("robert.testnet".to_string(), 50),
+        ("jane.testnet".to_string(), 50),
+        ("alice.testnet".to_string(), 1),
+      ]
+    );
+  }
+
+  #[test]
+  fn split_proceeds_without_splits_pays_owner() {
+    let owner = "alice.testnet".to_string();
+    assert_eq!(split_proceeds(&owner, &[], 10), vec![(owner.clone(), 10)]);
+  }
+
+  #[test]
+  fn get_link_splits_returns_table() {
+    // Given
+    let main = generate_split_hub();
+    // When
+    let splits = main.get_link_splits("alice.testnet".to_string(), 1, None);
+    // Then
+    assert_eq!(splits.len(), 3);
+    assert_eq!(splits[2], split("jane.testnet", 3334));
+  }
+
+  #[test]
+  fn buy_link_credits_each_beneficiary() {
+    // Given
+    let mut main = generate_split_hub();
+    // When
+    testing_env!(user_context("eve.testnet", Some(100)));
+    main.buy_link("alice.testnet".to_string(), 1, None);
+    // Then
+    assert_eq!(near_balance(&main, "alice.testnet"), U128(34));
+    assert_eq!(near_balance(&main, "robert.testnet"), U128(33));
+    assert_eq!(near_balance(&main, "jane.testnet"), U128(33));
+  }
+
+  #[test]
+  fn ft_on_transfer_credits_each_beneficiary() {
+    // Given
+    let mut main = generate_split_hub();
+    // When
+    testing_env!(user_context(USDC, None));
+    main.ft_on_transfer(
+      "eve.testnet".to_string(),
+      U128(100),
+      r#"{"owner_account_id":"alice.testnet","link_id":1}"#.to_string(),
+    );
+    // Then
+    let usdc = |account_id: &str| main.get_balance(account_id.to_string()).tokens[USDC];
+    assert_eq!(usdc("alice.testnet"), U128(34));
+    assert_eq!(usdc("robert.testnet"), U128(33));
+    assert_eq!(usdc("jane.testnet"), U128(33));
+  }
+
+  #[test]
+  fn buy_link_with_missing_beneficiary_still_succeeds() {
+    // Given
+    let mut main = generate_split_hub();
+    main.set_link_splits(
+      1,
+      vec![split("alice.testnet", 5000), split("missing.testnet", 5000)],
+      None,
+      None,
+    );
+    // When
+    testing_env!(user_context("eve.testnet", Some(100)));
+    main.buy_link("alice.testnet".to_string(), 1, None);
+    // Then
+    // - Nothing is sent, the share waits until the account exists and withdraws it
+    assert_eq!(created_receipts(), "[]");
+    assert_eq!(near_balance(&main, "alice.testnet"), U128(50));
+    assert_eq!(near_balance(&main, "missing.testnet"), U128(50));
+  }
+
+  #[test]
+  #[should_panic(expected = "Shares must add up to 10000 basis points")]
+  fn set_link_splits_not_adding_up_panics() {
+    // Given
+    let mut main = generate_split_hub();
+    // When
+    main.set_link_splits(1, vec![split("robert.testnet", 5000)], None, None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Beneficiary appears more than once")]
+  fn set_link_splits_with_duplicate_panics() {
+    // Given
+    let mut main = generate_split_hub();
+    // When
+    main.set_link_splits(
+      1,
+      vec![split("robert.testnet", 5000), split("robert.testnet", 5000)],
+      None,
+      None,
+    );
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the owner or an admin can split a link")]
+  fn set_link_splits_as_stranger_panics() {
+    // Given
+    let mut main = generate_split_hub();
+    // When
+    testing_env!(user_context("eve.testnet", None));
+    main.set_link_splits(1, vec![], None, Some("alice.testnet".to_string()));
+    // Then
+    // - Should panic
+  }
+}
@@ -0,0 +1,510 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen, AccountId, Balance};
+use serde::Serialize;
+use std::collections::HashMap;
+// Crates
+use crate::link3::Link3;
+use crate::profiles::normalize_profile;
+use crate::*;
+
+pub const BENEFICIARIES_MAX_COUNT: usize = 100;
+
+// NEAR a sponsor set aside to pay for the storage of its beneficiaries' writes
+#[derive(BorshSerialize, BorshDeserialize, Default)]
+pub struct Sponsorship {
+  balance: Balance,
+  // Beneficiary -> (cap, spent so far)
+  beneficiaries: HashMap<AccountId, (Balance, Balance)>,
+  // Lifetime spending, removed beneficiaries included
+  spent: Balance,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BeneficiaryUsage {
+  pub account_id: AccountId,
+  pub cap: U128,
+  pub spent: U128,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SponsorshipView {
+  pub balance: U128,
+  pub spent: U128,
+  pub beneficiaries: Vec<BeneficiaryUsage>,
+}
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  pub fn get_sponsorship(&self, sponsor_account_id: AccountId) -> SponsorshipView {
+    let sponsorship = self.sponsorships.get(&sponsor_account_id).unwrap_or_default();
+    let mut beneficiaries: Vec<BeneficiaryUsage> = sponsorship
+      .beneficiaries
+      .into_iter()
+      .map(|(account_id, (cap, spent))| BeneficiaryUsage {
+        account_id,
+        cap: U128(cap),
+        spent: U128(spent),
+      })
+      .collect();
+    beneficiaries.sort_by(|a, b| a.account_id.cmp(&b.account_id));
+
+    SponsorshipView {
+      balance: U128(sponsorship.balance),
+      spent: U128(sponsorship.spent),
+      beneficiaries,
+    }
+  }
+
+  pub fn get_sponsor(&self, account_id: AccountId) -> Option<AccountId> {
+    self.sponsors.get(&account_id)
+  }
+
+  pub fn get_relayers(&self) -> Vec<AccountId> {
+    self.relayers.to_vec()
+  }
+
+  /****************
+   * CALL METHODS *
+   ****************/
+  #[payable]
+  pub fn deposit_sponsorship(&mut self) -> U128 {
+    let sponsor_account_id = env::predecessor_account_id();
+    let deposit = env::attached_deposit();
+    if deposit == 0 {
+      env::panic(b"Deposit must be greater than 0");
+    }
+
+    let mut sponsorship = self.sponsorships.get(&sponsor_account_id).unwrap_or_default();
+    sponsorship.balance += deposit;
+    self.sponsorships.insert(&sponsor_account_id, &sponsorship);
+    U128(sponsorship.balance)
+  }
+
+  // Sets how much of the caller's sponsorship the account can spend, raising or
+  // lowering the cap keeps what it already spent. Only funded sponsors can claim
+  // accounts, so nobody can squat them for free
+  pub fn add_beneficiary(&mut self, account_id: AccountId, cap: U128) {
+    let sponsor_account_id = env::predecessor_account_id();
+    if !env::is_valid_account_id(account_id.as_bytes()) {
+      env::panic(b"Beneficiary account id is not valid");
+    }
+    match self.sponsors.get(&account_id) {
+      Some(sponsor) if sponsor != sponsor_account_id => {
+        env::panic(b"Account is already sponsored")
+      }
+      _ => {}
+    }
+
+    let mut sponsorship = self.sponsorships.get(&sponsor_account_id).unwrap_or_default();
+    if sponsorship.balance == 0 {
+      env::panic(b"Deposit a sponsorship before adding beneficiaries");
+    }
+    if !sponsorship.beneficiaries.contains_key(&account_id)
+      && sponsorship.beneficiaries.len() >= BENEFICIARIES_MAX_COUNT
+    {
+      panic!("Can sponsor at most {} accounts", BENEFICIARIES_MAX_COUNT);
+    }
+    let spent = sponsorship
+      .beneficiaries
+      .get(&account_id)
+      .map_or(0, |(_, spent)| *spent);
+    sponsorship.beneficiaries.insert(account_id.clone(), (cap.0, spent));
+    self.sponsorships.insert(&sponsor_account_id, &sponsorship);
+    self.sponsors.insert(&account_id, &sponsor_account_id);
+  }
+
+  pub fn remove_beneficiary(&mut self, account_id: AccountId) {
+    let sponsor_account_id = env::predecessor_account_id();
+    let mut sponsorship = self.sponsorships.get(&sponsor_account_id).unwrap_or_default();
+    if sponsorship.beneficiaries.remove(&account_id).is_none() {
+      env::panic(b"Account is not a beneficiary");
+    }
+
+    self.sponsorships.insert(&sponsor_account_id, &sponsorship);
+    self.sponsors.remove(&account_id);
+  }
+
+  // Moves unspent sponsorship to the sponsor's balance, withdrawable with `withdraw`
+  pub fn withdraw_sponsorship(&mut self, amount: Option<U128>) -> U128 {
+    let sponsor_account_id = env::predecessor_account_id();
+    let mut sponsorship = self.sponsorships.get(&sponsor_account_id).unwrap_or_default();
+    let amount = amount.map_or(sponsorship.balance, |amount| amount.0);
+    if amount == 0 {
+      env::panic(b"Nothing to withdraw");
+    }
+    if amount > sponsorship.balance {
+      env::panic(b"Not enough balance to withdraw");
+    }
+
+    sponsorship.balance -= amount;
+    self.sponsorships.insert(&sponsor_account_id, &sponsorship);
+    self.internal_credit(&sponsor_account_id, &None, amount);
+    U128(sponsorship.balance)
+  }
+
+  pub fn add_relayer(&mut self, account_id: AccountId) {
+    self.assert_admin();
+    if !env::is_valid_account_id(account_id.as_bytes()) {
+      env::panic(b"Relayer account id is not valid");
+    }
+
+    self.relayers.insert(&account_id);
+  }
+
+  pub fn remove_relayer(&mut self, account_id: AccountId) {
+    self.assert_admin();
+
+    self.relayers.remove(&account_id);
+  }
+
+  /*********************
+   * SPONSORED METHODS *
+   *********************/
+  // Relayers authenticate `account_id` themselves and pay the gas, the account's sponsor
+  // pays for the storage the write adds
+  pub fn sponsored_create(
+    &mut self,
+    account_id: AccountId,
+    title: String,
+    description: String,
+    image_uri: Option<String>,
+    is_published: Option<bool>,
+  ) -> Link3 {
+    self.internal_sponsored_write(&account_id, |main| {
+      if main.get(account_id.clone()).is_some() {
+        env::panic(b"Can't create, account has Link3 already")
+      }
+
+      let mut link3 = Link3::new(title, description, image_uri, is_published);
+      link3.transfer_ownership(account_id.clone());
+      main.internal_save_profile(&account_id, &None, &link3);
+      link3
+    })
+  }
+
+  pub fn sponsored_add_link(
+    &mut self,
+    account_id: AccountId,
+    uri: String,
+    title: String,
+    description: String,
+    image_uri: Option<String>,
+    profile: Option<String>,
+  ) -> Link3 {
+    self.internal_sponsored_write(&account_id, |main| {
+      let profile = normalize_profile(profile);
+      let mut link3 = main.internal_expect_profile(&account_id, &profile);
+
+      if link3.link_count() >= LINK_LIMIT as usize {
+        panic!("You can only have {} links", LINK_LIMIT);
+      }
+      link3.create_link_as(&account_id, uri, title, description, image_uri);
+
+      main.internal_save_profile(&account_id, &profile, &link3);
+      link3
+    })
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  pub fn sponsored_update_link(
+    &mut self,
+    account_id: AccountId,
+    id: u64,
+    uri: String,
+    title: String,
+    description: String,
+    image_uri: Option<String>,
+    profile: Option<String>,
+  ) -> Link3 {
+    self.internal_sponsored_write(&account_id, |main| {
+      let profile = normalize_profile(profile);
+      let mut link3 = main.internal_expect_profile(&account_id, &profile);
+
+      link3.update_link_as(&account_id, id, uri, title, description, image_uri);
+
+      main.internal_save_profile(&account_id, &profile, &link3);
+      link3
+    })
+  }
+}
+
+impl MainHub {
+  /************
+   * INTERNAL *
+   ************/
+  // Runs `write` for a relayer and charges the storage it adds to the account's sponsor,
+  // freed storage isn't refunded
+  fn internal_sponsored_write<T>(
+    &mut self,
+    account_id: &AccountId,
+    write: impl FnOnce(&mut Self) -> T,
+  ) -> T {
+    if !self.relayers.contains(&env::predecessor_account_id()) {
+      env::panic(b"Only a relayer can make sponsored writes");
+    }
+    let sponsor_account_id = self
+      .sponsors
+      .get(account_id)
+      .unwrap_or_else(|| env::panic(b"Account is not sponsored"));
+
+    let storage_usage = env::storage_usage();
+    let result = write(self);
+    let cost = env::storage_usage().saturating_sub(storage_usage) as Balance
+      * env::storage_byte_cost();
+
+    let mut sponsorship = self.sponsorships.get(&sponsor_account_id).unwrap_or_default();
+    let (cap, spent) = sponsorship
+      .beneficiaries
+      .get_mut(account_id)
+      .unwrap_or_else(|| env::panic(b"Account is not sponsored"));
+    if *spent + cost > *cap {
+      env::panic(b"Write goes over the account's sponsorship cap");
+    }
+    if cost > sponsorship.balance {
+      env::panic(b"Sponsor can't cover this write");
+    }
+    *spent += cost;
+    sponsorship.balance -= cost;
+    sponsorship.spent += cost;
+    self.sponsorships.insert(&sponsor_account_id, &sponsorship);
+
+    result
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::attestations::AttestationKind;
+  use crate::test_utils::*;
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  const NEAR: Balance = 1_000_000_000_000_000_000_000_000;
+  const RELAYER: &str = "relayer.testnet";
+
+  // Acme sponsors Newbie up to 0.1 NEAR out of 1 NEAR, writes go through the relayer
+  fn generate_hub() -> MainHub {
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    let mut main = MainHub::default();
+    main.add_relayer(RELAYER.to_string());
+    testing_env!(user_context("acme.testnet", Some(NEAR)));
+    main.deposit_sponsorship();
+    main.add_beneficiary("newbie.testnet".to_string(), U128(NEAR / 10));
+    main
+  }
+
+  // The relayer contract is both the predecessor and the signer of the call
+  fn relay_create(main: &mut MainHub, account_id: &str) -> Link3 {
+    testing_env!(user_context(RELAYER, None));
+    main.sponsored_create(
+      account_id.to_string(),
+      "Hello".to_string(),
+      "World".to_string(),
+      None,
+      Some(true),
+    )
+  }
+
+  fn relay_add_link(main: &mut MainHub, account_id: &str) -> Link3 {
+    testing_env!(user_context(RELAYER, None));
+    main.sponsored_add_link(
+      account_id.to_string(),
+      "uri".to_string(),
+      "title".to_string(),
+      "description".to_string(),
+      None,
+      None,
+    )
+  }
+
+  #[test]
+  fn relayer_creates_profile_owned_by_beneficiary() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    relay_create(&mut main, "newbie.testnet");
+    relay_add_link(&mut main, "newbie.testnet");
+    // Then
+    let link3 = main.get("newbie.testnet".to_string()).unwrap();
+    assert_eq!(link3.owner_account_id(), &"newbie.testnet".to_string());
+    assert_eq!(link3.link_count(), 1);
+  }
+
+  #[test]
+  fn sponsored_writes_are_charged_to_sponsor() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    relay_create(&mut main, "newbie.testnet");
+    relay_add_link(&mut main, "newbie.testnet");
+    // Then
+    let sponsorship = main.get_sponsorship("acme.testnet".to_string());
+    assert!(sponsorship.spent.0 > 0);
+    assert_eq!(sponsorship.balance.0 + sponsorship.spent.0, NEAR);
+    assert_eq!(sponsorship.beneficiaries.len(), 1);
+    assert_eq!(sponsorship.beneficiaries[0].spent, sponsorship.spent);
+  }
+
+  #[test]
+  fn sponsored_update_link_edits_as_beneficiary() {
+    // Given
+    let mut main = generate_hub();
+    relay_create(&mut main, "newbie.testnet");
+    relay_add_link(&mut main, "newbie.testnet");
+    // When
+    main.sponsored_update_link(
+      "newbie.testnet".to_string(),
+      1,
+      "uri".to_string(),
+      "New title".to_string(),
+      "description".to_string(),
+      None,
+      None,
+    );
+    // Then
+    let link3 = main.get("newbie.testnet".to_string()).unwrap();
+    assert_eq!(link3.list(None, &[], &[])[0].title, "New title".to_string());
+  }
+
+  #[test]
+  fn sponsored_links_follow_attestations() {
+    // Given
+    let mut main = generate_hub();
+    relay_create(&mut main, "newbie.testnet");
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    main.add_verifier("verifier.testnet".to_string());
+    testing_env!(user_context("verifier.testnet", None));
+    main.attest(
+      "newbie.testnet".to_string(),
+      None,
+      AttestationKind::Github,
+      "newbie".to_string(),
+      None,
+      None,
+    );
+    let is_verified = |main: &MainHub| {
+      main.get_links("newbie.testnet".to_string(), None, None)[0].is_verified
+    };
+    // When
+    testing_env!(user_context(RELAYER, None));
+    main.sponsored_add_link(
+      "newbie.testnet".to_string(),
+      "https://github.com/newbie".to_string(),
+      "GitHub".to_string(),
+      "My code".to_string(),
+      None,
+      None,
+    );
+    // Then
+    assert!(is_verified(&main));
+    main.sponsored_update_link(
+      "newbie.testnet".to_string(),
+      1,
+      "https://github.com/eve".to_string(),
+      "GitHub".to_string(),
+      "My code".to_string(),
+      None,
+      None,
+    );
+    assert!(!is_verified(&main));
+  }
+
+  #[test]
+  fn withdraw_sponsorship_credits_unspent_balance() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.withdraw_sponsorship(Some(U128(NEAR / 2)));
+    // Then
+    assert_eq!(main.get_sponsorship("acme.testnet".to_string()).balance, U128(NEAR / 2));
+    assert_eq!(main.get_balance("acme.testnet".to_string()).near, U128(NEAR / 2));
+  }
+
+  #[test]
+  #[should_panic(expected = "Write goes over the account's sponsorship cap")]
+  fn write_over_cap_panics() {
+    // Given
+    let mut main = generate_hub();
+    main.add_beneficiary("newbie.testnet".to_string(), U128(1));
+    // When
+    relay_create(&mut main, "newbie.testnet");
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Account is not sponsored")]
+  fn write_for_unsponsored_account_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    relay_create(&mut main, "eve.testnet");
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Only a relayer can make sponsored writes")]
+  fn sponsored_write_not_from_relayer_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    testing_env!(user_context("eve.testnet", None));
+    main.sponsored_create(
+      "newbie.testnet".to_string(),
+      "Hello".to_string(),
+      "World".to_string(),
+      None,
+      None,
+    );
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Account is already sponsored")]
+  fn add_beneficiary_sponsored_by_other_panics() {
+    // Given
+    let mut main = generate_hub();
+    testing_env!(user_context("globex.testnet", Some(NEAR)));
+    main.deposit_sponsorship();
+    // When
+    main.add_beneficiary("newbie.testnet".to_string(), U128(NEAR));
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Deposit a sponsorship before adding beneficiaries")]
+  fn add_beneficiary_without_sponsorship_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    testing_env!(user_context("eve.testnet", None));
+    main.add_beneficiary("victim.testnet".to_string(), U128(0));
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Can sponsor at most 100 accounts")]
+  fn add_too_many_beneficiaries_panics() {
+    // Given
+    let mut main = generate_hub();
+    for i in 1..BENEFICIARIES_MAX_COUNT {
+      main.add_beneficiary(format!("newbie{}.testnet", i), U128(1));
+    }
+    main.add_beneficiary("newbie.testnet".to_string(), U128(NEAR));
+    // When
+    main.add_beneficiary("one-more.testnet".to_string(), U128(1));
+    // Then
+    // - Should panic
+  }
+}
@@ -0,0 +1,416 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{env, near_bindgen, AccountId};
+use serde::{Deserialize, Serialize};
+// Crates
+use crate::directory::ProfileSummary;
+use crate::item::ItemInfo;
+use crate::link3::Link3;
+use crate::profiles::{normalize_profile, ProfileId, DEFAULT_PROFILE};
+use crate::*;
+
+pub const TAGS_MAX_COUNT: usize = 5;
+const TAG_MIN_LENGTH: usize = 2;
+const TAG_MAX_LENGTH: usize = 30;
+// Prefixes of the per-tag sets, followed by the tag's hash
+const PROFILE_TAG_SET_PREFIX: &[u8] = b"k";
+const LINK_TAG_SET_PREFIX: &[u8] = b"l";
+
+// Any link of the hub
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct LinkId {
+  pub account_id: AccountId,
+  pub profile: Option<String>,
+  pub link_id: u64,
+}
+
+// A link found by tag, gated links are shown without their uri
+#[derive(Serialize)]
+pub struct LinkSearchResult {
+  pub account_id: AccountId,
+  pub profile: String,
+  pub link: ItemInfo,
+}
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  pub fn search_profiles_by_tag(
+    &self,
+    tag: String,
+    from_index: u64,
+    limit: u64,
+  ) -> Vec<ProfileSummary> {
+    match self.profile_tags.get(&normalize_tag(&tag)) {
+      Some(profile_ids) => profile_ids
+        .as_vector()
+        .iter()
+        .skip(from_index as usize)
+        .take(limit as usize)
+        .filter_map(|profile_id| self.internal_profile_summary(&profile_id))
+        .collect(),
+      None => vec![],
+    }
+  }
+
+  pub fn search_links_by_tag(
+    &self,
+    tag: String,
+    from_index: u64,
+    limit: u64,
+  ) -> Vec<LinkSearchResult> {
+    match self.link_tags.get(&normalize_tag(&tag)) {
+      Some(link_ids) => link_ids
+        .as_vector()
+        .iter()
+        .skip(from_index as usize)
+        .take(limit as usize)
+        .filter_map(|link_id| self.internal_link_search_result(&link_id))
+        .collect(),
+      None => vec![],
+    }
+  }
+
+  /****************
+   * CALL METHODS *
+   ****************/
+  // Replaces the tags of the profile, or of one of its links when `link_id` is given
+  pub fn set_tags(
+    &mut self,
+    tags: Vec<String>,
+    link_id: Option<u64>,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
+  ) -> Link3 {
+    let account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
+
+    match link_id {
+      Some(id) => link3.set_link_tags(id, tags),
+      None => link3.set_tags(tags),
+    }
+
+    // Save to hub state
+    self.internal_save_profile(&account_id, &profile, &link3);
+    link3
+  }
+}
+
+impl MainHub {
+  /************
+   * INTERNAL *
+   ************/
+  // Moves the index from what `previous` exposed to what `current` exposes,
+  // a profile and its links are only indexed while it is published
+  pub(crate) fn internal_sync_tags(
+    &mut self,
+    profile_id: &ProfileId,
+    previous: Option<&Link3>,
+    current: Option<&Link3>,
+  ) {
+    let (previous_tags, previous_link_tags) = indexed_tags(previous);
+    let (current_tags, current_link_tags) = indexed_tags(current);
+
+    for tag in previous_tags.iter().filter(|tag| !current_tags.contains(tag)) {
+      nested_set_remove(&mut self.profile_tags, tag, profile_id);
+    }
+    for tag in current_tags.iter().filter(|tag| !previous_tags.contains(tag)) {
+      nested_set_insert(&mut self.profile_tags, PROFILE_TAG_SET_PREFIX, tag, profile_id);
+    }
+
+    for entry in previous_link_tags
+      .iter()
+      .filter(|entry| !current_link_tags.contains(entry))
+    {
+      let (link_id, tag) = entry;
+      nested_set_remove(&mut self.link_tags, tag, &link_id_of(profile_id, *link_id));
+    }
+    for entry in current_link_tags
+      .iter()
+      .filter(|entry| !previous_link_tags.contains(entry))
+    {
+      let (link_id, tag) = entry;
+      let link_id = link_id_of(profile_id, *link_id);
+      nested_set_insert(&mut self.link_tags, LINK_TAG_SET_PREFIX, tag, &link_id);
+    }
+  }
+
+  fn internal_link_search_result(&self, link_id: &LinkId) -> Option<LinkSearchResult> {
+    let link3 = self.internal_get_profile(&link_id.account_id, &link_id.profile)?;
+    let item = link3
+      .links()
+      .iter()
+      .find(|item| item.id() == link_id.link_id)?;
+    let attestations = self.internal_active_attestations(&ProfileId {
+      account_id: link_id.account_id.clone(),
+      profile: link_id.profile.clone(),
+    });
+
+    Some(LinkSearchResult {
+      account_id: link_id.account_id.clone(),
+      profile: link_id
+        .profile
+        .clone()
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string()),
+      link: ItemInfo::map(item, !item.is_gated(), &attestations),
+    })
+  }
+}
+
+// Lowercases the tags, drops the leading # and duplicates, panics when there are too many
+pub fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+  let mut normalized: Vec<String> = vec![];
+  for tag in tags {
+    let tag = normalize_tag(&tag);
+    if !normalized.contains(&tag) {
+      normalized.push(tag);
+    }
+  }
+
+  if normalized.len() > TAGS_MAX_COUNT {
+    panic!("Can have at most {} tags", TAGS_MAX_COUNT);
+  }
+
+  normalized
+}
+
+pub fn normalize_tag(tag: &str) -> String {
+  let tag = tag.trim();
+  let tag = tag.strip_prefix('#').unwrap_or(tag).to_lowercase();
+
+  if tag.len() < TAG_MIN_LENGTH {
+    panic!("Tag must be at least {} characters long", TAG_MIN_LENGTH);
+  }
+
+  if tag.len() > TAG_MAX_LENGTH {
+    panic!("Tag must be at most {} characters long", TAG_MAX_LENGTH);
+  }
+
+  if !tag
+    .chars()
+    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+  {
+    panic!("Tag can only contain letters, numbers, _ and -");
+  }
+
+  tag
+}
+
+// Profile tags and (link id, tag) pairs the index should hold for this Link3
+fn indexed_tags(link3: Option<&Link3>) -> (Vec<String>, Vec<(u64, String)>) {
+  match link3 {
+    Some(link3) if link3.is_published() => (
+      link3.tags().clone(),
+      link3
+        .links()
+        .iter()
+        .flat_map(|item| item.tags().iter().map(move |tag| (item.id(), tag.clone())))
+        .collect(),
+    ),
+    _ => (vec![], vec![]),
+  }
+}
+
+fn link_id_of(profile_id: &ProfileId, link_id: u64) -> LinkId {
+  LinkId {
+    account_id: profile_id.account_id.clone(),
+    profile: profile_id.profile.clone(),
+    link_id,
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::item::Price;
+  use crate::test_utils::*;
+  use near_sdk::json_types::{Base64VecU8, U128};
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  fn tags(tags: &[&str]) -> Vec<String> {
+    tags.iter().map(|tag| tag.to_string()).collect()
+  }
+
+  fn add_link(main: &mut MainHub, title: &str) {
+    main.add_link(
+      "uri".to_string(),
+      title.to_string(),
+      "description".to_string(),
+      None,
+      None,
+      None,
+    );
+  }
+
+  // Alice and Robert both have a published profile with one link
+  fn generate_hub() -> MainHub {
+    testing_env!(user_context("alice.testnet", None));
+    let mut main = MainHub::default();
+    main.create("Alice".to_string(), "World".to_string(), None, Some(true));
+    add_link(&mut main, "Alice's link");
+    testing_env!(user_context("robert.testnet", None));
+    main.create("Robert".to_string(), "World".to_string(), None, Some(true));
+    add_link(&mut main, "Robert's link");
+    main
+  }
+
+  fn profile_titles(summaries: Vec<ProfileSummary>) -> Vec<String> {
+    summaries.into_iter().map(|summary| summary.title).collect()
+  }
+
+  fn link_titles(results: Vec<LinkSearchResult>) -> Vec<String> {
+    results.into_iter().map(|result| result.link.title).collect()
+  }
+
+  #[test]
+  fn normalize_tags_lowercases_and_dedupes() {
+    assert_eq!(
+      normalize_tags(tags(&["#Music", "music", " Art "])),
+      tags(&["music", "art"])
+    );
+  }
+
+  #[test]
+  #[should_panic(expected = "Can have at most 5 tags")]
+  fn normalize_tags_over_limit_panics() {
+    normalize_tags(tags(&["aa", "bb", "cc", "dd", "ee", "ff"]));
+  }
+
+  #[test]
+  #[should_panic(expected = "Tag can only contain letters, numbers, _ and -")]
+  fn normalize_tag_with_invalid_chars_panics() {
+    normalize_tag("live music");
+  }
+
+  #[test]
+  fn search_profiles_by_tag_returns_tagged_profiles() {
+    // Given
+    let mut main = generate_hub();
+    main.set_tags(tags(&["music"]), None, None, None);
+    testing_env!(user_context("alice.testnet", None));
+    main.set_tags(tags(&["Music", "art"]), None, None, None);
+    // When
+    let found = main.search_profiles_by_tag("#MUSIC".to_string(), 0, 10);
+    // Then
+    assert_eq!(profile_titles(found), tags(&["Robert", "Alice"]));
+    assert_eq!(
+      profile_titles(main.search_profiles_by_tag("art".to_string(), 0, 10)),
+      tags(&["Alice"])
+    );
+    assert_eq!(
+      profile_titles(main.search_profiles_by_tag("music".to_string(), 1, 1)),
+      tags(&["Alice"])
+    );
+  }
+
+  #[test]
+  fn set_tags_replaces_indexed_tags() {
+    // Given
+    let mut main = generate_hub();
+    main.set_tags(tags(&["music"]), None, None, None);
+    // When
+    main.set_tags(tags(&["art"]), None, None, None);
+    // Then
+    assert!(main.search_profiles_by_tag("music".to_string(), 0, 10).is_empty());
+    assert_eq!(main.search_profiles_by_tag("art".to_string(), 0, 10).len(), 1);
+  }
+
+  #[test]
+  fn search_links_by_tag_returns_tagged_links() {
+    // Given
+    let mut main = generate_hub();
+    main.set_tags(tags(&["music"]), Some(1), None, None);
+    // When
+    let found = main.search_links_by_tag("music".to_string(), 0, 10);
+    // Then
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].account_id, "robert.testnet".to_string());
+    assert_eq!(found[0].profile, "default".to_string());
+    assert_eq!(found[0].link.tags, tags(&["music"]));
+    assert_eq!(found[0].link.uri, Some("uri".to_string()));
+  }
+
+  #[test]
+  fn deleting_link_removes_it_from_index() {
+    // Given
+    let mut main = generate_hub();
+    main.set_tags(tags(&["music"]), Some(1), None, None);
+    // When
+    main.delete_link(1, None, None);
+    // Then
+    assert!(main.search_links_by_tag("music".to_string(), 0, 10).is_empty());
+  }
+
+  #[test]
+  fn unpublishing_removes_profile_and_links_from_index() {
+    // Given
+    let mut main = generate_hub();
+    main.set_tags(tags(&["music"]), None, None, None);
+    main.set_tags(tags(&["music"]), Some(1), None, None);
+    // When
+    main.update_published_status(false, None);
+    // Then
+    assert!(main.search_profiles_by_tag("music".to_string(), 0, 10).is_empty());
+    assert!(main.search_links_by_tag("music".to_string(), 0, 10).is_empty());
+
+    // When
+    main.update_published_status(true, None);
+    // Then
+    assert_eq!(main.search_profiles_by_tag("music".to_string(), 0, 10).len(), 1);
+    assert_eq!(
+      link_titles(main.search_links_by_tag("music".to_string(), 0, 10)),
+      tags(&["Robert's link"])
+    );
+  }
+
+  #[test]
+  fn deleting_profile_removes_it_from_index() {
+    // Given
+    let mut main = generate_hub();
+    main.set_tags(tags(&["music"]), None, None, None);
+    main.set_tags(tags(&["music"]), Some(1), None, None);
+    // When
+    main.delete_profile(None);
+    // Then
+    assert!(main.search_profiles_by_tag("music".to_string(), 0, 10).is_empty());
+    assert!(main.search_links_by_tag("music".to_string(), 0, 10).is_empty());
+  }
+
+  #[test]
+  fn search_links_by_tag_hides_gated_uris() {
+    // Given
+    let mut main = generate_hub();
+    main.set_tags(tags(&["music"]), Some(1), None, None);
+    main.seal_link(1, Base64VecU8(b"ciphertext".to_vec()), None, None);
+    main.set_link_prices(
+      1,
+      vec![Price {
+        token_account_id: None,
+        amount: U128(10),
+      }],
+      None,
+      None,
+    );
+    // When
+    let found = main.search_links_by_tag("music".to_string(), 0, 10);
+    // Then
+    assert_eq!(found[0].link.uri, None);
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the owner or a collaborator can change link tags")]
+  fn set_link_tags_as_stranger_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    testing_env!(user_context("eve.testnet", None));
+    main.set_tags(tags(&["music"]), Some(1), None, Some("robert.testnet".to_string()));
+    // Then
+    // - Should panic
+  }
+}
@@ -0,0 +1,79 @@
+// Shared helpers for the hub's unit tests
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::{env, serde_json, Balance, PromiseResult, VMContext};
+use std::collections::HashMap;
+// Crates
+use crate::MainHub;
+
+pub const CONTRACT_ACCOUNT_ID: &str = "contract.testnet";
+// Enough for the hub to attach deposits to the promises it creates
+pub const CONTRACT_BALANCE: Balance = 100_000_000_000_000_000_000_000_000;
+
+pub fn get_context(
+  signer_account_id: &str,
+  predecessor_account_id: &str,
+  deposit: Option<Balance>,
+) -> VMContext {
+  VMContext {
+    current_account_id: CONTRACT_ACCOUNT_ID.to_string(),
+    signer_account_id: signer_account_id.to_string(),
+    signer_account_pk: vec![0, 1, 2],
+    predecessor_account_id: predecessor_account_id.to_string(),
+    input: vec![],
+    block_index: 0,
+    block_timestamp: 0,
+    account_balance: CONTRACT_BALANCE,
+    account_locked_balance: 0,
+    storage_usage: storage_usage(),
+    attached_deposit: deposit.unwrap_or(0),
+    prepaid_gas: 10u64.pow(18),
+    random_seed: vec![0, 1, 2],
+    is_view: false,
+    output_data_receivers: vec![],
+    epoch_height: 19,
+  }
+}
+
+// Context of a user calling the hub directly
+pub fn user_context(account_id: &str, deposit: Option<Balance>) -> VMContext {
+  get_context(account_id, account_id, deposit)
+}
+
+// Context of the hub running one of its own callbacks
+pub fn callback_context(signer_account_id: &str) -> VMContext {
+  get_context(signer_account_id, CONTRACT_ACCOUNT_ID, None)
+}
+
+// Keeps the storage usage between `testing_env!` calls, so rewriting state doesn't underflow it
+fn storage_usage() -> u64 {
+  match env::take_blockchain_interface() {
+    Some(interface) => {
+      env::set_blockchain_interface(interface);
+      env::storage_usage()
+    }
+    None => 0,
+  }
+}
+
+// Adds a link to the signer's default profile with a placeholder ciphertext, gated links
+// have to be sealed
+pub fn add_sealed_link(main: &mut MainHub) {
+  main.add_sealed_link(
+    Base64VecU8(b"ciphertext".to_vec()),
+    HashMap::new(),
+    "title".to_string(),
+    "description".to_string(),
+    None,
+    None,
+    None,
+  );
+}
+
+pub fn promise_success<T: serde::Serialize>(value: &T) -> PromiseResult {
+  PromiseResult::Successful(serde_json::to_vec(value).unwrap())
+}
+
+// Receipts created so far as JSON, the mocked receipts don't expose their fields
+pub fn created_receipts() -> String {
+  serde_json::to_string(&near_sdk::test_utils::get_created_receipts()).unwrap()
+}
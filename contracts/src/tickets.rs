@@ -0,0 +1,373 @@
+use near_sdk::json_types::U64;
+use near_sdk::{env, near_bindgen, AccountId};
+use serde::Serialize;
+// Crates
+use crate::item::TicketSettings;
+use crate::link3::Link3;
+use crate::profiles::normalize_profile;
+use crate::*;
+
+// Holders are kept in the profile along with the link, so each sale stays within what a
+// profile can load
+pub const TICKET_SUPPLY_MAX: u64 = 1_000;
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct TicketStatus {
+  pub supply: u64,
+  pub sold: u64,
+  pub remaining: u64,
+  pub sale_start: Option<U64>,
+  pub sale_end: Option<U64>,
+  pub max_per_buyer: u32,
+  // Whether a ticket can be bought right now
+  pub on_sale: bool,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct TicketHolder {
+  pub account_id: AccountId,
+  pub tickets: u32,
+}
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  // None when the link doesn't sell tickets
+  pub fn get_ticket_status(
+    &self,
+    owner_account_id: AccountId,
+    id: u64,
+    profile: Option<String>,
+  ) -> Option<TicketStatus> {
+    let link3 = self.internal_expect_profile(&owner_account_id, &normalize_profile(profile));
+    let item = link3.get_link(id);
+    let ticket = item.ticket()?;
+
+    let sold = item.tickets_sold();
+    let remaining = ticket.supply.saturating_sub(sold);
+    let now = env::block_timestamp();
+    let started = ticket.sale_start.is_none_or(|sale_start| now >= sale_start.0);
+    let ended = ticket.sale_end.is_some_and(|sale_end| now >= sale_end.0);
+    Some(TicketStatus {
+      supply: ticket.supply,
+      sold,
+      remaining,
+      sale_start: ticket.sale_start,
+      sale_end: ticket.sale_end,
+      max_per_buyer: ticket.max_per_buyer,
+      on_sale: started && !ended && remaining > 0,
+    })
+  }
+
+  // Buyers of the link in order of their first ticket, for check-in at the door
+  pub fn get_ticket_holders(
+    &self,
+    owner_account_id: AccountId,
+    id: u64,
+    profile: Option<String>,
+    from_index: u64,
+    limit: u64,
+  ) -> Vec<TicketHolder> {
+    let link3 = self.internal_expect_profile(&owner_account_id, &normalize_profile(profile));
+    link3
+      .get_link(id)
+      .ticket_holders()
+      .iter()
+      .skip(from_index as usize)
+      .take(limit as usize)
+      .map(|(account_id, tickets)| TicketHolder {
+        account_id: account_id.clone(),
+        tickets: *tickets,
+      })
+      .collect()
+  }
+
+  /****************
+   * CALL METHODS *
+   ****************/
+  // Sells a limited supply of the link within the sale window, None lifts the limits.
+  // Buyers keep the tickets they already bought
+  pub fn set_link_ticket(
+    &mut self,
+    id: u64,
+    ticket: Option<TicketSettings>,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
+  ) -> Link3 {
+    if let Some(ticket) = &ticket {
+      assert_valid_ticket(ticket);
+    }
+
+    let account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
+
+    // Update item
+    link3.update_ticket(id, ticket);
+
+    // Save to hub state
+    self.internal_save_profile(&account_id, &profile, &link3);
+    link3
+  }
+}
+
+fn assert_valid_ticket(ticket: &TicketSettings) {
+  if ticket.supply == 0 {
+    env::panic(b"Ticket supply must be greater than 0");
+  }
+  if ticket.supply > TICKET_SUPPLY_MAX {
+    panic!("Ticket supply can be at most {}", TICKET_SUPPLY_MAX);
+  }
+  if ticket.max_per_buyer == 0 {
+    env::panic(b"Buyers must be allowed at least 1 ticket");
+  }
+  if let (Some(sale_start), Some(sale_end)) = (ticket.sale_start, ticket.sale_end) {
+    if sale_end.0 <= sale_start.0 {
+      env::panic(b"Sale must end after it starts");
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::item::Price;
+  use crate::test_utils::*;
+  use near_sdk::json_types::U128;
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  const SALE_START: u64 = 1_000;
+  const SALE_END: u64 = 2_000;
+
+  fn robert() -> Option<AccountId> {
+    Some("robert.testnet".to_string())
+  }
+
+  // Alice sells 3 tickets to her livestream for 10 yocto, 2 per buyer at most
+  fn generate_hub() -> MainHub {
+    testing_env!(user_context("alice.testnet", None));
+    let mut main = MainHub::default();
+    main.create("Hello".to_string(), "World".to_string(), None, Some(true));
+    add_sealed_link(&mut main);
+    main.set_link_prices(
+      1,
+      vec![Price {
+        token_account_id: None,
+        amount: U128(10),
+      }],
+      None,
+      None,
+    );
+    main.set_link_ticket(
+      1,
+      Some(TicketSettings {
+        supply: 3,
+        sale_start: Some(U64(SALE_START)),
+        sale_end: Some(U64(SALE_END)),
+        max_per_buyer: 2,
+      }),
+      None,
+      None,
+    );
+    main
+  }
+
+  fn buy_at(main: &mut MainHub, buyer: &str, block_timestamp: u64) {
+    let mut context = user_context(buyer, Some(10));
+    context.block_timestamp = block_timestamp;
+    testing_env!(context);
+    main.buy_link("alice.testnet".to_string(), 1, None);
+  }
+
+  fn status_at(main: &MainHub, block_timestamp: u64) -> TicketStatus {
+    let mut context = user_context("robert.testnet", None);
+    context.block_timestamp = block_timestamp;
+    testing_env!(context);
+    main
+      .get_ticket_status("alice.testnet".to_string(), 1, None)
+      .unwrap()
+  }
+
+  #[test]
+  fn buying_tickets_counts_them_and_unlocks_the_link() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    buy_at(&mut main, "robert.testnet", SALE_START);
+    buy_at(&mut main, "robert.testnet", SALE_START);
+    // Then
+    let status = status_at(&main, SALE_START);
+    assert_eq!(status.sold, 2);
+    assert_eq!(status.remaining, 1);
+    assert!(status.on_sale);
+    let links = main.get_links("alice.testnet".to_string(), None, robert());
+    assert!(links[0].sealed_uri.is_some());
+  }
+
+  #[test]
+  fn status_shows_sale_window() {
+    // Given
+    let main = generate_hub();
+    // When
+    let before = status_at(&main, SALE_START - 1);
+    let after = status_at(&main, SALE_END);
+    // Then
+    assert!(!before.on_sale);
+    assert!(!after.on_sale);
+    assert_eq!(before.remaining, 3);
+  }
+
+  #[test]
+  fn links_without_tickets_have_no_status() {
+    // Given
+    let mut main = generate_hub();
+    testing_env!(user_context("alice.testnet", None));
+    main.set_link_ticket(1, None, None, None);
+    // When
+    let status = main.get_ticket_status("alice.testnet".to_string(), 1, None);
+    // Then
+    assert_eq!(status, None);
+  }
+
+  #[test]
+  #[should_panic(expected = "Tickets are sold out")]
+  fn buy_sold_out_ticket_panics() {
+    // Given
+    let mut main = generate_hub();
+    buy_at(&mut main, "robert.testnet", SALE_START);
+    buy_at(&mut main, "robert.testnet", SALE_START);
+    buy_at(&mut main, "carol.testnet", SALE_START);
+    assert!(!status_at(&main, SALE_START).on_sale);
+    // When
+    buy_at(&mut main, "dave.testnet", SALE_START);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Can buy at most 2 tickets")]
+  fn buy_more_tickets_than_allowed_panics() {
+    // Given
+    let mut main = generate_hub();
+    buy_at(&mut main, "robert.testnet", SALE_START);
+    buy_at(&mut main, "robert.testnet", SALE_START);
+    // When
+    buy_at(&mut main, "robert.testnet", SALE_START);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Ticket sales haven't started")]
+  fn buy_ticket_before_sale_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    buy_at(&mut main, "robert.testnet", SALE_START - 1);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Ticket sales have ended")]
+  fn buy_ticket_after_sale_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    buy_at(&mut main, "robert.testnet", SALE_END);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  fn holders_are_paginated_in_order_of_purchase() {
+    // Given
+    let mut main = generate_hub();
+    buy_at(&mut main, "robert.testnet", SALE_START);
+    buy_at(&mut main, "carol.testnet", SALE_START);
+    buy_at(&mut main, "robert.testnet", SALE_START);
+    // When
+    let holders = main.get_ticket_holders("alice.testnet".to_string(), 1, None, 1, 10);
+    // Then
+    assert_eq!(
+      holders,
+      vec![TicketHolder {
+        account_id: "carol.testnet".to_string(),
+        tickets: 1,
+      }]
+    );
+    let first = main.get_ticket_holders("alice.testnet".to_string(), 1, None, 0, 1);
+    assert_eq!(first[0].account_id, "robert.testnet");
+    assert_eq!(first[0].tickets, 2);
+  }
+
+  #[test]
+  #[should_panic(expected = "Supply can't be below the tickets already sold")]
+  fn lower_supply_below_sold_panics() {
+    // Given
+    let mut main = generate_hub();
+    buy_at(&mut main, "robert.testnet", SALE_START);
+    buy_at(&mut main, "carol.testnet", SALE_START);
+    testing_env!(user_context("alice.testnet", None));
+    // When
+    main.set_link_ticket(
+      1,
+      Some(TicketSettings {
+        supply: 1,
+        sale_start: None,
+        sale_end: None,
+        max_per_buyer: 1,
+      }),
+      None,
+      None,
+    );
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Ticket supply can be at most 1000")]
+  fn set_ticket_over_supply_max_panics() {
+    // Given
+    let mut main = generate_hub();
+    testing_env!(user_context("alice.testnet", None));
+    // When
+    main.set_link_ticket(
+      1,
+      Some(TicketSettings {
+        supply: TICKET_SUPPLY_MAX + 1,
+        sale_start: None,
+        sale_end: None,
+        max_per_buyer: 1,
+      }),
+      None,
+      None,
+    );
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Sale must end after it starts")]
+  fn set_ticket_with_inverted_window_panics() {
+    // Given
+    let mut main = generate_hub();
+    testing_env!(user_context("alice.testnet", None));
+    // When
+    main.set_link_ticket(
+      1,
+      Some(TicketSettings {
+        supply: 10,
+        sale_start: Some(U64(SALE_END)),
+        sale_end: Some(U64(SALE_START)),
+        max_per_buyer: 1,
+      }),
+      None,
+      None,
+    );
+    // Then
+    // - Should panic
+  }
+}
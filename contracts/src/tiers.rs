@@ -0,0 +1,383 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{env, near_bindgen, AccountId, Promise};
+use serde::{Deserialize, Serialize};
+// Crates
+use crate::fees::FeeSource;
+use crate::link3::Link3;
+use crate::profiles::normalize_profile;
+use crate::*;
+
+pub const TIERS_MAX_COUNT: usize = 10;
+pub const TIER_NAME_MAX_LENGTH: usize = 30;
+
+// Membership level of a profile, paid in NEAR for `period` (ns) at a time
+#[derive(BorshSerialize, BorshDeserialize, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Tier {
+  pub id: u64,
+  pub name: String,
+  pub price: U128,
+  pub period: U64,
+  // Higher ranks unlock the links of every lower tier
+  pub rank: u8,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Membership {
+  pub tier_id: u64,
+  pub expires_at: U64,
+}
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  pub fn get_tiers(&self, account_id: AccountId, profile: Option<String>) -> Vec<Tier> {
+    self
+      .internal_expect_profile(&account_id, &normalize_profile(profile))
+      .tiers()
+  }
+
+  // Current membership of `member_account_id` to the profile, None once it expired
+  pub fn get_membership(
+    &self,
+    account_id: AccountId,
+    member_account_id: AccountId,
+    profile: Option<String>,
+  ) -> Option<Membership> {
+    self
+      .internal_expect_profile(&account_id, &normalize_profile(profile))
+      .membership_of(&member_account_id)
+      .cloned()
+  }
+
+  /****************
+   * CALL METHODS *
+   ****************/
+  pub fn add_tier(
+    &mut self,
+    name: String,
+    price: U128,
+    period: U64,
+    rank: u8,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
+  ) -> Link3 {
+    assert_valid_tier(&name, price, period);
+
+    let account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
+
+    link3.add_tier(name, price, period, rank);
+
+    self.internal_save_profile(&account_id, &profile, &link3);
+    link3
+  }
+
+  pub fn remove_tier(
+    &mut self,
+    tier_id: u64,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
+  ) -> Link3 {
+    let account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
+
+    link3.remove_tier(tier_id);
+
+    self.internal_save_profile(&account_id, &profile, &link3);
+    link3
+  }
+
+  // Makes the link readable by members of `tier_id` and above, None removes the requirement
+  pub fn set_link_tier(
+    &mut self,
+    id: u64,
+    tier_id: Option<u64>,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
+  ) -> Link3 {
+    let account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
+
+    // Update item
+    link3.update_min_tier(id, tier_id);
+
+    // Save to hub state
+    self.internal_save_profile(&account_id, &profile, &link3);
+    link3
+  }
+
+  // Pays one period of the tier, any excess deposit is refunded
+  #[payable]
+  pub fn subscribe(
+    &mut self,
+    owner_account_id: AccountId,
+    tier_id: u64,
+    profile: Option<String>,
+  ) -> Membership {
+    let member_account_id = env::predecessor_account_id();
+    let deposit = env::attached_deposit();
+
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&owner_account_id, &profile);
+    if !link3.is_published() {
+      env::panic(b"This contract is not published");
+    }
+    if link3.owner_account_id() == &member_account_id {
+      env::panic(b"Can't subscribe to your own profile");
+    }
+    let price = link3.get_tier(tier_id).price.0;
+    if deposit < price {
+      env::panic(b"Not enough deposit to subscribe");
+    }
+
+    let membership = link3
+      .grant_membership(member_account_id.clone(), tier_id)
+      .clone();
+    self.internal_save_profile(&owner_account_id, &profile, &link3);
+
+    let proceeds =
+      self.internal_take_fee(FeeSource::Subscription, &owner_account_id, &None, price);
+    self.internal_credit(link3.owner_account_id(), &None, proceeds);
+    if deposit > price {
+      Promise::new(member_account_id).transfer(deposit - price);
+    }
+
+    membership
+  }
+}
+
+fn assert_valid_tier(name: &str, price: U128, period: U64) {
+  if name.trim().is_empty() {
+    env::panic(b"Tier name cannot be empty");
+  }
+  if name.len() > TIER_NAME_MAX_LENGTH {
+    panic!(
+      "Tier name must be at most {} characters long",
+      TIER_NAME_MAX_LENGTH
+    );
+  }
+  if price.0 == 0 {
+    env::panic(b"Price must be greater than 0");
+  }
+  if period.0 == 0 {
+    env::panic(b"Period must be greater than 0");
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::*;
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  const MONTH: u64 = 30 * 86_400_000_000_000;
+
+  fn robert() -> Option<AccountId> {
+    Some("robert.testnet".to_string())
+  }
+
+  // Alice has a Fan tier (rank 1, 10 yocto) and a Patron tier (rank 2, 30 yocto),
+  // her first link needs Fan and her second one Patron
+  fn generate_hub() -> MainHub {
+    testing_env!(user_context("alice.testnet", None));
+    let mut main = MainHub::default();
+    main.create("Hello".to_string(), "World".to_string(), None, Some(true));
+    main.add_tier("Fan".to_string(), U128(10), U64(MONTH), 1, None, None);
+    main.add_tier("Patron".to_string(), U128(30), U64(MONTH), 2, None, None);
+    for tier_id in 1..=2 {
+      add_sealed_link(&mut main);
+      main.set_link_tier(tier_id, Some(tier_id), None, None);
+    }
+    main
+  }
+
+  fn subscribe_at(main: &mut MainHub, tier_id: u64, deposit: u128, block_timestamp: u64) {
+    let mut context = user_context("robert.testnet", Some(deposit));
+    context.block_timestamp = block_timestamp;
+    testing_env!(context);
+    main.subscribe("alice.testnet".to_string(), tier_id, None);
+  }
+
+  fn robert_reads_at(main: &MainHub, block_timestamp: u64) -> Vec<bool> {
+    let mut context = user_context("robert.testnet", None);
+    context.block_timestamp = block_timestamp;
+    testing_env!(context);
+    let links = main.get_links("alice.testnet".to_string(), None, robert());
+    links.iter().map(|link| link.sealed_uri.is_some()).collect()
+  }
+
+  #[test]
+  fn get_tiers_returns_tiers_by_rank() {
+    // Given
+    let mut main = generate_hub();
+    main.add_tier("Supporter".to_string(), U128(1), U64(MONTH), 0, None, None);
+    // When
+    let tiers = main.get_tiers("alice.testnet".to_string(), None);
+    // Then
+    let names: Vec<&str> = tiers.iter().map(|tier| tier.name.as_str()).collect();
+    assert_eq!(names, vec!["Supporter", "Fan", "Patron"]);
+  }
+
+  #[test]
+  fn subscribe_unlocks_links_up_to_tier() {
+    // Given
+    let mut main = generate_hub();
+    assert_eq!(robert_reads_at(&main, 0), vec![false, false]);
+    // When
+    subscribe_at(&mut main, 1, 10, 0);
+    // Then
+    assert_eq!(robert_reads_at(&main, 0), vec![true, false]);
+    assert_eq!(main.get_balance("alice.testnet".to_string()).near, U128(10));
+  }
+
+  #[test]
+  fn higher_tier_unlocks_lower_tier_links() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    subscribe_at(&mut main, 2, 30, 0);
+    // Then
+    assert_eq!(robert_reads_at(&main, 0), vec![true, true]);
+  }
+
+  #[test]
+  fn membership_expires_after_period() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    subscribe_at(&mut main, 1, 10, 0);
+    // Then
+    assert_eq!(robert_reads_at(&main, MONTH - 1), vec![true, false]);
+    assert_eq!(robert_reads_at(&main, MONTH), vec![false, false]);
+    assert_eq!(
+      main.get_membership("alice.testnet".to_string(), "robert.testnet".to_string(), None),
+      None
+    );
+  }
+
+  #[test]
+  fn subscribe_drops_expired_memberships() {
+    // Given
+    let mut main = generate_hub();
+    subscribe_at(&mut main, 1, 10, 0);
+    // When
+    let mut context = user_context("carol.testnet", Some(10));
+    context.block_timestamp = MONTH;
+    testing_env!(context);
+    main.subscribe("alice.testnet".to_string(), 1, None);
+    // Then
+    let link3 = main.get("alice.testnet".to_string()).unwrap();
+    let members: Vec<&AccountId> = link3.members().collect();
+    assert_eq!(members, vec![&"carol.testnet".to_string()]);
+  }
+
+  #[test]
+  fn renewing_early_adds_remaining_time() {
+    // Given
+    let mut main = generate_hub();
+    subscribe_at(&mut main, 1, 10, 0);
+    // When
+    subscribe_at(&mut main, 1, 10, MONTH / 2);
+    // Then
+    let membership = main
+      .get_membership("alice.testnet".to_string(), "robert.testnet".to_string(), None)
+      .unwrap();
+    assert_eq!(membership.expires_at, U64(2 * MONTH));
+  }
+
+  #[test]
+  fn subscribe_pays_platform_fee_and_refunds_excess() {
+    // Given
+    let mut main = generate_hub();
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    main.set_platform_fee(1_000);
+    // When
+    subscribe_at(&mut main, 2, 35, 0);
+    // Then
+    assert_eq!(main.get_balance("alice.testnet".to_string()).near, U128(27));
+    assert_eq!(main.get_fees_collected()[0].source, FeeSource::Subscription);
+    assert!(created_receipts().contains(
+      r#""receiver_id":"robert.testnet","actions":[{"Transfer":{"deposit":5}}]"#
+    ));
+  }
+
+  #[test]
+  #[should_panic(expected = "Not enough deposit to subscribe")]
+  fn subscribe_with_low_deposit_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    subscribe_at(&mut main, 2, 10, 0);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Tier is still required by a link")]
+  fn remove_tier_required_by_link_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.remove_tier(1, None, None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Seal the link before gating it")]
+  fn set_tier_of_unsealed_link_panics() {
+    // Given
+    let mut main = generate_hub();
+    main.add_link(
+      "uri".to_string(),
+      "title".to_string(),
+      "description".to_string(),
+      None,
+      None,
+      None,
+    );
+    // When
+    main.set_link_tier(3, Some(1), None, None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Another tier already has this rank")]
+  fn add_tier_with_taken_rank_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.add_tier("VIP".to_string(), U128(50), U64(MONTH), 2, None, None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the owner or an admin can manage tiers")]
+  fn add_tier_as_stranger_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    testing_env!(user_context("eve.testnet", None));
+    main.add_tier(
+      "VIP".to_string(),
+      U128(50),
+      U64(MONTH),
+      3,
+      None,
+      Some("alice.testnet".to_string()),
+    );
+    // Then
+    // - Should panic
+  }
+}
@@ -0,0 +1,232 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{env, near_bindgen, AccountId};
+use serde::Serialize;
+// Crates
+use crate::fees::FeeSource;
+use crate::*;
+
+// Only the most recent tips are kept for display, older ones drop out
+pub const TIPS_MAX_RECENT: usize = 50;
+pub const TIP_MESSAGE_MAX_LENGTH: usize = 140;
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Clone, Debug)]
+pub struct Tip {
+  pub tipper_account_id: AccountId,
+  // What the owner received, after the platform fee
+  pub amount: U128,
+  pub message: Option<String>,
+  pub timestamp: U64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Default)]
+pub struct TipJar {
+  // Oldest first
+  recent: Vec<Tip>,
+  total: Balance,
+}
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  // Recent tips of the account, most recent first
+  pub fn get_tips(&self, account_id: AccountId, from_index: u64, limit: u64) -> Vec<Tip> {
+    self
+      .tips
+      .get(&account_id)
+      .unwrap_or_default()
+      .recent
+      .into_iter()
+      .rev()
+      .skip(from_index as usize)
+      .take(limit as usize)
+      .collect()
+  }
+
+  // Everything the account received in tips, including tips no longer listed
+  pub fn get_tips_total(&self, account_id: AccountId) -> U128 {
+    U128(self.tips.get(&account_id).unwrap_or_default().total)
+  }
+
+  /****************
+   * CALL METHODS *
+   ****************/
+  // Credits the deposit to the owner of the account's profile, minus the platform fee
+  #[payable]
+  pub fn tip(&mut self, account_id: AccountId, message: Option<String>) -> U128 {
+    let tipper_account_id = env::predecessor_account_id();
+    let deposit = env::attached_deposit();
+    if deposit == 0 {
+      env::panic(b"Tip must be greater than 0");
+    }
+    if let Some(message) = &message {
+      assert_valid_tip_message(message);
+    }
+
+    let link3 = self
+      .hub
+      .get(&account_id)
+      .unwrap_or_else(|| env::panic(b"Could not find link3 for this account."));
+    if !link3.is_published() {
+      env::panic(b"This contract is not published");
+    }
+    let owner_account_id = link3.owner_account_id().clone();
+    if owner_account_id == tipper_account_id {
+      env::panic(b"Can't tip yourself");
+    }
+
+    let amount = self.internal_take_fee(FeeSource::Tip, &owner_account_id, &None, deposit);
+
+    let mut tip_jar = self.tips.get(&account_id).unwrap_or_default();
+    tip_jar.recent.push(Tip {
+      tipper_account_id,
+      amount: U128(amount),
+      message,
+      timestamp: U64(env::block_timestamp()),
+    });
+    if tip_jar.recent.len() > TIPS_MAX_RECENT {
+      tip_jar.recent.remove(0);
+    }
+    tip_jar.total += amount;
+    self.tips.insert(&account_id, &tip_jar);
+
+    self.internal_credit(&owner_account_id, &None, amount);
+
+    U128(amount)
+  }
+}
+
+fn assert_valid_tip_message(message: &str) {
+  if message.is_empty() {
+    panic!("Tip message cannot be empty");
+  }
+
+  if message.len() > TIP_MESSAGE_MAX_LENGTH {
+    panic!(
+      "Tip message must be at most {} characters long",
+      TIP_MESSAGE_MAX_LENGTH
+    );
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::*;
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  fn generate_hub() -> MainHub {
+    testing_env!(user_context("alice.testnet", None));
+    let mut main = MainHub::default();
+    main.create("Hello".to_string(), "World".to_string(), None, Some(true));
+    main
+  }
+
+  fn tip(main: &mut MainHub, tipper: &str, amount: Balance, message: Option<&str>) -> U128 {
+    testing_env!(user_context(tipper, Some(amount)));
+    main.tip(
+      "alice.testnet".to_string(),
+      message.map(|message| message.to_string()),
+    )
+  }
+
+  #[test]
+  fn tip_credits_deposit_to_owner() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    tip(&mut main, "robert.testnet", 100, Some("Great links!"));
+    // Then
+    assert_eq!(main.get_balance("alice.testnet".to_string()).near, U128(100));
+    let tips = main.get_tips("alice.testnet".to_string(), 0, 10);
+    assert_eq!(tips.len(), 1);
+    assert_eq!(tips[0].tipper_account_id, "robert.testnet".to_string());
+    assert_eq!(tips[0].amount, U128(100));
+    assert_eq!(tips[0].message, Some("Great links!".to_string()));
+  }
+
+  #[test]
+  fn tip_keeps_platform_fee() {
+    // Given
+    let mut main = generate_hub();
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    main.set_platform_fee(250);
+    // When
+    let amount = tip(&mut main, "robert.testnet", 1_000, None);
+    // Then
+    assert_eq!(amount, U128(975));
+    assert_eq!(main.get_balance("alice.testnet".to_string()).near, U128(975));
+    assert_eq!(main.get_tips_total("alice.testnet".to_string()), U128(975));
+  }
+
+  #[test]
+  fn get_tips_returns_most_recent_first_and_total_keeps_everything() {
+    // Given
+    let mut main = generate_hub();
+    for i in 0..=TIPS_MAX_RECENT as u128 {
+      tip(&mut main, "robert.testnet", i + 1, None);
+    }
+    // When
+    let tips = main.get_tips("alice.testnet".to_string(), 0, 100);
+    // Then
+    assert_eq!(tips.len(), TIPS_MAX_RECENT);
+    assert_eq!(tips[0].amount, U128(TIPS_MAX_RECENT as u128 + 1));
+    assert_eq!(tips[TIPS_MAX_RECENT - 1].amount, U128(2));
+    assert_eq!(
+      main.get_tips("alice.testnet".to_string(), 1, 1)[0].amount,
+      U128(TIPS_MAX_RECENT as u128)
+    );
+    // 1 + 2 + ... + 51
+    assert_eq!(main.get_tips_total("alice.testnet".to_string()), U128(1_326));
+  }
+
+  #[test]
+  #[should_panic(expected = "Tip must be greater than 0")]
+  fn tip_without_deposit_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    tip(&mut main, "robert.testnet", 0, None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Tip message must be at most 140 characters long")]
+  fn tip_with_long_message_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    tip(&mut main, "robert.testnet", 1, Some(&"a".repeat(141)));
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "This contract is not published")]
+  fn tip_unpublished_profile_panics() {
+    // Given
+    let mut main = generate_hub();
+    main.update_published_status(false, None);
+    // When
+    tip(&mut main, "robert.testnet", 1, None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Can't tip yourself")]
+  fn tip_yourself_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    tip(&mut main, "alice.testnet", 1, None);
+    // Then
+    // - Should panic
+  }
+}
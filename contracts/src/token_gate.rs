@@ -0,0 +1,285 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde_json;
+use near_sdk::{env, near_bindgen, AccountId, Gas, Promise, PromiseResult};
+// Crates
+use crate::external::{ext_ft, ext_self};
+use crate::item::TokenGate;
+use crate::link3::Link3;
+use crate::profiles::normalize_profile;
+use crate::tags::LinkId;
+use crate::*;
+
+// How long a verified token balance unlocks a link before it has to be checked again (1 day)
+const TOKEN_GATE_TTL: u64 = 24 * 60 * 60 * 1_000_000_000;
+const GAS_FOR_FT_BALANCE_OF: Gas = 10_000_000_000_000;
+const GAS_FOR_ON_TOKEN_GATE_BALANCE: Gas = 10_000_000_000_000;
+const NO_DEPOSIT: u128 = 0;
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * CALL METHODS *
+   ****************/
+  pub fn set_token_gate(
+    &mut self,
+    id: u64,
+    token_gate: Option<TokenGate>,
+    profile: Option<String>,
+    owner_account_id: Option<AccountId>,
+  ) -> Link3 {
+    if let Some(gate) = &token_gate {
+      if !env::is_valid_account_id(gate.token_account_id.as_bytes()) {
+        env::panic(b"Token account id is not valid");
+      }
+    }
+
+    let account_id = owner_account_id.unwrap_or_else(env::signer_account_id);
+    let profile = normalize_profile(profile);
+    let mut link3 = self.internal_expect_profile(&account_id, &profile);
+
+    // Update item
+    link3.update_token_gate(id, token_gate);
+
+    // Save to hub state
+    self.internal_save_profile(&account_id, &profile, &link3);
+    link3
+  }
+
+  // Checks the signer's balance on the link's token and caches the grant when it is enough
+  pub fn verify_token_gate(
+    &mut self,
+    owner_account_id: AccountId,
+    link_id: u64,
+    profile: Option<String>,
+  ) -> Promise {
+    let profile = normalize_profile(profile);
+    let link3 = self.internal_expect_profile(&owner_account_id, &profile);
+
+    let token_gate = link3
+      .get_link(link_id)
+      .token_gate()
+      .cloned()
+      .unwrap_or_else(|| env::panic(b"Link is not token gated"));
+
+    let account_id = env::signer_account_id();
+    ext_ft::ft_balance_of(
+      account_id.clone(),
+      &token_gate.token_account_id,
+      NO_DEPOSIT,
+      GAS_FOR_FT_BALANCE_OF,
+    )
+    .then(ext_self::on_token_gate_balance(
+      LinkId {
+        account_id: owner_account_id,
+        profile,
+        link_id,
+      },
+      account_id,
+      token_gate,
+      &env::current_account_id(),
+      NO_DEPOSIT,
+      GAS_FOR_ON_TOKEN_GATE_BALANCE,
+    ))
+  }
+
+  /*************
+   * CALLBACKS *
+   *************/
+  // `token_gate` is the gate the balance was fetched for
+  #[private]
+  pub fn on_token_gate_balance(
+    &mut self,
+    link: LinkId,
+    account_id: AccountId,
+    token_gate: TokenGate,
+  ) -> bool {
+    let balance = match env::promise_result(0) {
+      PromiseResult::Successful(value) => serde_json::from_slice::<U128>(&value)
+        .map(|balance| balance.0)
+        .unwrap_or(0),
+      _ => return false,
+    };
+
+    let link3: Link3 = match self.internal_get_profile(&link.account_id, &link.profile) {
+      Some(link3) => link3,
+      None => return false,
+    };
+
+    // The gate might have changed (or be gone) while the balance was being fetched,
+    // a balance of another token proves nothing
+    if link3.get_link(link.link_id).token_gate() != Some(&token_gate) {
+      return false;
+    }
+    if balance < token_gate.min_balance.0 {
+      self.internal_drop_expired_grant(&link, &account_id);
+      return false;
+    }
+
+    self.internal_grant_access(&link, &account_id, env::block_timestamp() + TOKEN_GATE_TTL);
+    true
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::*;
+  use link3_mock_ft::MockFungibleToken;
+  use near_sdk::{testing_env, MockedBlockchain};
+
+  fn robert() -> Option<AccountId> {
+    Some("robert.testnet".to_string())
+  }
+
+  // What the mock NEP-141 contract answers to `ft_balance_of` once Robert holds `balance`
+  fn robert_ft_balance_of(balance: u128) -> PromiseResult {
+    let mut token = MockFungibleToken::default();
+    token.mint("robert.testnet".to_string(), U128(balance));
+    promise_success(&token.ft_balance_of("robert.testnet".to_string()))
+  }
+
+  // Runs the callback as the hub would after the token contract answered
+  fn set_callback_context(result: PromiseResult) {
+    testing_env!(
+      callback_context("robert.testnet"),
+      Default::default(),
+      Default::default(),
+      Default::default(),
+      vec![result]
+    );
+  }
+
+  fn generate_gate() -> TokenGate {
+    TokenGate {
+      token_account_id: "token.testnet".to_string(),
+      min_balance: U128(100),
+    }
+  }
+
+  // Robert's balance was fetched for `token_gate`
+  fn on_token_gate_balance(main: &mut MainHub, token_gate: TokenGate) -> bool {
+    let link = LinkId {
+      account_id: "alice.testnet".to_string(),
+      profile: None,
+      link_id: 1,
+    };
+    main.on_token_gate_balance(link, "robert.testnet".to_string(), token_gate)
+  }
+
+  fn generate_gated_hub() -> MainHub {
+    testing_env!(user_context("alice.testnet", None));
+    let mut main = MainHub::default();
+    main.create("Hello".to_string(), "World".to_string(), None, Some(true));
+    add_sealed_link(&mut main);
+    main.set_token_gate(1, Some(generate_gate()), None, None);
+    main
+  }
+
+  fn robert_can_read(main: &MainHub) -> bool {
+    testing_env!(user_context("robert.testnet", None));
+    let links = main.get_links("alice.testnet".to_string(), None, robert());
+    links[0].sealed_uri.is_some()
+  }
+
+  #[test]
+  fn verify_token_gate_calls_token_contract() {
+    // Given
+    let mut main = generate_gated_hub();
+    testing_env!(user_context("robert.testnet", None));
+    // When
+    main.verify_token_gate("alice.testnet".to_string(), 1, None);
+    // Then
+    let receipts = created_receipts();
+    assert!(receipts.contains("token.testnet"));
+    assert!(receipts.contains("ft_balance_of"));
+    assert!(receipts.contains("on_token_gate_balance"));
+  }
+
+  #[test]
+  #[should_panic(expected = "Link is not token gated")]
+  fn verify_token_gate_on_public_link_panics() {
+    // Given
+    let mut main = generate_gated_hub();
+    main.set_token_gate(1, None, None, None);
+    // When
+    main.verify_token_gate("alice.testnet".to_string(), 1, None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  fn on_token_gate_balance_with_enough_tokens_grants_access() {
+    // Given
+    let mut main = generate_gated_hub();
+    assert!(!robert_can_read(&main));
+    // When
+    set_callback_context(robert_ft_balance_of(100));
+    let granted = on_token_gate_balance(&mut main, generate_gate());
+    // Then
+    assert!(granted);
+    assert!(robert_can_read(&main));
+  }
+
+  #[test]
+  fn on_token_gate_balance_with_low_balance_does_not_grant_access() {
+    // Given
+    let mut main = generate_gated_hub();
+    // When
+    set_callback_context(robert_ft_balance_of(99));
+    let granted = on_token_gate_balance(&mut main, generate_gate());
+    // Then
+    assert!(!granted);
+    assert!(!robert_can_read(&main));
+  }
+
+  #[test]
+  fn on_token_gate_balance_with_failed_call_does_not_grant_access() {
+    // Given
+    let mut main = generate_gated_hub();
+    // When
+    set_callback_context(PromiseResult::Failed);
+    let granted = on_token_gate_balance(&mut main, generate_gate());
+    // Then
+    assert!(!granted);
+    assert!(!robert_can_read(&main));
+  }
+
+  #[test]
+  fn on_token_gate_balance_of_replaced_token_does_not_grant_access() {
+    // Given
+    let mut main = generate_gated_hub();
+    main.set_token_gate(
+      1,
+      Some(TokenGate {
+        token_account_id: "other.testnet".to_string(),
+        min_balance: U128(100),
+      }),
+      None,
+      None,
+    );
+    // When
+    set_callback_context(robert_ft_balance_of(100));
+    let granted = on_token_gate_balance(&mut main, generate_gate());
+    // Then
+    assert!(!granted);
+    assert!(!robert_can_read(&main));
+  }
+
+  #[test]
+  fn token_gate_grant_expires_after_ttl() {
+    // Given
+    let mut main = generate_gated_hub();
+    set_callback_context(robert_ft_balance_of(100));
+    on_token_gate_balance(&mut main, generate_gate());
+    // When
+    let mut context = user_context("robert.testnet", None);
+    context.block_timestamp = TOKEN_GATE_TTL;
+    testing_env!(context);
+    // Then
+    let links = main.get_links("alice.testnet".to_string(), None, robert());
+    assert_eq!(links[0].sealed_uri, None, "Grant should have expired");
+  }
+}
@@ -0,0 +1,643 @@
+use near_sdk::json_types::U64;
+use near_sdk::serde_json;
+use near_sdk::{env, near_bindgen, AccountId, Gas, Promise, PromiseResult};
+use serde::{Deserialize, Serialize};
+// Crates
+use crate::attestations::{assert_valid_subject, Attestation, AttestationKind};
+use crate::external::{ext_oracle, ext_self};
+use crate::profiles::{normalize_profile, ProfileId};
+use crate::*;
+
+const CHALLENGE_PREFIX: &str = "link3-verify:";
+const WELL_KNOWN_PATH: &str = "/.well-known/link3.txt";
+const DOMAIN_MAX_LENGTH: usize = 253;
+// Bytes of the hash kept in the challenge, short enough to fit in a bio or a tweet
+const CHALLENGE_HASH_LENGTH: usize = 16;
+const GAS_FOR_VERIFY_PROOF: Gas = 20_000_000_000_000;
+const GAS_FOR_ON_PROOF_VERIFIED: Gas = 20_000_000_000_000;
+const NO_DEPOSIT: u128 = 0;
+
+// Host names can't hold paths, ports nor credentials, and need at least two labels
+fn is_valid_domain(domain: &str) -> bool {
+  domain.len() <= DOMAIN_MAX_LENGTH
+    && domain.contains('.')
+    && domain.split('.').all(|label| {
+      !label.is_empty()
+        && !label.starts_with('-')
+        && !label.ends_with('-')
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    })
+}
+
+// Where the owner publishes the challenge of a domain
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DomainProof {
+  // TXT record of the domain
+  Dns,
+  // `https://<domain>/.well-known/link3.txt`
+  WellKnown,
+}
+
+impl DomainProof {
+  // Uri the oracle looks the challenge up at, `dns:` uris follow RFC 4501
+  pub fn uri(&self, domain: &str) -> String {
+    match self {
+      DomainProof::Dns => format!("dns:{}?type=TXT", domain),
+      DomainProof::WellKnown => format!("https://{}{}", domain, WELL_KNOWN_PATH),
+    }
+  }
+}
+
+#[near_bindgen]
+impl MainHub {
+  /****************
+   * VIEW METHODS *
+   ****************/
+  pub fn get_verification_oracle(&self) -> Option<AccountId> {
+    self.verification_oracle_account_id.clone()
+  }
+
+  // String the account posts publicly on the platform (or its domain) to prove it
+  // controls the handle, always the same for an account and platform on this hub
+  pub fn get_verification_challenge(
+    &self,
+    account_id: AccountId,
+    kind: AttestationKind,
+  ) -> String {
+    let seed = format!("{}/{}/{}", env::current_account_id(), account_id, kind.as_str());
+    let hash: String = env::sha256(seed.as_bytes())[..CHALLENGE_HASH_LENGTH]
+      .iter()
+      .map(|byte| format!("{:02x}", byte))
+      .collect();
+    format!("{}{}", CHALLENGE_PREFIX, hash)
+  }
+
+  /****************
+   * CALL METHODS *
+   ****************/
+  // Attestations of a previous oracle stop counting once it's replaced, so the links
+  // they proved lose their marks and domains
+  pub fn set_verification_oracle(&mut self, account_id: Option<AccountId>) {
+    self.assert_admin();
+    if let Some(account_id) = &account_id {
+      if !env::is_valid_account_id(account_id.as_bytes()) {
+        env::panic(b"Oracle account id is not valid");
+      }
+    }
+
+    self.verification_oracle_account_id = account_id;
+  }
+
+  // Asks the oracle to find the signer's challenge in the post at `proof_uri`, the
+  // profile gets an attestation of `subject` from the oracle when it does
+  pub fn request_verification(
+    &mut self,
+    kind: AttestationKind,
+    subject: String,
+    proof_uri: String,
+    profile: Option<String>,
+  ) -> Promise {
+    // Anyone can post on another site, a domain's proof has to live on the domain
+    if kind == AttestationKind::Domain {
+      env::panic(b"Domains are verified with request_domain_verification");
+    }
+    assert_valid_subject(&subject);
+    if proof_uri.is_empty() {
+      env::panic(b"Proof uri cannot be empty");
+    }
+
+    self.internal_request_verification(kind, subject, proof_uri, profile)
+  }
+
+  // Asks the oracle to find the signer's challenge in the domain's TXT record or
+  // well-known file, the profile's links to the domain get marked when it does
+  pub fn request_domain_verification(
+    &mut self,
+    domain: String,
+    proof: DomainProof,
+    profile: Option<String>,
+  ) -> Promise {
+    let domain = domain.to_lowercase();
+    if !is_valid_domain(&domain) {
+      env::panic(b"Domain is not valid");
+    }
+
+    let proof_uri = proof.uri(&domain);
+    self.internal_request_verification(AttestationKind::Domain, domain, proof_uri, profile)
+  }
+
+  /*************
+   * CALLBACKS *
+   *************/
+  // Records the attestation the request prepared, issued when the oracle answers
+  #[private]
+  pub fn on_proof_verified(
+    &mut self,
+    account_id: AccountId,
+    profile: Option<String>,
+    attestation: Attestation,
+  ) -> bool {
+    let is_found = match env::promise_result(0) {
+      PromiseResult::Successful(value) => serde_json::from_slice::<bool>(&value).unwrap_or(false),
+      _ => false,
+    };
+    if !is_found {
+      return false;
+    }
+
+    // The oracle might have been replaced (or the profile deleted) while it was checking
+    if self.verification_oracle_account_id.as_ref() != Some(&attestation.verifier_account_id)
+      || self.internal_get_profile(&account_id, &profile).is_none()
+    {
+      return false;
+    }
+
+    let attestation = Attestation {
+      issued_at: U64(env::block_timestamp()),
+      ..attestation
+    };
+    self.internal_add_attestation(&ProfileId { account_id, profile }, attestation);
+    true
+  }
+}
+
+impl MainHub {
+  /************
+   * INTERNAL *
+   ************/
+  fn internal_request_verification(
+    &mut self,
+    kind: AttestationKind,
+    subject: String,
+    proof_uri: String,
+    profile: Option<String>,
+  ) -> Promise {
+    let oracle_account_id = self
+      .verification_oracle_account_id
+      .clone()
+      .unwrap_or_else(|| env::panic(b"No verification oracle is set"));
+
+    let account_id = env::signer_account_id();
+    let profile = normalize_profile(profile);
+    self.internal_expect_profile(&account_id, &profile);
+
+    let challenge = self.get_verification_challenge(account_id.clone(), kind);
+    ext_oracle::verify_proof(
+      kind,
+      subject.clone(),
+      proof_uri.clone(),
+      challenge,
+      &oracle_account_id,
+      NO_DEPOSIT,
+      GAS_FOR_VERIFY_PROOF,
+    )
+    .then(ext_self::on_proof_verified(
+      account_id,
+      profile,
+      Attestation {
+        verifier_account_id: oracle_account_id,
+        kind,
+        subject,
+        issued_at: U64(env::block_timestamp()),
+        expires_at: None,
+        evidence_uri: Some(proof_uri),
+      },
+      &env::current_account_id(),
+      NO_DEPOSIT,
+      GAS_FOR_ON_PROOF_VERIFIED,
+    ))
+  }
+}
+
+/*********
+ * TESTS *
+ *********/
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::*;
+  use crate::item::ItemInfo;
+  use near_sdk::{testing_env, MockedBlockchain};
+  use std::collections::HashMap;
+
+  const ORACLE: &str = "oracle.testnet";
+  const GIST_URI: &str = "https://gist.github.com/alice/1";
+
+  // Stand-in oracle contract: knows public posts by uri, with their author and content
+  struct MockOracle {
+    posts: HashMap<String, (String, String)>,
+  }
+
+  impl MockOracle {
+    fn verify_proof(
+      &self,
+      _kind: AttestationKind,
+      subject: String,
+      proof_uri: String,
+      challenge: String,
+    ) -> bool {
+      self
+        .posts
+        .get(&proof_uri)
+        .is_some_and(|(author, content)| author == &subject && content.contains(&challenge))
+    }
+  }
+
+  // Alice has a published profile linking to her GitHub account
+  fn generate_hub() -> MainHub {
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    let mut main = MainHub::default();
+    main.set_verification_oracle(Some(ORACLE.to_string()));
+    testing_env!(user_context("alice.testnet", None));
+    main.create("Hello".to_string(), "World".to_string(), None, Some(true));
+    main.add_link(
+      "https://github.com/Alice".to_string(),
+      "GitHub".to_string(),
+      "My code".to_string(),
+      None,
+      None,
+      None,
+    );
+    main
+  }
+
+  // The oracle's gist of `author`, holding `content`
+  fn oracle_with_gist(author: &str, content: String) -> MockOracle {
+    let mut posts = HashMap::new();
+    posts.insert(GIST_URI.to_string(), (author.to_string(), content));
+    MockOracle { posts }
+  }
+
+  // Alice requests a verification of the `alice` handle, the oracle answers the callback
+  fn verify_github(main: &mut MainHub, oracle: &MockOracle) -> bool {
+    testing_env!(user_context("alice.testnet", None));
+    main.request_verification(
+      AttestationKind::Github,
+      "alice".to_string(),
+      GIST_URI.to_string(),
+      None,
+    );
+    let challenge =
+      main.get_verification_challenge("alice.testnet".to_string(), AttestationKind::Github);
+    let is_found = oracle.verify_proof(
+      AttestationKind::Github,
+      "alice".to_string(),
+      GIST_URI.to_string(),
+      challenge,
+    );
+
+    testing_env!(
+      callback_context("alice.testnet"),
+      Default::default(),
+      Default::default(),
+      Default::default(),
+      vec![promise_success(&is_found)]
+    );
+    main.on_proof_verified(
+      "alice.testnet".to_string(),
+      None,
+      Attestation {
+        verifier_account_id: ORACLE.to_string(),
+        kind: AttestationKind::Github,
+        subject: "alice".to_string(),
+        issued_at: U64(0),
+        expires_at: None,
+        evidence_uri: Some(GIST_URI.to_string()),
+      },
+    )
+  }
+
+  // Alice links to her website, the oracle answers a domain verification of alice.dev
+  fn verify_domain(main: &mut MainHub, oracle: &MockOracle, proof: DomainProof) -> bool {
+    testing_env!(user_context("alice.testnet", None));
+    main.add_link(
+      "https://alice.dev/about".to_string(),
+      "Website".to_string(),
+      "About me".to_string(),
+      None,
+      None,
+      None,
+    );
+    main.request_domain_verification("Alice.dev".to_string(), proof, None);
+    let challenge =
+      main.get_verification_challenge("alice.testnet".to_string(), AttestationKind::Domain);
+    let is_found = oracle.verify_proof(
+      AttestationKind::Domain,
+      "alice.dev".to_string(),
+      proof.uri("alice.dev"),
+      challenge,
+    );
+
+    testing_env!(
+      callback_context("alice.testnet"),
+      Default::default(),
+      Default::default(),
+      Default::default(),
+      vec![promise_success(&is_found)]
+    );
+    main.on_proof_verified(
+      "alice.testnet".to_string(),
+      None,
+      Attestation {
+        verifier_account_id: ORACLE.to_string(),
+        kind: AttestationKind::Domain,
+        subject: "alice.dev".to_string(),
+        issued_at: U64(0),
+        expires_at: None,
+        evidence_uri: Some(proof.uri("alice.dev")),
+      },
+    )
+  }
+
+  // The oracle's view of alice.dev: `content` published with the given proof
+  fn oracle_with_domain(main: &MainHub, proof: DomainProof) -> MockOracle {
+    let challenge =
+      main.get_verification_challenge("alice.testnet".to_string(), AttestationKind::Domain);
+    let mut posts = HashMap::new();
+    posts.insert(proof.uri("alice.dev"), ("alice.dev".to_string(), challenge));
+    MockOracle { posts }
+  }
+
+  fn website(main: &MainHub) -> ItemInfo {
+    main.get_links("alice.testnet".to_string(), None, None).remove(1)
+  }
+
+  fn alice_challenge(main: &MainHub) -> String {
+    main.get_verification_challenge("alice.testnet".to_string(), AttestationKind::Github)
+  }
+
+  #[test]
+  fn challenge_is_deterministic_per_account_and_platform() {
+    // Given
+    let main = generate_hub();
+    // When
+    let challenge = alice_challenge(&main);
+    // Then
+    assert!(challenge.starts_with(CHALLENGE_PREFIX));
+    assert_eq!(challenge, alice_challenge(&main));
+    assert_ne!(
+      challenge,
+      main.get_verification_challenge("bob.testnet".to_string(), AttestationKind::Github)
+    );
+    assert_ne!(
+      challenge,
+      main.get_verification_challenge("alice.testnet".to_string(), AttestationKind::Twitter)
+    );
+  }
+
+  #[test]
+  fn request_verification_calls_oracle() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.request_verification(
+      AttestationKind::Github,
+      "alice".to_string(),
+      GIST_URI.to_string(),
+      None,
+    );
+    // Then
+    let receipts = created_receipts();
+    assert!(receipts.contains(&format!(r#""receiver_id":"{}""#, ORACLE)));
+    assert!(receipts.contains("verify_proof"));
+    assert!(receipts.contains("on_proof_verified"));
+  }
+
+  #[test]
+  fn found_proof_attests_profile_and_marks_matching_link() {
+    // Given
+    let mut main = generate_hub();
+    let post = format!("Verifying my Link3: {}", alice_challenge(&main));
+    let oracle = oracle_with_gist("alice", post);
+    // When
+    let is_verified = verify_github(&mut main, &oracle);
+    // Then
+    assert!(is_verified);
+    let attestations = main.get_attestations("alice.testnet".to_string(), None);
+    assert_eq!(attestations.len(), 1);
+    assert_eq!(attestations[0].verifier_account_id, ORACLE.to_string());
+    assert_eq!(attestations[0].evidence_uri, Some(GIST_URI.to_string()));
+    assert!(main.get_links("alice.testnet".to_string(), None, None)[0].is_verified);
+  }
+
+  #[test]
+  fn missing_proof_does_not_attest() {
+    // Given
+    let mut main = generate_hub();
+    let oracle = oracle_with_gist("alice", "Hello world".to_string());
+    // When
+    let is_verified = verify_github(&mut main, &oracle);
+    // Then
+    assert!(!is_verified);
+    assert!(main.get_attestations("alice.testnet".to_string(), None).is_empty());
+    assert!(!main.get_links("alice.testnet".to_string(), None, None)[0].is_verified);
+  }
+
+  #[test]
+  fn proof_posted_by_someone_else_does_not_attest() {
+    // Given
+    let mut main = generate_hub();
+    let oracle = oracle_with_gist("eve", alice_challenge(&main));
+    // When
+    let is_verified = verify_github(&mut main, &oracle);
+    // Then
+    assert!(!is_verified);
+  }
+
+  #[test]
+  fn changing_link_uri_clears_verified_mark() {
+    // Given
+    let mut main = generate_hub();
+    let oracle = oracle_with_gist("alice", alice_challenge(&main));
+    verify_github(&mut main, &oracle);
+    // When
+    testing_env!(user_context("alice.testnet", None));
+    main.update_link(
+      1,
+      "https://github.com/eve".to_string(),
+      "GitHub".to_string(),
+      "My code".to_string(),
+      None,
+      None,
+      None,
+    );
+    // Then
+    assert!(!main.get_links("alice.testnet".to_string(), None, None)[0].is_verified);
+  }
+
+  #[test]
+  fn replaced_oracle_answer_is_ignored() {
+    // Given
+    let mut main = generate_hub();
+    let oracle = oracle_with_gist("alice", alice_challenge(&main));
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    main.set_verification_oracle(Some("oracle2.testnet".to_string()));
+    // When
+    let is_verified = verify_github(&mut main, &oracle);
+    // Then
+    assert!(!is_verified);
+  }
+
+  #[test]
+  fn dns_record_verifies_website_link() {
+    // Given
+    let mut main = generate_hub();
+    let oracle = oracle_with_domain(&main, DomainProof::Dns);
+    // When
+    let is_verified = verify_domain(&mut main, &oracle, DomainProof::Dns);
+    // Then
+    assert!(is_verified);
+    let website = website(&main);
+    assert!(website.is_verified);
+    assert_eq!(website.verified_domain, Some("alice.dev".to_string()));
+    let attestations = main.get_attestations("alice.testnet".to_string(), None);
+    assert_eq!(attestations[0].evidence_uri, Some("dns:alice.dev?type=TXT".to_string()));
+  }
+
+  #[test]
+  fn well_known_file_verifies_website_link() {
+    // Given
+    let mut main = generate_hub();
+    let oracle = oracle_with_domain(&main, DomainProof::WellKnown);
+    // When
+    let is_verified = verify_domain(&mut main, &oracle, DomainProof::WellKnown);
+    // Then
+    assert!(is_verified);
+    assert_eq!(website(&main).verified_domain, Some("alice.dev".to_string()));
+  }
+
+  #[test]
+  fn proof_published_elsewhere_does_not_verify_domain() {
+    // Given
+    let mut main = generate_hub();
+    let oracle = oracle_with_domain(&main, DomainProof::Dns);
+    // When
+    let is_verified = verify_domain(&mut main, &oracle, DomainProof::WellKnown);
+    // Then
+    assert!(!is_verified);
+    assert!(!website(&main).is_verified);
+  }
+
+  #[test]
+  fn website_link_keeps_domain_on_same_host_only() {
+    // Given
+    let mut main = generate_hub();
+    let oracle = oracle_with_domain(&main, DomainProof::Dns);
+    verify_domain(&mut main, &oracle, DomainProof::Dns);
+    let update_website = |main: &mut MainHub, uri: &str| {
+      testing_env!(user_context("alice.testnet", None));
+      main.update_link(
+        2,
+        uri.to_string(),
+        "Website".to_string(),
+        "About me".to_string(),
+        None,
+        None,
+        None,
+      );
+    };
+    // When
+    update_website(&mut main, "https://www.alice.dev/blog");
+    // Then
+    assert_eq!(website(&main).verified_domain, Some("alice.dev".to_string()));
+    update_website(&mut main, "https://alice.blog");
+    assert!(!website(&main).is_verified);
+    assert_eq!(website(&main).verified_domain, None);
+  }
+
+  #[test]
+  fn link_added_after_domain_proof_gets_the_domain() {
+    // Given
+    let mut main = generate_hub();
+    let oracle = oracle_with_domain(&main, DomainProof::Dns);
+    verify_domain(&mut main, &oracle, DomainProof::Dns);
+    // When
+    testing_env!(user_context("alice.testnet", None));
+    main.add_link(
+      "https://blog.alice.dev".to_string(),
+      "Blog".to_string(),
+      "My posts".to_string(),
+      None,
+      None,
+      None,
+    );
+    // Then
+    let blog = main.get_links("alice.testnet".to_string(), None, None).remove(2);
+    assert!(blog.is_verified);
+    assert_eq!(blog.verified_domain, Some("alice.dev".to_string()));
+  }
+
+  #[test]
+  fn replacing_oracle_drops_its_domain_proofs() {
+    // Given
+    let mut main = generate_hub();
+    let oracle = oracle_with_domain(&main, DomainProof::Dns);
+    verify_domain(&mut main, &oracle, DomainProof::Dns);
+    // When
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    main.set_verification_oracle(Some("oracle2.testnet".to_string()));
+    // Then
+    assert!(!website(&main).is_verified);
+    assert_eq!(website(&main).verified_domain, None);
+    main.set_verification_oracle(Some(ORACLE.to_string()));
+    assert_eq!(website(&main).verified_domain, Some("alice.dev".to_string()));
+  }
+
+  #[test]
+  #[should_panic(expected = "Domains are verified with request_domain_verification")]
+  fn request_verification_of_domain_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.request_verification(
+      AttestationKind::Domain,
+      "alice.dev".to_string(),
+      GIST_URI.to_string(),
+      None,
+    );
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Domain is not valid")]
+  fn request_domain_verification_with_path_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.request_domain_verification("alice.dev/about".to_string(), DomainProof::Dns, None);
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "No verification oracle is set")]
+  fn request_verification_without_oracle_panics() {
+    // Given
+    let mut main = generate_hub();
+    testing_env!(user_context(CONTRACT_ACCOUNT_ID, None));
+    main.set_verification_oracle(None);
+    // When
+    testing_env!(user_context("alice.testnet", None));
+    main.request_verification(
+      AttestationKind::Github,
+      "alice".to_string(),
+      GIST_URI.to_string(),
+      None,
+    );
+    // Then
+    // - Should panic
+  }
+
+  #[test]
+  #[should_panic(expected = "Only the admin can call this method")]
+  fn set_verification_oracle_not_admin_panics() {
+    // Given
+    let mut main = generate_hub();
+    // When
+    main.set_verification_oracle(Some("eve.testnet".to_string()));
+    // Then
+    // - Should panic
+  }
+}